@@ -0,0 +1,207 @@
+use crate::PlayerView;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::collections::VecDeque;
+
+const FLIP_TIME: f32 = 0.25;
+const RIPPLE_DELAY: f32 = 0.04;
+const EXPLOSION_TIME: f32 = 0.6;
+const SWEEP_DELAY: f32 = 0.06;
+
+#[derive(Clone, Copy)]
+struct Animation {
+    start: f32,
+    duration: f32,
+}
+
+impl Animation {
+    fn progress(&self, time: f32) -> f32 {
+        (time - self.start) / self.duration
+    }
+}
+
+pub struct Animator {
+    pub enabled: bool,
+    time: f32,
+    flips: HashMap<(usize, usize), Animation>,
+    explosions: Vec<((usize, usize), Animation)>,
+}
+
+impl Animator {
+    pub fn new() -> Self {
+        Self {
+            enabled: true,
+            time: 0.0,
+            flips: HashMap::new(),
+            explosions: vec![],
+        }
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.time += dt;
+        let time = self.time;
+        self.flips.retain(|_, a| a.progress(time) < 1.0);
+        self.explosions.retain(|(_, a)| a.progress(time) < 1.0);
+    }
+
+    pub fn is_animating(&self) -> bool {
+        !self.flips.is_empty() || !self.explosions.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.flips.clear();
+        self.explosions.clear();
+    }
+
+    // Flood-filled cells flip in order of their BFS distance from the clicked cell
+    pub fn reveal(&mut self, view: &PlayerView, origin: (usize, usize), cells: &[(usize, usize)]) {
+        if !self.enabled {
+            return;
+        }
+        let cells_set: HashSet<_> = cells.iter().copied().collect();
+        let dist = distances(view, origin, |tile| cells_set.contains(&tile));
+        for tile in cells {
+            self.flip(
+                *tile,
+                dist.get(tile).copied().unwrap_or(0) as f32 * RIPPLE_DELAY,
+            );
+        }
+    }
+
+    pub fn explode(&mut self, tile: (usize, usize)) {
+        if !self.enabled {
+            return;
+        }
+        self.explosions.push((
+            tile,
+            Animation {
+                start: self.time,
+                duration: EXPLOSION_TIME,
+            },
+        ));
+    }
+
+    pub fn sweep(&mut self, view: &PlayerView, origin: (usize, usize), cells: &[(usize, usize)]) {
+        if !self.enabled {
+            return;
+        }
+        let dist = distances(view, origin, |_| true);
+        for tile in cells {
+            self.flip(
+                *tile,
+                dist.get(tile).copied().unwrap_or(0) as f32 * SWEEP_DELAY,
+            );
+        }
+    }
+
+    fn flip(&mut self, tile: (usize, usize), delay: f32) {
+        self.flips.insert(
+            tile,
+            Animation {
+                start: self.time + delay,
+                duration: FLIP_TIME,
+            },
+        );
+    }
+
+    // Negative while the flip is still waiting for its turn in a ripple
+    pub fn flip_progress(&self, tile: (usize, usize)) -> Option<f32> {
+        self.flips.get(&tile).map(|a| a.progress(self.time))
+    }
+
+    pub fn explosions(&self) -> Vec<((usize, usize), f32)> {
+        self.explosions
+            .iter()
+            .map(|(tile, a)| (*tile, a.progress(self.time).max(0.0)))
+            .collect()
+    }
+}
+
+impl Default for Animator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn distances<F>(
+    view: &PlayerView,
+    origin: (usize, usize),
+    allowed: F,
+) -> HashMap<(usize, usize), usize>
+where
+    F: Fn((usize, usize)) -> bool,
+{
+    let mut dist = HashMap::new();
+    let mut queue = VecDeque::new();
+    dist.insert(origin, 0);
+    queue.push_back(origin);
+
+    while let Some((x, y)) = queue.pop_front() {
+        let d = dist[&(x, y)];
+        for next in view.neighbours(x, y) {
+            if !dist.contains_key(&next) && allowed(next) {
+                dist.insert(next, d + 1);
+                queue.push_back(next);
+            }
+        }
+    }
+    dist
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Animator;
+    use crate::PlayerView;
+
+    const EPS: f32 = 0.001;
+
+    // TEST reveal
+    #[test]
+    fn reveal_ripple() {
+        let view = PlayerView::new(10, 10, 10);
+        let mut animator = Animator::new();
+        animator.reveal(&view, (4, 4), &[(4, 4), (5, 4), (6, 4)]);
+
+        assert!((animator.flip_progress((4, 4)).unwrap()).abs() <= EPS);
+        assert!(animator.flip_progress((5, 4)).unwrap() < 0.0);
+        assert!(animator.flip_progress((6, 4)).unwrap() < animator.flip_progress((5, 4)).unwrap());
+        assert_eq!(animator.flip_progress((7, 4)), None);
+    }
+
+    #[test]
+    fn reveal_disabled() {
+        let view = PlayerView::new(10, 10, 10);
+        let mut animator = Animator::new();
+        animator.enabled = false;
+        animator.reveal(&view, (4, 4), &[(4, 4)]);
+        animator.explode((4, 4));
+        assert!(!animator.is_animating());
+    }
+
+    // TEST update
+    #[test]
+    fn update_finishes() {
+        let view = PlayerView::new(10, 10, 10);
+        let mut animator = Animator::new();
+        animator.reveal(&view, (4, 4), &[(4, 4), (5, 4)]);
+        animator.explode((5, 4));
+        assert!(animator.is_animating());
+
+        animator.update(0.1);
+        assert!(animator.flip_progress((4, 4)).unwrap() > 0.0);
+        assert!((animator.explosions()[0].1 - 0.1 / 0.6).abs() <= EPS);
+
+        animator.update(1.0);
+        assert!(!animator.is_animating());
+        assert_eq!(animator.flip_progress((4, 4)), None);
+    }
+
+    // TEST sweep
+    #[test]
+    fn sweep_by_distance() {
+        let view = PlayerView::new(10, 10, 10);
+        let mut animator = Animator::new();
+        animator.sweep(&view, (0, 0), &[(9, 9), (1, 0)]);
+        assert!(animator.flip_progress((9, 9)).unwrap() < animator.flip_progress((1, 0)).unwrap());
+    }
+}
@@ -0,0 +1,225 @@
+use crate::AudioSettings;
+use crate::GameScene;
+use crate::GameSetup;
+use crate::Link;
+use crate::MainMenu;
+use crate::Profile;
+use crate::Session;
+use crate::SharedState;
+use crate::SoundBank;
+use crate::Theme;
+use crate::TileBatch;
+use crate::Transition;
+use ggez::filesystem;
+use ggez::Context;
+use ggez::GameResult;
+use std::env;
+use std::path::PathBuf;
+use std::time::Duration;
+use std::time::Instant;
+
+const THEME_RELOAD: Duration = Duration::from_secs(1);
+// Shipped defaults in the resources directory; changes go to the user's config directory
+const AUDIO_SETTINGS: &str = "audio.toml";
+
+// The crate's resources directory when run through cargo
+pub fn manifest_resources() -> Option<PathBuf> {
+    env::var("CARGO_MANIFEST_DIR")
+        .ok()
+        .map(|dir| PathBuf::from(dir).join("resources"))
+}
+
+// Where the resources are read from outside ggez's own filesystem
+fn resources_dir(ctx: &Context) -> PathBuf {
+    manifest_resources().unwrap_or_else(|| filesystem::resources_dir(ctx).to_path_buf())
+}
+
+// Everything the scenes share; the game itself lives in its scene
+pub struct App {
+    pub themes: Vec<Theme>,
+    theme: usize,
+    // Bumped whenever the active theme changes, so cached drawings can be invalidated
+    pub theme_version: usize,
+    last_theme_check: Instant,
+    pub sounds: SoundBank,
+    pub tiles: TileBatch,
+    pub animations: bool,
+    // Hides chat and emotes from other players
+    pub chat_muted: bool,
+    pub setup: GameSetup,
+    pub session: Option<Session>,
+    // Set while hosting or after joining a game on the network
+    pub net: Option<Link>,
+    // Why the last network game ended, shown in the main menu
+    pub net_error: Option<String>,
+    // Address and token of a game this client lost the connection to
+    pub rejoin: Option<(String, String)>,
+    // Name and colour shown to the others in a network lobby
+    pub profile: Profile,
+    audio_path: PathBuf,
+}
+
+impl App {
+    pub fn new(ctx: &mut Context) -> GameResult<Self> {
+        Ok(Self {
+            themes: load_themes(ctx),
+            theme: 0,
+            theme_version: 0,
+            last_theme_check: Instant::now(),
+            sounds: SoundBank::new(ctx, load_audio_settings(ctx)),
+            tiles: TileBatch::new(ctx, 50.0)?,
+            animations: true,
+            chat_muted: false,
+            setup: GameSetup::default(),
+            session: None,
+            net: None,
+            net_error: None,
+            rejoin: None,
+            profile: Profile::new(0),
+            audio_path: filesystem::user_config_dir(ctx).join(AUDIO_SETTINGS),
+        })
+    }
+
+    pub fn theme_index(&self) -> usize {
+        self.theme
+    }
+
+    pub fn theme(&self) -> &Theme {
+        &self.themes[self.theme]
+    }
+
+    pub fn change_theme(&mut self, delta: isize) {
+        let len = self.themes.len() as isize;
+        self.theme = (self.theme as isize + delta).rem_euclid(len) as usize;
+        self.theme_version += 1;
+    }
+
+    pub fn toggle_mute(&mut self) {
+        self.sounds.settings.muted ^= true;
+        self.save_audio();
+    }
+
+    pub fn change_volume(&mut self, steps: f32) {
+        self.sounds.settings.change_volume(steps);
+        self.save_audio();
+    }
+
+    fn save_audio(&self) {
+        let saved = self
+            .audio_path
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .map_err(Into::into)
+            .and_then(|_| self.sounds.settings.save(&self.audio_path));
+        if let Err(e) = saved {
+            eprintln!("Failed to save audio settings: {}", e);
+        }
+    }
+}
+
+impl App {
+    fn reload_theme(&mut self, ctx: &mut Context) {
+        if self.last_theme_check.elapsed() < THEME_RELOAD {
+            return;
+        }
+        self.last_theme_check = Instant::now();
+        let theme = &mut self.themes[self.theme];
+        match theme.reload_if_changed() {
+            Ok(true) => {
+                if let Err(e) = theme.load_fonts(ctx) {
+                    eprintln!("Failed to load theme fonts: {}", e);
+                }
+                self.theme_version += 1;
+            }
+            Ok(false) => {}
+            Err(e) => eprintln!("Failed to reload theme: {}", e),
+        }
+    }
+
+    // Keeps the lobby going and starts the games the host starts
+    fn update_net(&mut self, ctx: &mut Context) -> Transition<Self> {
+        let client = match &mut self.net {
+            // Runs every frame, so remote players keep playing while a menu covers the game
+            Some(Link::Host(host)) => {
+                host.poll();
+                host.set_profile(self.profile.clone());
+                host.set_settings(self.setup);
+                return Transition::None;
+            }
+            Some(Link::Client(client)) => client,
+            None => return Transition::None,
+        };
+        if client.is_closed() {
+            // Reconnecting could block the frame, so the join menu offers to rejoin instead
+            if let (Some(token), false) = (client.token.take(), client.watching) {
+                self.rejoin = Some((client.addr.clone(), token));
+            }
+            self.net_error = Some(
+                client
+                    .error
+                    .take()
+                    .unwrap_or_else(|| "Lost the connection to the host".to_string()),
+            );
+            self.net = None;
+            return Transition::Reset(vec![Box::new(MainMenu::new())]);
+        }
+        let (setup, order) = match client.poll() {
+            Some(start) => start,
+            None => return Transition::None,
+        };
+        // Rounds of the same match keep their standings
+        if self.session.as_ref().map(|s| s.setup) != Some(setup) {
+            self.session = Some(Session::new(setup));
+        }
+        let game = client.mirror(&setup, &order);
+        if let Some(session) = &mut self.session {
+            session.order = order;
+            session.profiles = client.profiles.clone();
+        }
+        self.setup = setup;
+        match GameScene::new(ctx, self, game) {
+            Ok(scene) => Transition::Reset(vec![Box::new(MainMenu::new()), Box::new(scene)]),
+            Err(e) => {
+                eprintln!("Failed to start game: {}", e);
+                Transition::None
+            }
+        }
+    }
+}
+
+impl SharedState for App {
+    fn update(&mut self, ctx: &mut Context) -> Transition<Self> {
+        self.reload_theme(ctx);
+        self.update_net(ctx)
+    }
+}
+
+// The user's saved settings, or else the shipped ones
+fn load_audio_settings(ctx: &Context) -> AudioSettings {
+    let user = filesystem::user_config_dir(ctx).join(AUDIO_SETTINGS);
+    let shipped = resources_dir(ctx).join(AUDIO_SETTINGS);
+    let path = match [user, shipped].into_iter().find(|p| p.exists()) {
+        Some(path) => path,
+        None => return AudioSettings::default(),
+    };
+    AudioSettings::load(&path).unwrap_or_else(|e| {
+        eprintln!("Failed to load audio settings: {}", e);
+        AudioSettings::default()
+    })
+}
+
+fn load_themes(ctx: &mut Context) -> Vec<Theme> {
+    let mut themes = Theme::presets();
+    for theme in Theme::load_dir(resources_dir(ctx).join("themes")) {
+        match theme {
+            Ok(theme) => themes.push(theme),
+            Err(e) => eprintln!("Failed to load theme: {}", e),
+        }
+    }
+    for theme in &mut themes {
+        if let Err(e) = theme.load_fonts(ctx) {
+            eprintln!("Failed to load fonts for theme {}: {}", theme.name, e);
+        }
+    }
+    themes
+}
@@ -0,0 +1,259 @@
+use crate::GameEvent;
+use ggez::audio;
+use ggez::audio::SoundSource;
+use ggez::filesystem;
+use ggez::Context;
+use ggez::GameError;
+use ggez::GameResult;
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+const VOLUME_STEP: f32 = 0.1;
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum Sound {
+    Reveal,
+    Flag,
+    Chord,
+    Explosion,
+    TurnChange,
+    Timeout,
+    Victory,
+}
+
+impl Sound {
+    pub const ALL: [Sound; 7] = [
+        Sound::Reveal,
+        Sound::Flag,
+        Sound::Chord,
+        Sound::Explosion,
+        Sound::TurnChange,
+        Sound::Timeout,
+        Sound::Victory,
+    ];
+
+    pub fn for_event(event: &GameEvent) -> Option<Self> {
+        match event {
+            GameEvent::Revealed { .. } => Some(Sound::Reveal),
+            GameEvent::Chorded { .. } => Some(Sound::Chord),
+            GameEvent::Flagged { .. } => Some(Sound::Flag),
+            GameEvent::MineHit { .. } => Some(Sound::Explosion),
+            GameEvent::Eliminated { .. } | GameEvent::TeamEliminated { .. } => None,
+            GameEvent::ShieldEarned { .. } | GameEvent::ShieldUsed { .. } => None,
+            GameEvent::TurnChanged { .. } => Some(Sound::TurnChange),
+            GameEvent::Timeout { .. } => Some(Sound::Timeout),
+            GameEvent::GameOver { cleared: true } => Some(Sound::Victory),
+            GameEvent::GameOver { cleared: false } => None,
+        }
+    }
+
+    // Each distinct sound of an action plays once, however many events caused it
+    pub fn for_events(events: &[GameEvent]) -> Vec<Self> {
+        let mut sounds: Vec<_> = events.iter().filter_map(Self::for_event).collect();
+        sounds.sort();
+        sounds.dedup();
+        sounds
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Sound::Reveal => "reveal",
+            Sound::Flag => "flag",
+            Sound::Chord => "chord",
+            Sound::Explosion => "explosion",
+            Sound::TurnChange => "turn_change",
+            Sound::Timeout => "timeout",
+            Sound::Victory => "victory",
+        }
+    }
+
+    fn file(self) -> String {
+        format!("/sounds/{}.ogg", self.name())
+    }
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AudioSettings {
+    pub muted: bool,
+    pub volume: f32,
+    // Paths are relative to the resources directory; an empty path disables the sound
+    pub sounds: BTreeMap<String, String>,
+}
+
+impl AudioSettings {
+    pub fn from_toml(src: &str) -> GameResult<Self> {
+        let mut settings: Self =
+            toml::from_str(src).map_err(|e| GameError::ConfigError(e.to_string()))?;
+        for sound in Sound::ALL {
+            settings
+                .sounds
+                .entry(sound.name().to_string())
+                .or_insert_with(|| sound.file());
+        }
+        Ok(settings)
+    }
+
+    pub fn to_toml(&self) -> GameResult<String> {
+        toml::to_string(self).map_err(|e| GameError::ConfigError(e.to_string()))
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> GameResult<Self> {
+        Self::from_toml(&fs::read_to_string(path)?)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> GameResult {
+        fs::write(path, self.to_toml()?)?;
+        Ok(())
+    }
+
+    pub fn change_volume(&mut self, steps: f32) {
+        self.volume = (self.volume + steps * VOLUME_STEP).clamp(0.0, 1.0);
+    }
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        Self {
+            muted: false,
+            volume: 0.8,
+            sounds: Sound::ALL
+                .iter()
+                .map(|s| (s.name().to_string(), s.file()))
+                .collect(),
+        }
+    }
+}
+
+pub struct SoundBank {
+    pub settings: AudioSettings,
+    sources: HashMap<Sound, audio::Source>,
+}
+
+impl SoundBank {
+    // Missing sound files are skipped so the game runs without any audio assets
+    pub fn new(ctx: &mut Context, settings: AudioSettings) -> Self {
+        let mut sources = HashMap::new();
+        for sound in Sound::ALL {
+            let path = match settings.sounds.get(sound.name()) {
+                Some(path) if !path.is_empty() && filesystem::exists(ctx, path) => path,
+                _ => continue,
+            };
+            match audio::Source::new(ctx, path) {
+                Ok(source) => {
+                    sources.insert(sound, source);
+                }
+                Err(e) => eprintln!("Failed to load sound {}: {}", path, e),
+            }
+        }
+        Self { settings, sources }
+    }
+
+    pub fn play(&mut self, ctx: &mut Context, events: &[GameEvent]) {
+        if self.settings.muted {
+            return;
+        }
+        for sound in Sound::for_events(events) {
+            if let Some(source) = self.sources.get_mut(&sound) {
+                source.set_volume(self.settings.volume);
+                if let Err(e) = source.play_detached(ctx) {
+                    eprintln!("Failed to play sound: {}", e);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::AudioSettings;
+    use crate::GameEvent;
+    use crate::Sound;
+
+    // TEST for_event
+    #[test]
+    fn for_event_all() {
+        assert_eq!(
+            Sound::for_event(&GameEvent::Flagged {
+                tile: (0, 0),
+                marked: true
+            }),
+            Some(Sound::Flag)
+        );
+        assert_eq!(
+            Sound::for_event(&GameEvent::GameOver { cleared: true }),
+            Some(Sound::Victory)
+        );
+        assert_eq!(
+            Sound::for_event(&GameEvent::GameOver { cleared: false }),
+            None
+        );
+    }
+
+    // TEST for_events
+    #[test]
+    fn for_events_distinct() {
+        let events = [
+            GameEvent::Chorded { origin: (1, 1) },
+            GameEvent::Revealed {
+                origin: (1, 1),
+                cells: vec![(0, 0)],
+            },
+            GameEvent::MineHit {
+                tile: (0, 0),
+                player: 0,
+            },
+            GameEvent::TurnChanged { player: 1 },
+        ];
+        assert_eq!(
+            Sound::for_events(&events),
+            vec![
+                Sound::Reveal,
+                Sound::Chord,
+                Sound::Explosion,
+                Sound::TurnChange
+            ]
+        );
+        assert_eq!(
+            Sound::for_events(&[events[1].clone(), events[1].clone()]),
+            vec![Sound::Reveal]
+        );
+        assert!(Sound::for_events(&[]).is_empty());
+    }
+
+    // TEST from_toml
+    #[test]
+    fn from_toml_partial() {
+        let settings = AudioSettings::from_toml(
+            "muted = true\n\
+             [sounds]\n\
+             flag = \"/sounds/pop.ogg\"\n",
+        )
+        .unwrap();
+        assert!(settings.muted);
+        assert_eq!(settings.volume, AudioSettings::default().volume);
+        assert_eq!(settings.sounds["flag"], "/sounds/pop.ogg");
+        assert_eq!(settings.sounds["reveal"], "/sounds/reveal.ogg");
+    }
+
+    #[test]
+    fn to_toml_round_trip() {
+        let settings = AudioSettings::default();
+        let parsed = AudioSettings::from_toml(&settings.to_toml().unwrap()).unwrap();
+        assert_eq!(parsed, settings);
+    }
+
+    // TEST change_volume
+    #[test]
+    fn change_volume_clamped() {
+        let mut settings = AudioSettings::default();
+        settings.change_volume(5.0);
+        assert_eq!(settings.volume, 1.0);
+        settings.change_volume(-20.0);
+        assert_eq!(settings.volume, 0.0);
+    }
+}
@@ -0,0 +1,58 @@
+use hex_sweeper::Server;
+use hex_sweeper::ServerConfig;
+use hex_sweeper::DEFAULT_PORT;
+use std::env;
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration;
+
+const USAGE: &str =
+    "Usage: hexsweeper-server [--port N] [--turn-time SECONDS] [--logs DIR] [--caster-key KEY]";
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+fn parse_args(args: &[String]) -> Result<(u16, ServerConfig), String> {
+    let mut port = DEFAULT_PORT;
+    let mut config = ServerConfig::default();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let value = iter
+            .next()
+            .ok_or_else(|| format!("{} needs a value", arg))?;
+        let invalid = |_| format!("{} is not a number: {}", arg, value);
+        match arg.as_str() {
+            "--port" => port = value.parse().map_err(invalid)?,
+            "--turn-time" => {
+                let secs: u64 = value.parse().map_err(invalid)?;
+                config.turn_time = Some(Duration::from_secs(secs.max(1)));
+            }
+            "--logs" => config.log_dir = Some(PathBuf::from(value)),
+            "--caster-key" => config.caster_key = Some(value.clone()),
+            _ => return Err(format!("unknown option: {}", arg)),
+        }
+    }
+    Ok((port, config))
+}
+
+pub fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let (port, config) = match parse_args(&args) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}", e);
+            eprintln!("{}", USAGE);
+            std::process::exit(2);
+        }
+    };
+    let mut server = match Server::bind(("0.0.0.0", port), config) {
+        Ok(server) => server,
+        Err(e) => {
+            eprintln!("Failed to listen on port {}: {}", port, e);
+            std::process::exit(1);
+        }
+    };
+    println!("Listening on port {}", port);
+    loop {
+        server.poll();
+        thread::sleep(POLL_INTERVAL);
+    }
+}
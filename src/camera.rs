@@ -0,0 +1,154 @@
+use glam::Vec2;
+
+const MIN_ZOOM: f32 = 0.1;
+const MAX_ZOOM: f32 = 4.0;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum CameraMode {
+    Free,
+    Fit,
+    Follow,
+}
+
+impl CameraMode {
+    pub fn next(self) -> Self {
+        match self {
+            CameraMode::Free => CameraMode::Fit,
+            CameraMode::Fit => CameraMode::Follow,
+            CameraMode::Follow => CameraMode::Free,
+        }
+    }
+
+    pub fn prev(self) -> Self {
+        self.next().next()
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct Camera {
+    pub offset: Vec2,
+    pub zoom: f32,
+}
+
+impl Camera {
+    pub fn new() -> Self {
+        Self {
+            offset: Vec2::new(0.0, 0.0),
+            zoom: 1.0,
+        }
+    }
+
+    pub fn fit(min: Vec2, max: Vec2, screen: Vec2) -> Self {
+        let zoom = (screen / (max - min))
+            .min_element()
+            .clamp(MIN_ZOOM, MAX_ZOOM);
+        Self::centered((min + max) / 2.0, zoom, screen)
+    }
+
+    pub fn centered(p: Vec2, zoom: f32, screen: Vec2) -> Self {
+        Self {
+            offset: screen / 2.0 - p * zoom,
+            zoom,
+        }
+    }
+
+    pub fn to_world(&self, p: Vec2) -> Vec2 {
+        (p - self.offset) / self.zoom
+    }
+
+    pub fn to_screen(&self, p: Vec2) -> Vec2 {
+        p * self.zoom + self.offset
+    }
+
+    pub fn pan(&mut self, delta: Vec2) {
+        self.offset += delta;
+    }
+
+    pub fn zoom_at(&mut self, p: Vec2, factor: f32) {
+        let world = self.to_world(p);
+        self.zoom = (self.zoom * factor).clamp(MIN_ZOOM, MAX_ZOOM);
+        self.offset = p - world * self.zoom;
+    }
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Camera;
+    use crate::CameraMode;
+    use glam::Vec2;
+
+    const EPS: f32 = 0.001;
+
+    // TEST to_world
+    #[test]
+    fn to_world_identity() {
+        let camera = Camera::new();
+        assert!((camera.to_world(Vec2::new(42.0, 33.0)) - Vec2::new(42.0, 33.0)).length() <= EPS);
+    }
+
+    #[test]
+    fn to_world_inverse() {
+        let mut camera = Camera::new();
+        camera.pan(Vec2::new(10.0, -20.0));
+        camera.zoom_at(Vec2::new(100.0, 100.0), 2.0);
+        let p = Vec2::new(42.0, 33.0);
+        assert!((camera.to_world(camera.to_screen(p)) - p).length() <= EPS);
+    }
+
+    // TEST fit
+    #[test]
+    fn fit0() {
+        let camera = Camera::fit(
+            Vec2::new(0.0, 0.0),
+            Vec2::new(100.0, 50.0),
+            Vec2::new(200.0, 200.0),
+        );
+        assert!((camera.zoom - 2.0).abs() <= EPS);
+        assert!(
+            (camera.to_screen(Vec2::new(50.0, 25.0)) - Vec2::new(100.0, 100.0)).length() <= EPS
+        );
+    }
+
+    // TEST centered
+    #[test]
+    fn centered0() {
+        let camera = Camera::centered(Vec2::new(42.0, 33.0), 2.0, Vec2::new(600.0, 400.0));
+        assert!(
+            (camera.to_screen(Vec2::new(42.0, 33.0)) - Vec2::new(300.0, 200.0)).length() <= EPS
+        );
+    }
+
+    // TEST CameraMode
+    #[test]
+    fn camera_mode_cycle() {
+        assert_eq!(CameraMode::Free.next(), CameraMode::Fit);
+        assert_eq!(CameraMode::Free.prev(), CameraMode::Follow);
+        assert_eq!(CameraMode::Fit.next().next().next(), CameraMode::Fit);
+    }
+
+    // TEST zoom_at
+    #[test]
+    fn zoom_at_keeps_point() {
+        let mut camera = Camera::new();
+        let p = Vec2::new(200.0, 160.0);
+        let before = camera.to_world(p);
+        camera.zoom_at(p, 1.5);
+        assert!((camera.to_world(p) - before).length() <= EPS);
+        assert!((camera.zoom - 1.5).abs() <= EPS);
+    }
+
+    #[test]
+    fn zoom_at_clamped() {
+        let mut camera = Camera::new();
+        camera.zoom_at(Vec2::new(0.0, 0.0), 100.0);
+        assert!((camera.zoom - 4.0).abs() <= EPS);
+        camera.zoom_at(Vec2::new(0.0, 0.0), 0.0001);
+        assert!((camera.zoom - 0.1).abs() <= EPS);
+    }
+}
@@ -0,0 +1,183 @@
+use serde::Deserialize;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::time::Duration;
+use std::time::Instant;
+
+pub const MAX_CHAT: usize = 120;
+// Lines kept until the game scene picks them up
+const CHAT_HISTORY: usize = 50;
+// Each player may send this many lines in any window of CHAT_WINDOW
+const CHAT_BURST: usize = 5;
+const CHAT_WINDOW: Duration = Duration::from_secs(10);
+
+// Quick messages sent with a single key
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Emote {
+    Hello,
+    GoodLuck,
+    NiceMove,
+    Oops,
+    Thanks,
+    GoodGame,
+}
+
+impl Emote {
+    pub fn text(self) -> &'static str {
+        match self {
+            Emote::Hello => "Hello!",
+            Emote::GoodLuck => "Good luck!",
+            Emote::NiceMove => "Nice move!",
+            Emote::Oops => "Oops!",
+            Emote::Thanks => "Thanks!",
+            Emote::GoodGame => "Good game!",
+        }
+    }
+}
+
+// Typed text or an emote
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Said {
+    Text(String),
+    Emote(Emote),
+}
+
+impl Said {
+    // Text is trimmed, stripped of control characters and shortened; None if nothing is left
+    pub fn cleaned(self) -> Option<Self> {
+        match self {
+            Said::Text(text) => {
+                let text: String = text
+                    .trim()
+                    .chars()
+                    .filter(|c| !c.is_control())
+                    .take(MAX_CHAT)
+                    .collect();
+                (!text.is_empty()).then_some(Said::Text(text))
+            }
+            emote => Some(emote),
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        match self {
+            Said::Text(text) => text,
+            Said::Emote(emote) => emote.text(),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct ChatLine {
+    pub slot: usize,
+    pub said: Said,
+}
+
+// Lines received but not shown yet; the oldest are dropped when nobody picks them up
+#[derive(Default)]
+pub struct ChatLog {
+    lines: VecDeque<ChatLine>,
+}
+
+impl ChatLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, line: ChatLine) {
+        if self.lines.len() == CHAT_HISTORY {
+            self.lines.pop_front();
+        }
+        self.lines.push_back(line);
+    }
+
+    pub fn take(&mut self) -> Vec<ChatLine> {
+        self.lines.drain(..).collect()
+    }
+}
+
+// Counts the lines sent in the last CHAT_WINDOW
+#[derive(Default)]
+pub struct RateLimit {
+    sent: VecDeque<Instant>,
+}
+
+impl RateLimit {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Whether another line may be sent now; allowed lines are counted
+    pub fn allow(&mut self, now: Instant) -> bool {
+        while self
+            .sent
+            .front()
+            .is_some_and(|t| now.duration_since(*t) >= CHAT_WINDOW)
+        {
+            self.sent.pop_front();
+        }
+        if self.sent.len() >= CHAT_BURST {
+            return false;
+        }
+        self.sent.push_back(now);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chat::ChatLog;
+    use crate::chat::RateLimit;
+    use crate::chat::CHAT_BURST;
+    use crate::chat::CHAT_HISTORY;
+    use crate::chat::CHAT_WINDOW;
+    use crate::ChatLine;
+    use crate::Emote;
+    use crate::Said;
+    use std::time::Duration;
+    use std::time::Instant;
+
+    // TEST cleaned
+    #[test]
+    fn cleaned_text() {
+        let said = Said::Text("  hi\tthere\n ".to_string());
+        assert_eq!(said.cleaned(), Some(Said::Text("hithere".to_string())));
+        assert_eq!(Said::Text(" \n ".to_string()).cleaned(), None);
+        let long = Said::Text("a".repeat(500)).cleaned().unwrap();
+        assert_eq!(long.text().len(), 120);
+        assert_eq!(
+            Said::Emote(Emote::Oops).cleaned(),
+            Some(Said::Emote(Emote::Oops))
+        );
+    }
+
+    // TEST push
+    #[test]
+    fn push_drops_oldest() {
+        let mut log = ChatLog::new();
+        for slot in 0..CHAT_HISTORY + 2 {
+            log.push(ChatLine {
+                slot,
+                said: Said::Emote(Emote::Hello),
+            });
+        }
+        let lines = log.take();
+        assert_eq!(lines.len(), CHAT_HISTORY);
+        assert_eq!(lines[0].slot, 2);
+        assert!(log.take().is_empty());
+    }
+
+    // TEST allow
+    #[test]
+    fn allow_burst() {
+        let mut limit = RateLimit::new();
+        let start = Instant::now();
+        for _ in 0..CHAT_BURST {
+            assert!(limit.allow(start));
+        }
+        assert!(!limit.allow(start + Duration::from_secs(1)));
+        assert!(limit.allow(start + CHAT_WINDOW));
+    }
+}
@@ -0,0 +1,67 @@
+use serde::Deserialize;
+use serde::Serialize;
+
+// A player colour; kept apart from ggez so the server can be built without it
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Rgba {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+    pub a: f32,
+}
+
+impl Rgba {
+    pub const GREEN: Rgba = Rgba::new(0.0, 1.0, 0.0, 1.0);
+    pub const BLUE: Rgba = Rgba::new(0.0, 0.0, 1.0, 1.0);
+    pub const YELLOW: Rgba = Rgba::new(1.0, 1.0, 0.0, 1.0);
+    pub const CYAN: Rgba = Rgba::new(0.0, 1.0, 1.0, 1.0);
+    pub const MAGENTA: Rgba = Rgba::new(1.0, 0.0, 1.0, 1.0);
+    pub const ORANGE: Rgba = Rgba::new(1.0, 0.55, 0.0, 1.0);
+    pub const PURPLE: Rgba = Rgba::new(0.55, 0.3, 1.0, 1.0);
+    pub const PINK: Rgba = Rgba::new(1.0, 0.6, 0.75, 1.0);
+
+    pub const fn new(r: f32, g: f32, b: f32, a: f32) -> Self {
+        Self { r, g, b, a }
+    }
+
+    // Alternately darker and lighter, further from the base colour for later members
+    pub fn shade(self, member: usize) -> Self {
+        if member == 0 {
+            return self;
+        }
+        let amount = 0.35 * member.div_ceil(2) as f32;
+        let target = if member.is_multiple_of(2) { 1.0 } else { 0.0 };
+        let mix = |c: f32| c + (target - c) * amount.min(0.8);
+        Self::new(mix(self.r), mix(self.g), mix(self.b), self.a)
+    }
+}
+
+#[cfg(feature = "gui")]
+impl From<Rgba> for ggez::graphics::Color {
+    fn from(c: Rgba) -> Self {
+        Self::new(c.r, c.g, c.b, c.a)
+    }
+}
+
+#[cfg(feature = "gui")]
+impl From<ggez::graphics::Color> for Rgba {
+    fn from(c: ggez::graphics::Color) -> Self {
+        Self::new(c.r, c.g, c.b, c.a)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Rgba;
+
+    // TEST shade
+    #[test]
+    fn shade_family() {
+        let base = Rgba::new(0.5, 0.5, 0.5, 1.0);
+        assert_eq!(base.shade(0), base);
+        assert!(base.shade(1).r < base.r);
+        assert!(base.shade(2).r > base.r);
+        assert!(base.shade(3).r < base.shade(1).r);
+        assert_eq!(base.shade(1).a, 1.0);
+    }
+}
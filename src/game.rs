@@ -0,0 +1,1001 @@
+use crate::ClickResult;
+use crate::HexGrid;
+use crate::Player;
+use serde::Deserialize;
+use serde::Serialize;
+use std::rc::Rc;
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum GameEvent {
+    Revealed {
+        origin: (usize, usize),
+        cells: Vec<(usize, usize)>,
+    },
+    Chorded {
+        origin: (usize, usize),
+    },
+    Flagged {
+        tile: (usize, usize),
+        marked: bool,
+    },
+    MineHit {
+        tile: (usize, usize),
+        player: usize,
+    },
+    Eliminated {
+        player: usize,
+    },
+    TeamEliminated {
+        team: usize,
+    },
+    ShieldEarned {
+        player: usize,
+    },
+    ShieldUsed {
+        player: usize,
+    },
+    TurnChanged {
+        player: usize,
+    },
+    Timeout {
+        player: usize,
+    },
+    GameOver {
+        cleared: bool,
+    },
+}
+
+// What hitting a mine costs a player
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum LifeRule {
+    // Eliminated on the last of N hits, one life is instant death
+    Lives(usize),
+    // Each hit costs N points and nobody is eliminated
+    Penalty(usize),
+    // Opening at least N cells at once earns a shield that absorbs one hit
+    Shield(usize),
+}
+
+impl LifeRule {
+    // Every kind of rule with its default amount
+    pub const KINDS: [LifeRule; 3] = [
+        LifeRule::Lives(1),
+        LifeRule::Penalty(5),
+        LifeRule::Shield(10),
+    ];
+
+    pub fn amount(self) -> usize {
+        match self {
+            LifeRule::Lives(n) | LifeRule::Penalty(n) | LifeRule::Shield(n) => n,
+        }
+    }
+
+    pub fn amount_range(self) -> (usize, usize) {
+        match self {
+            LifeRule::Lives(_) => (1, 9),
+            LifeRule::Penalty(_) => (1, 20),
+            LifeRule::Shield(_) => (2, 50),
+        }
+    }
+
+    // Same kind of rule, amount clamped to its range
+    pub fn with_amount(self, amount: usize) -> Self {
+        let (min, max) = self.amount_range();
+        let amount = amount.clamp(min, max);
+        match self {
+            LifeRule::Lives(_) => LifeRule::Lives(amount),
+            LifeRule::Penalty(_) => LifeRule::Penalty(amount),
+            LifeRule::Shield(_) => LifeRule::Shield(amount),
+        }
+    }
+
+    pub fn kind_index(self) -> usize {
+        match self {
+            LifeRule::Lives(_) => 0,
+            LifeRule::Penalty(_) => 1,
+            LifeRule::Shield(_) => 2,
+        }
+    }
+}
+
+impl Default for LifeRule {
+    fn default() -> Self {
+        LifeRule::Lives(1)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub enum TurnPolicy {
+    // Every reveal or chord ends the turn
+    #[default]
+    OnePerAction,
+    // Like OnePerAction, but opening a zero region gives another turn
+    ExtraOnOpening,
+    // No turns, every player acts whenever they want
+    Simultaneous,
+}
+
+impl TurnPolicy {
+    pub const ALL: [TurnPolicy; 3] = [
+        TurnPolicy::OnePerAction,
+        TurnPolicy::ExtraOnOpening,
+        TurnPolicy::Simultaneous,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            TurnPolicy::OnePerAction => "one",
+            TurnPolicy::ExtraOnOpening => "opening",
+            TurnPolicy::Simultaneous => "simultaneous",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|p| p.name() == name)
+    }
+
+    pub fn index(self) -> usize {
+        Self::ALL.iter().position(|p| *p == self).unwrap_or(0)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Death {
+    // None if the player left the game
+    pub tile: Option<(usize, usize)>,
+    pub player: usize,
+    pub score: usize,
+}
+
+pub struct Game {
+    pub grid: HexGrid,
+    pub players: Vec<Rc<Player>>,
+    pub players_alive: usize,
+    pub curr_player: usize,
+    // In order of elimination
+    pub deaths: Vec<Death>,
+    pub rule: LifeRule,
+    pub policy: TurnPolicy,
+    // Mines hit and shields held, indexed by player slot
+    pub hits: Vec<usize>,
+    pub shields: Vec<usize>,
+    // Player slots in the starting turn order
+    order: Vec<usize>,
+    // Slot of the member who last had the turn, indexed by team
+    last_member: Vec<Option<usize>>,
+}
+
+impl Game {
+    pub fn new(grid: HexGrid, players: Vec<Rc<Player>>) -> Self {
+        let count = players.len();
+        let teams = players.iter().map(|p| p.team + 1).max().unwrap_or(0);
+        let mut last_member = vec![None; teams];
+        if let Some(first) = players.first() {
+            last_member[first.team] = Some(first.slot);
+        }
+        Self {
+            grid,
+            players_alive: players.len(),
+            order: players.iter().map(|p| p.slot).collect(),
+            players,
+            curr_player: 0,
+            deaths: vec![],
+            rule: LifeRule::default(),
+            policy: TurnPolicy::default(),
+            hits: vec![0; count],
+            shields: vec![0; count],
+            last_member,
+        }
+    }
+
+    fn player(&self, slot: usize) -> Option<&Rc<Player>> {
+        self.players.iter().find(|p| p.slot == slot)
+    }
+
+    fn is_alive(&self, slot: usize) -> bool {
+        self.players[..self.players_alive]
+            .iter()
+            .any(|p| p.slot == slot)
+    }
+
+    // Team ids in turn order
+    pub fn teams(&self) -> Vec<usize> {
+        let mut teams = vec![];
+        for slot in &self.order {
+            if let Some(player) = self.player(*slot) {
+                if !teams.contains(&player.team) {
+                    teams.push(player.team);
+                }
+            }
+        }
+        teams
+    }
+
+    pub fn is_team_game(&self) -> bool {
+        self.teams().len() < self.players.len()
+    }
+
+    pub fn team_score(&self, team: usize) -> usize {
+        (0..self.players.len())
+            .filter(|i| self.players[*i].team == team)
+            .map(|i| self.score(i))
+            .sum()
+    }
+
+    pub fn team_ranking(&self) -> Vec<usize> {
+        let mut teams = self.teams();
+        teams.sort_by_key(|t| std::cmp::Reverse(self.team_score(*t)));
+        teams
+    }
+
+    // Team with the best combined score, None on a tie
+    pub fn winning_team(&self) -> Option<usize> {
+        match self.team_ranking().as_slice() {
+            [first, second, ..] if self.team_score(*first) == self.team_score(*second) => None,
+            [first, ..] => Some(*first),
+            [] => None,
+        }
+    }
+
+    // Turns alternate between teams and rotate within each team; without teams
+    // this is the starting order, skipping eliminated players
+    fn next_player(&self, slot: usize) -> Option<usize> {
+        let teams = self.teams();
+        let team = self.player(slot)?.team;
+        let start = teams.iter().position(|t| *t == team)?;
+        (1..=teams.len())
+            .map(|k| teams[(start + k) % teams.len()])
+            .find_map(|t| self.next_member(t))
+    }
+
+    fn next_member(&self, team: usize) -> Option<usize> {
+        let members: Vec<_> = self
+            .order
+            .iter()
+            .copied()
+            .filter(|s| self.player(*s).is_some_and(|p| p.team == team))
+            .collect();
+        let start = self.last_member[team]
+            .and_then(|last| members.iter().position(|s| *s == last))
+            .map_or(0, |i| i + 1);
+        (0..members.len())
+            .map(|k| members[(start + k) % members.len()])
+            .find(|s| self.is_alive(*s))
+    }
+
+    fn set_current(&mut self, slot: usize) {
+        if let Some(i) = self.players[..self.players_alive]
+            .iter()
+            .position(|p| p.slot == slot)
+        {
+            self.curr_player = i;
+            self.last_member[self.players[i].team] = Some(slot);
+        }
+    }
+
+    // Player indices in order of score, best first
+    pub fn ranking(&self) -> Vec<usize> {
+        let mut order: Vec<_> = (0..self.players.len()).collect();
+        order.sort_by_key(|i| std::cmp::Reverse(self.score(*i)));
+        order
+    }
+
+    // Slot of the player with the best score, None on a tie
+    pub fn winner(&self) -> Option<usize> {
+        match self.ranking().as_slice() {
+            [first, second, ..] if self.score(*first) == self.score(*second) => None,
+            [first, ..] => Some(self.players[*first].slot),
+            [] => None,
+        }
+    }
+
+    pub fn death(&self, slot: usize) -> Option<&Death> {
+        self.deaths.iter().find(|d| d.player == slot)
+    }
+
+    pub fn score(&self, i: usize) -> usize {
+        let revealed = self.revealed_by(i);
+        match self.rule {
+            LifeRule::Penalty(points) => {
+                revealed.saturating_sub(points * self.hits[self.players[i].slot])
+            }
+            _ => revealed,
+        }
+    }
+
+    fn revealed_by(&self, i: usize) -> usize {
+        Rc::strong_count(&self.players[i]) - 1
+    }
+
+    pub fn cnt_revealed(&self) -> usize {
+        (0..self.players.len()).map(|i| self.revealed_by(i)).sum()
+    }
+
+    pub fn lives_left(&self, slot: usize) -> Option<usize> {
+        match self.rule {
+            LifeRule::Lives(lives) => Some(lives.saturating_sub(self.hits[slot])),
+            _ => None,
+        }
+    }
+
+    // Short summary of a player's team score and lives, hits or shields for the player panel
+    pub fn status(&self, slot: usize) -> Option<String> {
+        let team = self
+            .player(slot)
+            .filter(|_| self.is_team_game())
+            .map(|p| format!("Team {}: {}", p.team + 1, self.team_score(p.team)));
+        let parts: Vec<_> = team.into_iter().chain(self.rule_status(slot)).collect();
+        if parts.is_empty() {
+            None
+        } else {
+            Some(parts.join(", "))
+        }
+    }
+
+    fn rule_status(&self, slot: usize) -> Option<String> {
+        match self.rule {
+            LifeRule::Lives(1) => None,
+            LifeRule::Lives(_) => match self.lives_left(slot)? {
+                1 => Some("1 life left".to_string()),
+                n => Some(format!("{} lives left", n)),
+            },
+            LifeRule::Penalty(points) => match self.hits[slot] {
+                0 => None,
+                n => Some(format!("-{} for {} hit", points * n, n)),
+            },
+            LifeRule::Shield(_) => match self.shields[slot] {
+                1 => Some("1 shield".to_string()),
+                n => Some(format!("{} shields", n)),
+            },
+        }
+    }
+
+    pub fn is_cleared(&self) -> bool {
+        self.cnt_revealed() >= self.grid.tile_number() - self.grid.mine_number()
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.players_alive > 0 && !self.is_cleared()
+    }
+
+    pub fn current(&self) -> Option<&Rc<Player>> {
+        if self.players_alive > 0 {
+            self.players.get(self.curr_player)
+        } else {
+            None
+        }
+    }
+
+    // Index of the player with the given slot, if they may act now
+    fn actor(&self, slot: usize) -> Option<usize> {
+        if !self.is_running() {
+            return None;
+        }
+        let i = self.players[..self.players_alive]
+            .iter()
+            .position(|p| p.slot == slot)?;
+        if self.policy == TurnPolicy::Simultaneous || i == self.curr_player {
+            Some(i)
+        } else {
+            None
+        }
+    }
+
+    // Whether the player may reveal or chord right now
+    pub fn can_act(&self, slot: usize) -> bool {
+        self.actor(slot).is_some()
+    }
+
+    // Acts for the current player
+    pub fn reveal(&mut self, x: usize, y: usize) -> Vec<GameEvent> {
+        match self.current().map(|p| p.slot) {
+            Some(slot) => self.reveal_by(slot, x, y),
+            None => vec![],
+        }
+    }
+
+    pub fn chord(&mut self, x: usize, y: usize) -> Vec<GameEvent> {
+        match self.current().map(|p| p.slot) {
+            Some(slot) => self.chord_by(slot, x, y),
+            None => vec![],
+        }
+    }
+
+    // Revealing an already revealed number chords it
+    pub fn reveal_by(&mut self, slot: usize, x: usize, y: usize) -> Vec<GameEvent> {
+        if self.grid.tile(x, y).display.is_some() {
+            return self.chord_by(slot, x, y);
+        }
+        let i = match self.actor(slot) {
+            Some(i) => i,
+            None => return vec![],
+        };
+        let res = self.grid.reveal(x, y, &self.players[i]);
+        self.resolve(i, (x, y), res, vec![])
+    }
+
+    pub fn chord_by(&mut self, slot: usize, x: usize, y: usize) -> Vec<GameEvent> {
+        let i = match self.actor(slot) {
+            Some(i) => i,
+            None => return vec![],
+        };
+        let res = self.grid.chord(x, y, &self.players[i]);
+        let events = if res == ClickResult::Invalid {
+            vec![]
+        } else {
+            vec![GameEvent::Chorded { origin: (x, y) }]
+        };
+        self.resolve(i, (x, y), res, events)
+    }
+
+    pub fn toggle_mark(&mut self, x: usize, y: usize) -> Vec<GameEvent> {
+        if !self.is_running() || self.grid.tile(x, y).display.is_some() {
+            return vec![];
+        }
+        self.grid.toggle_mark(x, y);
+        vec![GameEvent::Flagged {
+            tile: (x, y),
+            marked: self.grid.tile(x, y).marked,
+        }]
+    }
+
+    // i is the index of the acting player, who is credited with every revealed cell
+    fn resolve(
+        &mut self,
+        i: usize,
+        origin: (usize, usize),
+        res: ClickResult,
+        mut events: Vec<GameEvent>,
+    ) -> Vec<GameEvent> {
+        let turn = self.current().map(|p| p.slot);
+        let player = self.players[i].slot;
+        let cells = self.grid.take_revealed();
+        let mine = cells.last().copied().filter(|_| res == ClickResult::Mine);
+        let zero = |(x, y): &(usize, usize)| self.grid.tile(*x, *y).display == Some(0);
+        let opening = cells.iter().any(zero);
+        // Shields are for opening a region: the zeros and the numbers around them,
+        // not numbered cells revealed or chorded one by one
+        let opened = cells
+            .iter()
+            .filter(|c| Some(**c) != mine)
+            .filter(|c| zero(c) || self.grid.get_neighbours(c.0, c.1).iter().any(zero))
+            .count();
+        if !cells.is_empty() {
+            events.push(GameEvent::Revealed { origin, cells });
+        }
+
+        if let LifeRule::Shield(size) = self.rule {
+            if opened >= size {
+                self.shields[player] += 1;
+                events.push(GameEvent::ShieldEarned { player });
+            }
+        }
+
+        if let Some(tile) = mine {
+            self.hit(i, tile, &mut events);
+        } else if res == ClickResult::Ok {
+            let extra = self.policy == TurnPolicy::ExtraOnOpening && opening;
+            if !extra {
+                self.pass_turn();
+            }
+        }
+
+        self.finish(turn, mine, &mut events);
+        events
+    }
+
+    // Ends the game or announces the next turn after a change; turn is who had it before
+    fn finish(
+        &mut self,
+        turn: Option<usize>,
+        mine: Option<(usize, usize)>,
+        events: &mut Vec<GameEvent>,
+    ) {
+        if !self.is_running() {
+            let fatal = if self.players_alive == 0 { mine } else { None };
+            self.grid.end_game(fatal);
+            events.push(GameEvent::GameOver {
+                cleared: self.is_cleared(),
+            });
+        } else if self.policy != TurnPolicy::Simultaneous {
+            if let Some(player) = self.current().map(|p| p.slot) {
+                if Some(player) != turn {
+                    events.push(GameEvent::TurnChanged { player });
+                }
+            }
+        }
+    }
+
+    // The current player ran out of time and loses the turn
+    pub fn timeout(&mut self) -> Vec<GameEvent> {
+        let player = match self.current().map(|p| p.slot) {
+            Some(player) if self.is_running() && self.policy != TurnPolicy::Simultaneous => player,
+            _ => return vec![],
+        };
+        self.pass_turn();
+        let mut events = vec![GameEvent::Timeout { player }];
+        if let Some(next) = self.current().map(|p| p.slot).filter(|p| *p != player) {
+            events.push(GameEvent::TurnChanged { player: next });
+        }
+        events
+    }
+
+    // The player left the game for good and is out of it
+    pub fn forfeit(&mut self, slot: usize) -> Vec<GameEvent> {
+        let i = match self.players[..self.players_alive]
+            .iter()
+            .position(|p| p.slot == slot)
+        {
+            Some(i) if self.is_running() => i,
+            _ => return vec![],
+        };
+        let turn = self.current().map(|p| p.slot);
+        let mut events = vec![];
+        self.eliminate(i, None, &mut events);
+        self.finish(turn, None, &mut events);
+        events
+    }
+
+    fn pass_turn(&mut self) {
+        if self.policy == TurnPolicy::Simultaneous {
+            return;
+        }
+        if let Some(next) = self.current().and_then(|p| self.next_player(p.slot)) {
+            self.set_current(next);
+        }
+    }
+
+    // The mine stays revealed and the turn passes on, whether or not the player survives
+    fn hit(&mut self, i: usize, tile: (usize, usize), events: &mut Vec<GameEvent>) {
+        let player = self.players[i].slot;
+        self.hits[player] += 1;
+        events.push(GameEvent::MineHit { tile, player });
+
+        let survives = match self.rule {
+            LifeRule::Lives(lives) => self.hits[player] < lives,
+            LifeRule::Penalty(_) => true,
+            LifeRule::Shield(_) if self.shields[player] > 0 => {
+                self.shields[player] -= 1;
+                events.push(GameEvent::ShieldUsed { player });
+                true
+            }
+            LifeRule::Shield(_) => false,
+        };
+        if survives {
+            self.pass_turn();
+        } else {
+            self.eliminate(i, Some(tile), events);
+        }
+    }
+
+    // tile is the mine that did it, None for a player who left
+    fn eliminate(&mut self, i: usize, tile: Option<(usize, usize)>, events: &mut Vec<GameEvent>) {
+        let player = self.players[i].slot;
+        let turn = self.current().map(|p| p.slot);
+        self.deaths.push(Death {
+            tile,
+            player,
+            score: self.score(i),
+        });
+        self.players_alive -= 1;
+        let mut j = i;
+        while j < self.players_alive {
+            self.players.swap(j, j + 1);
+            j += 1;
+        }
+        if self.policy == TurnPolicy::Simultaneous {
+            // The eliminated player can be before the current one
+            if i < self.curr_player {
+                self.curr_player -= 1;
+            }
+            if self.players_alive > 0 {
+                self.curr_player %= self.players_alive;
+            }
+        } else if turn != Some(player) {
+            // Whoever had the turn keeps it, wherever they moved to
+            if let Some(turn) = turn {
+                self.set_current(turn);
+            }
+        } else if let Some(next) = self.next_player(player) {
+            self.set_current(next);
+        }
+        events.push(GameEvent::Eliminated { player });
+
+        // A team is out once all its members are
+        let team = self.players[self.players_alive].team;
+        if self.is_team_game()
+            && !self.players[..self.players_alive]
+                .iter()
+                .any(|p| p.team == team)
+        {
+            events.push(GameEvent::TeamEliminated { team });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Death;
+    use crate::Game;
+    use crate::GameEvent;
+    use crate::HexGrid;
+    use crate::LifeRule;
+    use crate::Player;
+    use crate::Rgba;
+    use crate::TurnPolicy;
+    use std::rc::Rc;
+
+    fn game(mines: &[(usize, usize)]) -> Game {
+        let mut grid = HexGrid::new(10, 10, mines.len());
+        grid.place_mines(mines);
+        Game::new(
+            grid,
+            vec![
+                Rc::new(Player::new(0, Rgba::GREEN, "Player 1".to_string())),
+                Rc::new(Player::new(1, Rgba::BLUE, "Player 2".to_string())),
+                Rc::new(Player::new(2, Rgba::YELLOW, "Player 3".to_string())),
+            ],
+        )
+    }
+
+    // A wall of mines keeps openings on its right from clearing the board
+    fn wall() -> Vec<(usize, usize)> {
+        (0..10).map(|y| (3, y)).collect()
+    }
+
+    // TEST reveal
+    #[test]
+    fn reveal_turn() {
+        let mut game = game(&[(0, 0)]);
+        let events = game.reveal(0, 1);
+        assert_eq!(
+            events,
+            vec![
+                GameEvent::Revealed {
+                    origin: (0, 1),
+                    cells: vec![(0, 1)]
+                },
+                GameEvent::TurnChanged { player: 1 },
+            ]
+        );
+        assert_eq!(game.score(0), 1);
+        assert!(game.reveal(0, 1).is_empty());
+    }
+
+    #[test]
+    fn reveal_mine() {
+        let mut game = game(&[(0, 0), (9, 9)]);
+        game.reveal(0, 1);
+        let events = game.reveal(0, 0);
+        assert_eq!(
+            events[1],
+            GameEvent::MineHit {
+                tile: (0, 0),
+                player: 1
+            }
+        );
+        assert_eq!(events[2], GameEvent::Eliminated { player: 1 });
+        assert_eq!(events[3], GameEvent::TurnChanged { player: 2 });
+        assert_eq!(game.players_alive, 2);
+        assert_eq!(game.players[2].slot, 1);
+        assert_eq!(
+            game.death(1),
+            Some(&Death {
+                tile: Some((0, 0)),
+                player: 1,
+                score: 0
+            })
+        );
+        assert_eq!(game.death(0), None);
+    }
+
+    #[test]
+    fn reveal_last_player() {
+        let mut game = game(&[(0, 0), (9, 9)]);
+        game.players_alive = 1;
+        let events = game.reveal(0, 0);
+        assert_eq!(events.last(), Some(&GameEvent::GameOver { cleared: false }));
+        assert!(!game.is_running());
+        assert!(game.grid.is_game_over());
+        assert_eq!(game.grid.fatal(), Some((0, 0)));
+        assert!(game.grid.tile(0, 0).hit_by.is_some());
+        assert!(game.reveal(5, 5).is_empty());
+    }
+
+    #[test]
+    fn reveal_lives() {
+        let mut game = game(&[(0, 0), (9, 0), (9, 9)]);
+        game.rule = LifeRule::Lives(2);
+        let events = game.reveal(0, 0);
+        assert_eq!(events[2], GameEvent::TurnChanged { player: 1 });
+        assert_eq!(game.players_alive, 3);
+        assert_eq!(game.lives_left(0), Some(1));
+        assert_eq!(game.status(0), Some("1 life left".to_string()));
+        assert!(game.grid.is_flagged(0, 0));
+
+        game.curr_player = 0;
+        let events = game.reveal(9, 0);
+        assert!(events.contains(&GameEvent::Eliminated { player: 0 }));
+        assert_eq!(game.players_alive, 2);
+        assert_eq!(game.death(0).and_then(|d| d.tile), Some((9, 0)));
+    }
+
+    #[test]
+    fn reveal_penalty() {
+        let mut game = game(&[(0, 0), (9, 9)]);
+        game.rule = LifeRule::Penalty(2);
+        game.reveal(0, 1);
+        assert_eq!(game.score(0), 1);
+        game.curr_player = 0;
+        game.reveal(0, 0);
+        assert_eq!(game.score(0), 0);
+        assert_eq!(game.hits[0], 1);
+        assert_eq!(game.cnt_revealed(), 1);
+        assert_eq!(game.players_alive, 3);
+        assert_eq!(game.current().map(|p| p.slot), Some(1));
+    }
+
+    #[test]
+    fn reveal_shield() {
+        let mut game = game(&wall());
+        game.rule = LifeRule::Shield(10);
+        let events = game.reveal(7, 5);
+        assert!(events.contains(&GameEvent::ShieldEarned { player: 0 }));
+        assert_eq!(game.status(0), Some("1 shield".to_string()));
+
+        game.curr_player = 0;
+        let events = game.reveal(3, 0);
+        assert!(events.contains(&GameEvent::ShieldUsed { player: 0 }));
+        assert_eq!(game.shields[0], 0);
+        assert_eq!(game.players_alive, 3);
+
+        game.curr_player = 0;
+        game.reveal(3, 1);
+        assert_eq!(game.players_alive, 2);
+    }
+
+    #[test]
+    fn chord_numbers_no_shield() {
+        // Every cell between the two walls borders a mine, so none is a zero
+        let mut mines = wall();
+        mines.extend((0..10).map(|y| (0, y)));
+        let mut game = game(&mines);
+        game.rule = LifeRule::Shield(2);
+        game.reveal(1, 5);
+        for (x, y) in game.grid.get_neighbours(1, 5) {
+            if game.grid.tile(x, y).mine {
+                game.toggle_mark(x, y);
+            }
+        }
+        let events = game.chord(1, 5);
+        let opened = events.iter().find_map(|e| match e {
+            GameEvent::Revealed { cells, .. } => Some(cells.len()),
+            _ => None,
+        });
+        assert!(opened >= Some(2));
+        assert!(!events
+            .iter()
+            .any(|e| matches!(e, GameEvent::ShieldEarned { .. })));
+    }
+
+    #[test]
+    fn reveal_cleared() {
+        let mut game = game(&[(0, 0)]);
+        let events = game.reveal(5, 5);
+        assert_eq!(events.last(), Some(&GameEvent::GameOver { cleared: true }));
+        assert!(game.is_cleared());
+        assert_eq!(game.grid.fatal(), None);
+    }
+
+    // TEST reveal_by
+    #[test]
+    fn reveal_by_one_per_action() {
+        let mut game = game(&wall());
+        let events = game.reveal_by(0, 7, 5);
+        assert_eq!(events.last(), Some(&GameEvent::TurnChanged { player: 1 }));
+        assert_eq!(game.score(1), 0);
+        assert!(game.score(0) > 1);
+        assert!(game.reveal_by(0, 0, 0).is_empty());
+    }
+
+    #[test]
+    fn reveal_by_extra_on_opening() {
+        let mut game = game(&wall());
+        game.policy = TurnPolicy::ExtraOnOpening;
+        game.reveal_by(0, 2, 0);
+        assert_eq!(game.curr_player, 1);
+        let events = game.reveal_by(1, 7, 5);
+        assert!(game.is_running());
+        assert!(!events.contains(&GameEvent::TurnChanged { player: 2 }));
+        assert_eq!(game.curr_player, 1);
+    }
+
+    #[test]
+    fn reveal_by_simultaneous() {
+        let mut game = game(&[(0, 0), (9, 0), (9, 9)]);
+        game.policy = TurnPolicy::Simultaneous;
+        let events = game.reveal_by(2, 0, 1);
+        assert_eq!(events.len(), 1);
+        assert_eq!(game.score(2), 1);
+        game.reveal_by(2, 1, 0);
+        assert_eq!(game.score(2), 2);
+
+        game.curr_player = 2;
+        let events = game.reveal_by(1, 9, 0);
+        assert!(events.contains(&GameEvent::Eliminated { player: 1 }));
+        assert_eq!(game.curr_player, 1);
+        assert_eq!(game.players[game.curr_player].slot, 2);
+        assert!(game.reveal_by(1, 5, 5).is_empty());
+    }
+
+    fn team_game(mines: &[(usize, usize)], teams: &[usize]) -> Game {
+        let mut grid = HexGrid::new(10, 10, mines.len());
+        grid.place_mines(mines);
+        let mut members = vec![0; teams.len()];
+        let players = teams
+            .iter()
+            .enumerate()
+            .map(|(slot, team)| {
+                members[*team] += 1;
+                let player = Player::new(slot, Rgba::GREEN, format!("Player {}", slot + 1));
+                Rc::new(player.with_team(*team, members[*team] - 1))
+            })
+            .collect();
+        Game::new(grid, players)
+    }
+
+    fn turn(game: &Game) -> usize {
+        game.current().map(|p| p.slot).unwrap()
+    }
+
+    // TEST next_player
+    #[test]
+    fn next_player_alternates_teams() {
+        let mut game = team_game(&wall(), &[0, 1, 0]);
+        assert!(game.is_team_game());
+        let mut turns = vec![];
+        for y in 0..4 {
+            turns.push(turn(&game));
+            game.reveal(4, y);
+        }
+        assert_eq!(turns, vec![0, 1, 2, 1]);
+        assert_eq!(turn(&game), 0);
+    }
+
+    #[test]
+    fn next_player_without_teams() {
+        let mut game = game(&wall());
+        assert!(!game.is_team_game());
+        game.reveal(4, 0);
+        game.reveal(4, 1);
+        game.reveal(4, 2);
+        assert_eq!(turn(&game), 0);
+    }
+
+    // TEST team_score
+    #[test]
+    fn team_score_combined() {
+        let mut game = team_game(&wall(), &[0, 1, 0, 1]);
+        game.reveal(4, 0);
+        game.reveal(4, 1);
+        game.reveal(4, 2);
+        assert_eq!(game.team_score(0), 2);
+        assert_eq!(game.team_score(1), 1);
+        assert_eq!(game.winning_team(), Some(0));
+        assert_eq!(game.team_ranking(), vec![0, 1]);
+        assert_eq!(game.status(0), Some("Team 1: 2".to_string()));
+    }
+
+    // TEST hit
+    #[test]
+    fn hit_team_eliminated() {
+        let mut game = team_game(&wall(), &[0, 1, 0, 1]);
+        game.reveal(4, 0);
+        let events = game.reveal(3, 0);
+        assert!(events.contains(&GameEvent::Eliminated { player: 1 }));
+        assert!(!events.contains(&GameEvent::TeamEliminated { team: 1 }));
+        assert_eq!(turn(&game), 2);
+        game.reveal(4, 1);
+        assert_eq!(turn(&game), 3);
+        let events = game.reveal(3, 1);
+        assert!(events.contains(&GameEvent::TeamEliminated { team: 1 }));
+        assert_eq!(turn(&game), 0);
+        game.reveal(4, 2);
+        assert_eq!(turn(&game), 2);
+    }
+
+    // TEST winner
+    #[test]
+    fn winner0() {
+        let mut game = game(&[(0, 0)]);
+        assert_eq!(game.winner(), None);
+        game.reveal(0, 1);
+        assert_eq!(game.winner(), Some(0));
+        assert_eq!(game.ranking()[0], 0);
+    }
+
+    // TEST chord
+    #[test]
+    fn chord_events() {
+        let mut game = game(&[(4, 4), (0, 0)]);
+        game.reveal(4, 5);
+        assert!(game.reveal(4, 5).is_empty());
+        game.toggle_mark(4, 4);
+        let events = game.reveal(4, 5);
+        assert_eq!(events[0], GameEvent::Chorded { origin: (4, 5) });
+        assert!(matches!(events[1], GameEvent::Revealed { .. }));
+    }
+
+    // TEST toggle_mark
+    #[test]
+    fn toggle_mark_events() {
+        let mut game = game(&[(0, 0)]);
+        assert_eq!(
+            game.toggle_mark(0, 0),
+            vec![GameEvent::Flagged {
+                tile: (0, 0),
+                marked: true
+            }]
+        );
+        assert_eq!(
+            game.toggle_mark(0, 0),
+            vec![GameEvent::Flagged {
+                tile: (0, 0),
+                marked: false
+            }]
+        );
+        game.reveal(0, 1);
+        assert!(game.toggle_mark(0, 1).is_empty());
+    }
+
+    // TEST timeout
+    #[test]
+    fn timeout_passes_turn() {
+        let mut game = game(&[(0, 0)]);
+        assert_eq!(
+            game.timeout(),
+            vec![
+                GameEvent::Timeout { player: 0 },
+                GameEvent::TurnChanged { player: 1 }
+            ]
+        );
+        assert_eq!(game.current().map(|p| p.slot), Some(1));
+        assert_eq!(game.cnt_revealed(), 0);
+
+        game.policy = TurnPolicy::Simultaneous;
+        assert!(game.timeout().is_empty());
+    }
+
+    // TEST forfeit
+    #[test]
+    fn forfeit_keeps_turn() {
+        let mut game = game(&[(0, 0)]);
+        game.timeout();
+        assert_eq!(game.forfeit(2), vec![GameEvent::Eliminated { player: 2 }]);
+        assert_eq!(game.current().map(|p| p.slot), Some(1));
+        assert_eq!(game.death(2).map(|d| d.tile), Some(None));
+        assert!(game.forfeit(2).is_empty());
+    }
+
+    #[test]
+    fn forfeit_passes_turn() {
+        let mut game = game(&[(0, 0)]);
+        assert_eq!(
+            game.forfeit(0),
+            vec![
+                GameEvent::Eliminated { player: 0 },
+                GameEvent::TurnChanged { player: 1 }
+            ]
+        );
+        game.forfeit(1);
+        assert_eq!(
+            game.forfeit(2),
+            vec![
+                GameEvent::Eliminated { player: 2 },
+                GameEvent::GameOver { cleared: false }
+            ]
+        );
+        assert!(!game.is_running());
+    }
+}
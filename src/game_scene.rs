@@ -0,0 +1,754 @@
+use crate::Action;
+use crate::Animator;
+use crate::App;
+use crate::Camera;
+use crate::CameraMode;
+use crate::CellState;
+use crate::ChatLine;
+use crate::ClientMessage;
+use crate::Game;
+use crate::GameEvent;
+use crate::Link;
+use crate::Move;
+use crate::PauseMenu;
+use crate::PlayerView;
+use crate::RateLimit;
+use crate::ResultsMenu;
+use crate::Said;
+use crate::Scene;
+use crate::Theme;
+use crate::Transition;
+use crate::TurnPolicy;
+use crate::MAX_CHAT;
+use ggez::event::Axis;
+use ggez::event::Button;
+use ggez::event::GamepadId;
+use ggez::event::MouseButton;
+use ggez::graphics;
+use ggez::graphics::Canvas;
+use ggez::graphics::DrawMode;
+use ggez::graphics::DrawParam;
+use ggez::graphics::PxScale;
+use ggez::graphics::Rect;
+use ggez::graphics::Text;
+use ggez::graphics::TextFragment;
+use ggez::input::keyboard::KeyCode;
+use ggez::input::mouse;
+use ggez::timer;
+use ggez::Context;
+use ggez::GameResult;
+use glam::Vec2;
+use std::collections::VecDeque;
+use std::time::Duration;
+use std::time::Instant;
+
+const BOARD_WIDTH: f32 = 600.0;
+const FOLLOW_ZOOM: f32 = 1.5;
+// Moves shown to spectators
+const FEED_LINES: usize = 6;
+// Chat lines kept on screen, and for how long while nobody is typing
+const CHAT_LINES: usize = 6;
+const CHAT_SHOW: Duration = Duration::from_secs(15);
+
+// Cursor and stick state of the keyboard and mouse or of one gamepad
+struct Controller {
+    cursor: Option<(usize, usize)>,
+    stick: Vec2,
+    stick_released: bool,
+}
+
+impl Controller {
+    fn new() -> Self {
+        Self {
+            cursor: None,
+            stick: Vec2::new(0.0, 0.0),
+            stick_released: true,
+        }
+    }
+}
+
+// UI state around a running game: camera, cursors, animations and the cached board
+pub struct GameScene {
+    game: Game,
+    // Everything on the board is drawn from the view, never from the grid itself
+    view: PlayerView,
+    camera: Camera,
+    camera_mode: CameraMode,
+    board: Canvas,
+    board_dirty: bool,
+    theme_version: usize,
+    animator: Animator,
+    // The keyboard and mouse come first, then gamepads in the order they were first used.
+    // Taking turns they all share the first controller, in simultaneous play
+    // each controls the player in the same position of the starting order.
+    controllers: Vec<Controller>,
+    pads: Vec<GamepadId>,
+    seats: Vec<usize>,
+    hover: Option<(usize, usize)>,
+    // The slot played on this machine in a network game, where every controller acts for it
+    local: Option<usize>,
+    // Spectators only watch; casters also see the mines
+    watching: bool,
+    mines: Vec<(usize, usize)>,
+    // Chat lines with the time they arrived, and the line being typed
+    chat: VecDeque<(ChatLine, Instant)>,
+    typing: Option<String>,
+    chat_limit: RateLimit,
+    // The last line was typed too soon after the ones before it
+    slowed: bool,
+    results_shown: bool,
+}
+
+impl GameScene {
+    pub fn new(ctx: &mut Context, app: &App, game: Game) -> GameResult<Self> {
+        let mut animator = Animator::new();
+        animator.enabled = app.animations;
+        // Large boards such as the expert preset start zoomed out to fit
+        let view = game.grid.view();
+        let (_, max) = view.bounds();
+        let camera_mode = if max.x > BOARD_WIDTH || max.y > graphics::screen_coordinates(ctx).h {
+            CameraMode::Fit
+        } else {
+            CameraMode::Free
+        };
+        let seats = game.players.iter().map(|p| p.slot).collect();
+        let mut scene = Self {
+            game,
+            view,
+            camera: Camera::new(),
+            camera_mode,
+            board: Canvas::with_window_size(ctx)?,
+            board_dirty: true,
+            theme_version: app.theme_version,
+            animator,
+            controllers: vec![Controller::new()],
+            pads: vec![],
+            seats,
+            hover: None,
+            local: app.net.as_ref().and_then(Link::slot),
+            watching: matches!(&app.net, Some(Link::Client(c)) if c.watching),
+            mines: vec![],
+            chat: VecDeque::new(),
+            typing: None,
+            chat_limit: RateLimit::new(),
+            slowed: false,
+            results_shown: false,
+        };
+        scene.update_camera(ctx);
+        Ok(scene)
+    }
+
+    fn controller(&mut self, pad: GamepadId) -> usize {
+        if self.game.policy != TurnPolicy::Simultaneous || self.local.is_some() {
+            return 0;
+        }
+        match self.pads.iter().position(|p| *p == pad) {
+            Some(i) => i + 1,
+            None => {
+                self.pads.push(pad);
+                self.controllers.push(Controller::new());
+                self.pads.len()
+            }
+        }
+    }
+
+    // Slot of the player a controller acts for
+    fn player(&self, c: usize) -> Option<usize> {
+        if self.local.is_some() {
+            self.local
+        } else if self.game.policy == TurnPolicy::Simultaneous {
+            self.seats.get(c).copied()
+        } else {
+            self.game.current().map(|p| p.slot)
+        }
+    }
+
+    fn act(
+        &mut self,
+        ctx: &mut Context,
+        app: &mut App,
+        c: usize,
+        action: Action,
+        x: usize,
+        y: usize,
+    ) {
+        let slot = match self.player(c) {
+            Some(slot) if !self.watching => slot,
+            _ => return,
+        };
+        // Network moves only come back as updates, the host's own included
+        if let Some(link) = &mut app.net {
+            let msg = match action {
+                Action::Reveal if self.game.can_act(slot) => ClientMessage::Reveal { x, y },
+                Action::Chord if self.game.can_act(slot) => ClientMessage::Chord { x, y },
+                Action::Flag if self.game.is_running() => ClientMessage::Flag { x, y },
+                _ => return,
+            };
+            match link {
+                Link::Host(host) => host.play(&msg),
+                Link::Client(client) => client.send(&msg),
+            }
+            return;
+        }
+        let events = match action {
+            Action::Reveal => self.game.reveal_by(slot, x, y),
+            Action::Chord => self.game.chord_by(slot, x, y),
+            Action::Flag => self.game.toggle_mark(x, y),
+            _ => vec![],
+        };
+        self.play(ctx, app, &events);
+    }
+
+    // Animates and sounds the events of a move, wherever it was made
+    fn play(&mut self, ctx: &mut Context, app: &mut App, events: &[GameEvent]) {
+        self.view = self.game.grid.view();
+        let mut origin = None;
+        for event in events {
+            match event {
+                GameEvent::Revealed { origin: o, cells } => {
+                    origin = Some(*o);
+                    self.animator.reveal(&self.view, *o, cells)
+                }
+                GameEvent::Chorded { origin: o } => origin = Some(*o),
+                GameEvent::Flagged { tile, .. } => origin = Some(*tile),
+                GameEvent::MineHit { tile, .. } => {
+                    origin = Some(*tile);
+                    self.animator.explode(*tile)
+                }
+                GameEvent::GameOver { .. } => {
+                    let (size_x, size_y) = self.view.size();
+                    self.animator.sweep(
+                        &self.view,
+                        origin.unwrap_or((size_x / 2, size_y / 2)),
+                        &self.view.exposed_mines(),
+                    )
+                }
+                _ => {}
+            }
+        }
+        app.sounds.play(ctx, events);
+        self.board_dirty = true;
+    }
+
+    fn input(
+        &mut self,
+        ctx: &mut Context,
+        app: &mut App,
+        c: usize,
+        action: Action,
+    ) -> Transition<App> {
+        let (size_x, size_y) = self.view.size();
+        let cursor = self.controllers[c].cursor;
+        let (x, y) = cursor.unwrap_or((size_x / 2, size_y / 2));
+        // Only the cursor, the camera and moves change what the board shows; the
+        // theme is picked up by draw and animations by update
+        let redraw = match action {
+            Action::Pause if self.is_joined(app) => {
+                return Transition::Push(Box::new(PauseMenu::joined()))
+            }
+            Action::Pause => return Transition::Push(Box::new(PauseMenu::new())),
+            Action::ToggleMute => {
+                app.toggle_mute();
+                false
+            }
+            Action::VolumeUp => {
+                app.change_volume(1.0);
+                false
+            }
+            Action::VolumeDown => {
+                app.change_volume(-1.0);
+                false
+            }
+            Action::ToggleAnimations => {
+                app.animations ^= true;
+                false
+            }
+            Action::NextTheme => {
+                app.change_theme(1);
+                false
+            }
+            Action::ToggleChat => {
+                app.chat_muted ^= true;
+                self.chat.clear();
+                false
+            }
+            Action::Chat if app.net.is_some() && !self.watching => {
+                self.typing = Some(String::new());
+                false
+            }
+            Action::Emote(emote) => {
+                self.say(app, Said::Emote(emote));
+                false
+            }
+            Action::Chat => false,
+            Action::NextCamera | Action::PrevCamera => {
+                self.camera_mode = if action == Action::NextCamera {
+                    self.camera_mode.next()
+                } else {
+                    self.camera_mode.prev()
+                };
+                if self.camera_mode == CameraMode::Free {
+                    self.camera = Camera::new();
+                }
+                true
+            }
+            Action::Move(dir) if cursor.is_some() => {
+                self.controllers[c].cursor = self.view.step(x, y, dir).or(cursor);
+                true
+            }
+            // The keyboard cursor takes over from the mouse for the move
+            _ if cursor.is_some() => {
+                self.act(ctx, app, c, action, x, y);
+                true
+            }
+            _ => {
+                self.controllers[c].cursor = Some((x, y));
+                true
+            }
+        };
+        if redraw {
+            self.hover = None;
+            self.update_camera(ctx);
+            self.board_dirty = true;
+        }
+        Transition::None
+    }
+
+    // Returns false when the line is held back for being sent too soon after the last ones
+    fn say(&mut self, app: &mut App, said: Said) -> bool {
+        let link = match &mut app.net {
+            Some(link) if !self.watching => link,
+            _ => return true,
+        };
+        if !self.chat_limit.allow(Instant::now()) {
+            return false;
+        }
+        link.say(said);
+        true
+    }
+
+    // Enter sends the line being typed and Escape drops it
+    fn type_key(&mut self, app: &mut App, keycode: KeyCode) {
+        let text = match &mut self.typing {
+            Some(text) => text,
+            None => return,
+        };
+        match keycode {
+            KeyCode::Return | KeyCode::NumpadEnter => {
+                let said = Said::Text(text.clone());
+                self.slowed = !self.say(app, said);
+                if !self.slowed {
+                    self.typing = None;
+                }
+            }
+            KeyCode::Escape => self.typing = None,
+            KeyCode::Back => {
+                text.pop();
+            }
+            _ => {}
+        }
+    }
+
+    // Clients can't restart games, only the host can
+    fn is_joined(&self, app: &App) -> bool {
+        matches!(app.net, Some(Link::Client(_)))
+    }
+
+    // The turn's time left on a server with turn times, and the latest moves for spectators
+    fn draw_feed(&self, ctx: &mut Context, app: &App) -> GameResult {
+        let client = match &app.net {
+            Some(Link::Client(client)) => client,
+            _ => return Ok(()),
+        };
+        let theme = app.theme();
+        let fragment = |text: String, color| TextFragment {
+            text: text + "\n",
+            color: Some(color),
+            font: Some(theme.fonts.text),
+            scale: Some(PxScale::from(theme.text_size * 0.6)),
+        };
+        let mut txt = Text::default();
+        match client.time_left() {
+            Some(left) if self.game.is_running() => {
+                let secs = left.as_secs_f32().ceil();
+                txt.add(fragment(format!("Time left: {}s", secs), theme.panel_text));
+            }
+            _ => {}
+        }
+        if self.watching {
+            txt.add(fragment("Watching".to_string(), theme.panel_text));
+            let skip = client.feed.len().saturating_sub(FEED_LINES);
+            for played in &client.feed[skip..] {
+                let player = self.game.players.iter().find(|p| p.slot == played.slot);
+                let color = player.map_or(theme.panel_text, |p| theme.player_color(p));
+                txt.add(fragment(
+                    feed_line(played, player.map(|p| p.name.as_str())),
+                    color,
+                ));
+            }
+        }
+        let mut y = (self.game.players.len() * 100) as f32 + 40.0;
+        if !self.game.is_running() {
+            y += theme.title_size * 1.5;
+        }
+        draw_panel(ctx, theme, &txt, Vec2::new(610.0, y))
+    }
+
+    // Recent chat in each player's colour, and the line being typed
+    fn draw_chat(&self, ctx: &mut Context, app: &App) -> GameResult {
+        let theme = app.theme();
+        let fragment = |text: String, color| TextFragment {
+            text: text + "\n",
+            color: Some(color),
+            font: Some(theme.fonts.text),
+            scale: Some(PxScale::from(theme.text_size * 0.6)),
+        };
+        let mut txt = Text::default();
+        let recent = self
+            .chat
+            .iter()
+            .filter(|(_, at)| self.typing.is_some() || at.elapsed() < CHAT_SHOW);
+        for (line, _) in recent {
+            let player = self.game.players.iter().find(|p| p.slot == line.slot);
+            let name =
+                player.map_or_else(|| format!("Player {}", line.slot + 1), |p| p.name.clone());
+            let color = player.map_or(theme.panel_text, |p| theme.player_color(p));
+            txt.add(fragment(format!("{}: {}", name, line.said.text()), color));
+        }
+        if let Some(text) = &self.typing {
+            let slowed = if self.slowed { " (too fast)" } else { "" };
+            txt.add(fragment(
+                format!("Say: {}_{}", text, slowed),
+                theme.panel_text,
+            ));
+        }
+        let h = txt.dimensions(ctx).h;
+        let pos = Vec2::new(10.0, graphics::screen_coordinates(ctx).h - h - 10.0);
+        draw_panel(ctx, theme, &txt, pos)
+    }
+
+    fn update_camera(&mut self, ctx: &mut Context) {
+        let screen = Vec2::new(BOARD_WIDTH, graphics::screen_coordinates(ctx).h);
+        match self.camera_mode {
+            CameraMode::Free => {}
+            CameraMode::Fit => {
+                let (min, max) = self.view.bounds();
+                self.camera = Camera::fit(min, max, screen);
+            }
+            CameraMode::Follow => {
+                let (size_x, size_y) = self.view.size();
+                let (x, y) = self.controllers[0]
+                    .cursor
+                    .unwrap_or((size_x / 2, size_y / 2));
+                self.camera = Camera::centered(self.view.pos(x, y), FOLLOW_ZOOM, screen);
+            }
+        }
+    }
+
+    fn draw_board(&mut self, ctx: &mut Context, app: &mut App) -> GameResult {
+        graphics::set_canvas(ctx, Some(&self.board));
+        let theme = &app.themes[app.theme_index()];
+        let tiles = &mut app.tiles;
+        graphics::clear(ctx, theme.background);
+        tiles.set_number_style(
+            ctx,
+            theme.fonts.number,
+            theme.fonts.number_outlines.as_ref(),
+            theme.number_scale,
+        );
+        self.view.draw(
+            tiles,
+            theme,
+            &self.game.players,
+            self.hover.or(self.controllers[0].cursor),
+            &self.animator,
+        );
+        // Casters see the mines nobody has found yet through the covers
+        let mut hidden = theme.mine_glyph;
+        hidden.a *= 0.5;
+        for &(x, y) in &self.mines {
+            if self.view.cell(x, y) == CellState::Covered {
+                tiles.add_mine(self.view.pos(x, y), hidden);
+            }
+        }
+        for (tile, progress) in self.animator.explosions() {
+            tiles.add_burst(self.view.pos(tile.0, tile.1), progress, theme.game_over);
+        }
+        for (c, controller) in self.controllers.iter().enumerate() {
+            let (x, y) = match controller.cursor {
+                Some(cursor) => cursor,
+                None => continue,
+            };
+            // Gamepad cursors in simultaneous play are drawn in their player's colour
+            let player = self
+                .game
+                .players
+                .iter()
+                .find(|p| c > 0 && Some(p.slot) == self.seats.get(c).copied());
+            let color = match player {
+                Some(player) => theme.player_color(player),
+                None => theme.cursor,
+            };
+            tiles.add_highlight(self.view.pos(x, y), color);
+        }
+        tiles.draw(
+            ctx,
+            DrawParam::new()
+                .dest(self.camera.offset)
+                .scale(Vec2::splat(self.camera.zoom)),
+        )?;
+        graphics::set_canvas(ctx, None);
+        self.board_dirty = false;
+        self.theme_version = app.theme_version;
+        Ok(())
+    }
+}
+
+// Text on a dark panel, so it reads on any background
+fn draw_panel(ctx: &mut Context, theme: &Theme, txt: &Text, pos: Vec2) -> GameResult {
+    if txt.fragments().is_empty() {
+        return Ok(());
+    }
+    let dims = txt.dimensions(ctx);
+    let panel = Rect::new(pos.x - 5.0, pos.y - 5.0, dims.w + 10.0, dims.h + 10.0);
+    let cover = graphics::Mesh::new_rectangle(ctx, DrawMode::fill(), panel, theme.panel_cover)?;
+    graphics::draw(ctx, &cover, (Vec2::new(0.0, 0.0),))?;
+    graphics::draw(ctx, txt, (pos,))
+}
+
+fn feed_line(played: &Move, name: Option<&str>) -> String {
+    let name = name.map_or_else(|| format!("Player {}", played.slot + 1), String::from);
+    match &played.message {
+        Some(ClientMessage::Reveal { x, y }) => format!("{} revealed {}, {}", name, x, y),
+        Some(ClientMessage::Chord { x, y }) => format!("{} chorded {}, {}", name, x, y),
+        Some(ClientMessage::Flag { x, y }) => format!("{} flagged {}, {}", name, x, y),
+        Some(_) => name,
+        None => format!("{} ran out of time", name),
+    }
+}
+
+impl Scene<App> for GameScene {
+    fn update(&mut self, ctx: &mut Context, app: &mut App) -> GameResult<Transition<App>> {
+        if self.animator.enabled != app.animations {
+            self.animator.enabled = app.animations;
+            self.animator.clear();
+            self.board_dirty = true;
+        }
+        if let Some(Link::Client(client)) = &app.net {
+            if client.mines != self.mines {
+                self.mines = client.mines.clone();
+                self.board_dirty = true;
+            }
+        }
+        // The app polls the network every frame and queues what happened
+        if let Some(link) = &mut app.net {
+            for update in link.take_updates() {
+                update.apply(&mut self.game);
+                self.play(ctx, app, &update.events);
+            }
+        }
+        if let Some(link) = &mut app.net {
+            for line in link.take_chat() {
+                if !app.chat_muted {
+                    self.chat.push_back((line, Instant::now()));
+                }
+            }
+            while self.chat.len() > CHAT_LINES {
+                self.chat.pop_front();
+            }
+        }
+        self.animator.update(timer::delta(ctx).as_secs_f32());
+        if self.animator.is_animating() {
+            self.board_dirty = true;
+        }
+        if !self.game.is_running() && !self.animator.is_animating() && !self.results_shown {
+            self.results_shown = true;
+            if let Some(session) = &mut app.session {
+                session.record(&self.game);
+            }
+            return Ok(Transition::Push(Box::new(ResultsMenu::new(
+                &self.game,
+                app.session.as_ref(),
+                self.is_joined(app),
+            ))));
+        }
+        Ok(Transition::None)
+    }
+
+    fn draw(&mut self, ctx: &mut Context, app: &mut App) -> GameResult {
+        // The theme can change in the settings menu while this scene is below it
+        if self.board_dirty || self.theme_version != app.theme_version {
+            self.draw_board(ctx, app)?;
+        }
+        graphics::draw(ctx, &self.board, DrawParam::new())?;
+
+        let theme = app.theme();
+        let game = &self.game;
+        if game.is_running() {
+            for i in 0..game.players.len() {
+                let pos = Vec2::new(BOARD_WIDTH, (i * 100) as f32 + 10.0);
+                if i >= game.players_alive {
+                    game.players[i].draw_dead(ctx, theme, pos, game.score(i))?;
+                } else if i == game.curr_player || game.policy == TurnPolicy::Simultaneous {
+                    game.players[i].draw_active(ctx, theme, pos, game.score(i))?;
+                } else {
+                    game.players[i].draw_inactive(ctx, theme, pos, game.score(i))?;
+                }
+                if let Some(status) = game.status(game.players[i].slot) {
+                    game.players[i].draw_status(ctx, theme, pos, &status)?;
+                }
+            }
+        } else {
+            for i in 0..game.players.len() {
+                let pos = Vec2::new(BOARD_WIDTH, (i * 100) as f32 + 10.0);
+                game.players[i].draw_active(ctx, theme, pos, game.score(i))?;
+            }
+            let txt = Text::new(TextFragment {
+                text: String::from("Game Over!"),
+                color: Some(theme.game_over),
+                font: Some(theme.fonts.text),
+                scale: Some(PxScale::from(theme.title_size)),
+            });
+            graphics::draw(
+                ctx,
+                &txt,
+                (Vec2::new(610.0, (game.players.len() * 100) as f32 + 40.0),),
+            )?;
+        }
+        self.draw_feed(ctx, app)?;
+        self.draw_chat(ctx, app)
+    }
+
+    fn is_animating(&self) -> bool {
+        self.animator.is_animating()
+    }
+
+    fn mouse_button_up_event(
+        &mut self,
+        ctx: &mut Context,
+        app: &mut App,
+        button: MouseButton,
+        x: f32,
+        y: f32,
+    ) -> Transition<App> {
+        let pos = self.camera.to_world(Vec2::new(x, y));
+        if let Some((x, y)) = self.view.cell_at(pos) {
+            if self.controllers[0].cursor.is_some() {
+                self.controllers[0].cursor = Some((x, y));
+            }
+            match button {
+                MouseButton::Left => self.act(ctx, app, 0, Action::Reveal, x, y),
+                MouseButton::Right => self.act(ctx, app, 0, Action::Flag, x, y),
+                _ => {}
+            }
+        }
+        Transition::None
+    }
+
+    fn mouse_motion_event(
+        &mut self,
+        ctx: &mut Context,
+        _app: &mut App,
+        x: f32,
+        y: f32,
+        dx: f32,
+        dy: f32,
+    ) {
+        let hover = self.view.cell_at(self.camera.to_world(Vec2::new(x, y)));
+        if hover != self.hover {
+            self.hover = hover;
+            self.board_dirty = true;
+        }
+        if mouse::button_pressed(ctx, MouseButton::Middle) {
+            self.camera_mode = CameraMode::Free;
+            self.camera.pan(Vec2::new(dx, dy));
+            self.board_dirty = true;
+        }
+    }
+
+    fn mouse_wheel_event(&mut self, ctx: &mut Context, _app: &mut App, _x: f32, y: f32) {
+        let pos = mouse::position(ctx);
+        self.camera_mode = CameraMode::Free;
+        self.camera.zoom_at(
+            Vec2::new(pos.x, pos.y),
+            if y > 0.0 { 1.1 } else { 1.0 / 1.1 },
+        );
+        self.board_dirty = true;
+    }
+
+    fn key_down_event(
+        &mut self,
+        ctx: &mut Context,
+        app: &mut App,
+        keycode: KeyCode,
+    ) -> Transition<App> {
+        // While typing, keys edit the line instead of playing
+        if self.typing.is_some() {
+            self.type_key(app, keycode);
+            return Transition::None;
+        }
+        match Action::from_key(keycode) {
+            Some(action) => self.input(ctx, app, 0, action),
+            None => Transition::None,
+        }
+    }
+
+    fn text_input_event(&mut self, _ctx: &mut Context, _app: &mut App, character: char) {
+        if let Some(text) = &mut self.typing {
+            if !character.is_control() && text.chars().count() < MAX_CHAT {
+                text.push(character);
+                self.slowed = false;
+            }
+        }
+    }
+
+    fn gamepad_button_down_event(
+        &mut self,
+        ctx: &mut Context,
+        app: &mut App,
+        btn: Button,
+        id: GamepadId,
+    ) -> Transition<App> {
+        let c = self.controller(id);
+        match Action::from_button(btn) {
+            Some(action) => self.input(ctx, app, c, action),
+            None => Transition::None,
+        }
+    }
+
+    fn gamepad_axis_event(
+        &mut self,
+        ctx: &mut Context,
+        app: &mut App,
+        axis: Axis,
+        value: f32,
+        id: GamepadId,
+    ) -> Transition<App> {
+        let c = self.controller(id);
+        let controller = &mut self.controllers[c];
+        match axis {
+            Axis::LeftStickX => controller.stick.x = value,
+            Axis::LeftStickY => controller.stick.y = value,
+            _ => return Transition::None,
+        }
+        match Action::from_stick(controller.stick.x, controller.stick.y) {
+            Some(action) if controller.stick_released => {
+                controller.stick_released = false;
+                self.input(ctx, app, c, action)
+            }
+            Some(_) => Transition::None,
+            None => {
+                controller.stick_released = true;
+                Transition::None
+            }
+        }
+    }
+
+    fn resize_event(&mut self, ctx: &mut Context, _app: &mut App) {
+        if let Ok(board) = Canvas::with_window_size(ctx) {
+            self.board = board;
+        }
+        self.update_camera(ctx);
+        self.board_dirty = true;
+    }
+}
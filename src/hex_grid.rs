@@ -1,49 +1,87 @@
+use crate::CellState;
 use crate::HexTile;
 use crate::Player;
-use ggez::Context;
-use ggez::GameResult;
+use crate::PlayerView;
 use glam::Vec2;
 use std::f32::consts::PI;
 use std::rc::Rc;
 
+pub(crate) const TILE_SIZE: f32 = 50.0;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub enum ClickResult {
     Ok,
     Invalid,
     Mine,
 }
 
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Direction {
+    Up,
+    Down,
+    UpLeft,
+    UpRight,
+    Left,
+    Right,
+    DownLeft,
+    DownRight,
+}
+
 #[derive(Clone)]
 pub struct HexGrid {
     grid: Vec<Vec<HexTile>>,
     mines_loaded: bool,
     mine_count: usize,
+    revealed: Vec<(usize, usize)>,
+    game_over: bool,
+    fatal: Option<(usize, usize)>,
+    seed: u64,
 }
 
 impl HexGrid {
     pub fn new(cnt_x: usize, cnt_y: usize, mine_count: usize) -> Self {
+        Self::with_seed(cnt_x, cnt_y, mine_count, fastrand::u64(..))
+    }
+
+    // The same seed and first revealed cell always give the same mines. A mine
+    // count that doesn't fit is cut down to the most the board can hold.
+    pub fn with_seed(cnt_x: usize, cnt_y: usize, mine_count: usize, seed: u64) -> Self {
         let mut grid = vec![];
 
         for i in 0..cnt_x {
             grid.push(vec![]);
             for j in 0..cnt_y {
-                grid[i as usize].push(HexTile::new(
-                    50.0,
-                    Vec2::new(
-                        i as f32 * 50.0 + 25.0 * if j % 2 == 0 { 0.0 } else { 1.0 } + 33.0,
-                        j as f32 * (50.0 / 2.0 / (PI / 6.0).cos() + 50.0 / 2.0 * (PI / 6.0).tan())
-                            + 33.0,
-                    ),
-                ));
+                grid[i].push(HexTile::new(TILE_SIZE, tile_pos(i, j)));
             }
         }
 
         Self {
             grid,
-            mine_count,
+            mine_count: mine_count.min(Self::max_mines(cnt_x, cnt_y)),
             mines_loaded: false,
+            revealed: vec![],
+            game_over: false,
+            fatal: None,
+            seed,
         }
     }
 
+    // A network client's copy of the board: mines are unknown until the host sends them
+    pub fn remote(cnt_x: usize, cnt_y: usize, mine_count: usize) -> Self {
+        let mut grid = Self::with_seed(cnt_x, cnt_y, mine_count, 0);
+        grid.mines_loaded = true;
+        grid
+    }
+
+    // The first revealed cell is never a mine, so at least one cell stays free
+    pub fn max_mines(cnt_x: usize, cnt_y: usize) -> usize {
+        (cnt_x * cnt_y).saturating_sub(1)
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
     pub fn tile_number(&self) -> usize {
         self.grid.len() * self.grid[0].len()
     }
@@ -53,11 +91,12 @@ impl HexGrid {
     }
 
     fn gen_mines(&mut self, pos: Vec2) {
+        let rng = fastrand::Rng::with_seed(self.seed);
         for _ in 0..self.mine_count {
             loop {
                 let (x, y) = (
-                    fastrand::usize(..(self.grid.len())),
-                    fastrand::usize(..(self.grid[0].len())),
+                    rng.usize(..(self.grid.len())),
+                    rng.usize(..(self.grid[0].len())),
                 );
                 if !self.grid[x][y].mine && !self.grid[x][y].is_inside(pos) {
                     self.grid[x][y].mine = true;
@@ -68,117 +107,252 @@ impl HexGrid {
         self.mines_loaded = true;
     }
 
-    pub fn draw(&self, ctx: &mut Context) -> GameResult {
-        for i in &self.grid {
-            for j in i {
-                j.draw(ctx)?;
-            }
+    pub fn place_mines(&mut self, mines: &[(usize, usize)]) {
+        for (x, y) in mines {
+            self.grid[*x][*y].mine = true;
         }
-        Ok(())
+        self.mine_count = mines.len();
+        self.mines_loaded = true;
     }
 
-    pub fn click(
-        &mut self,
-        pos: Vec2,
-        players: &Vec<Rc<Player>>,
-        players_alive: usize,
-        curr_player: &mut usize,
-    ) -> ClickResult {
-        if !self.mines_loaded {
-            self.gen_mines(pos);
-        }
-        let cl = self.clone();
-        let (mut x, mut y) = (0, 0);
-        for i in &mut self.grid {
-            for j in i {
-                if j.is_inside(pos) {
-                    if !j.marked && j.display == None {
-                        j.display = Some(cl.count_mines(x, y));
-                        if j.mine {
-                            return ClickResult::Mine;
-                        }
-                        j.player = Some(players[*curr_player].clone());
-                        *curr_player += 1;
-                        *curr_player %= players_alive;
-
-                        if j.display == Some(0) {
-                            for (nx, ny) in self.get_neighbours(x, y) {
-                                self.click(
-                                    self.grid[nx][ny].pos,
-                                    players,
-                                    players_alive,
-                                    curr_player,
-                                );
-                            }
-                        };
-                        return ClickResult::Ok;
-                    }
-                    return ClickResult::Invalid;
+    // fatal is the mine that eliminated the last player, if the game ended that way
+    pub fn end_game(&mut self, fatal: Option<(usize, usize)>) {
+        self.game_over = true;
+        self.fatal = fatal;
+    }
+
+    pub fn fatal(&self) -> Option<(usize, usize)> {
+        self.fatal
+    }
+
+    pub fn is_wrong_flag(&self, x: usize, y: usize) -> bool {
+        self.game_over && self.grid[x][y].marked && !self.grid[x][y].mine
+    }
+
+    pub fn is_game_over(&self) -> bool {
+        self.game_over
+    }
+
+    pub fn mines(&self) -> Vec<(usize, usize)> {
+        let mut res = vec![];
+        for (x, i) in self.grid.iter().enumerate() {
+            for (y, j) in i.iter().enumerate() {
+                if j.mine {
+                    res.push((x, y));
                 }
-                y += 1;
             }
-            y = 0;
-            x += 1;
         }
-        ClickResult::Invalid
+        res
     }
 
-    pub fn mark(&mut self, pos: Vec2) {
-        for i in &mut self.grid {
-            for j in i {
-                if j.is_inside(pos) && j.display == None {
-                    j.marked ^= true;
-                    return;
+    pub fn hidden_mines(&self) -> Vec<(usize, usize)> {
+        let mut res = vec![];
+        for (x, i) in self.grid.iter().enumerate() {
+            for (y, j) in i.iter().enumerate() {
+                if j.mine && j.display.is_none() && !j.marked {
+                    res.push((x, y));
                 }
             }
         }
+        res
+    }
+
+    // Cells revealed since the last call, in reveal order
+    pub fn take_revealed(&mut self) -> Vec<(usize, usize)> {
+        std::mem::take(&mut self.revealed)
+    }
+
+    // What the players may see of the board
+    pub fn view(&self) -> PlayerView {
+        let (size_x, size_y) = self.size();
+        let mut view = PlayerView::new(size_x, size_y, self.mine_count);
+        for x in 0..size_x {
+            for y in 0..size_y {
+                view.set_cell(x, y, self.cell_state(x, y));
+            }
+        }
+        if self.game_over {
+            view.end_game(self.fatal);
+        }
+        view
+    }
+
+    // Unrevealed mines are only shown once the game is over
+    fn cell_state(&self, x: usize, y: usize) -> CellState {
+        let tile = &self.grid[x][y];
+        if tile.mine && (tile.display.is_some() || self.game_over) {
+            CellState::Mine {
+                hit_by: tile
+                    .hit_by
+                    .as_ref()
+                    .and_then(|p| p.upgrade())
+                    .map(|p| p.slot),
+                flagged: tile.marked,
+            }
+        } else if let Some(count) = tile.display {
+            CellState::Revealed {
+                count,
+                by: tile.player.as_ref().map_or(0, |p| p.slot),
+            }
+        } else if tile.marked {
+            CellState::Flagged
+        } else {
+            CellState::Covered
+        }
     }
 
-    fn get_neighbours(&self, x: usize, y: usize) -> Vec<(usize, usize)> {
-        let mut res = vec![];
+    pub fn set_cell_state(&mut self, x: usize, y: usize, state: CellState, players: &[Rc<Player>]) {
+        let player = |slot: usize| players.iter().find(|p| p.slot == slot);
+        let tile = &mut self.grid[x][y];
+        match state {
+            CellState::Covered => tile.marked = false,
+            CellState::Flagged => tile.marked = true,
+            CellState::Revealed { count, by } => {
+                tile.marked = false;
+                tile.display = Some(count);
+                tile.player = player(by).cloned();
+            }
+            CellState::Mine { hit_by, flagged } => {
+                tile.mine = true;
+                tile.marked = flagged;
+                if let Some(p) = hit_by.and_then(player) {
+                    tile.display = Some(0);
+                    tile.hit_by = Some(Rc::downgrade(p));
+                }
+            }
+        }
+    }
 
-        let size_x = self.grid.len();
-        let size_y = self.grid[0].len();
+    // Mines that were hit stay revealed and count as flags
+    pub fn is_flagged(&self, x: usize, y: usize) -> bool {
+        let tile = &self.grid[x][y];
+        tile.marked || (tile.mine && tile.display.is_some())
+    }
 
-        if x > 0 {
-            res.push((x - 1, y));
+    pub fn is_satisfied(&self, x: usize, y: usize) -> bool {
+        match self.grid[x][y].display {
+            Some(num) if !self.grid[x][y].mine => {
+                self.get_neighbours(x, y)
+                    .iter()
+                    .filter(|(nx, ny)| self.is_flagged(*nx, *ny))
+                    .count()
+                    == num
+            }
+            _ => false,
         }
+    }
 
-        if x + 1 < size_x {
-            res.push((x + 1, y));
+    pub fn size(&self) -> (usize, usize) {
+        (self.grid.len(), self.grid[0].len())
+    }
+
+    pub fn bounds(&self) -> (Vec2, Vec2) {
+        let mut min = Vec2::splat(f32::MAX);
+        let mut max = Vec2::splat(f32::MIN);
+        for i in &self.grid {
+            for j in i {
+                min = min.min(j.pos - Vec2::splat(j.size / 2.0));
+                max = max.max(j.pos + Vec2::splat(j.size / 2.0));
+            }
         }
+        (min, max)
+    }
 
-        if y > 0 {
-            if y % 2 == 0 {
-                if x > 0 {
-                    res.push((x - 1, y - 1));
-                }
-                res.push((x, y - 1));
-            } else {
-                if x + 1 < size_x {
-                    res.push((x + 1, y - 1));
+    pub fn tile(&self, x: usize, y: usize) -> &HexTile {
+        &self.grid[x][y]
+    }
+
+    pub fn tile_at(&self, pos: Vec2) -> Option<(usize, usize)> {
+        for (x, i) in self.grid.iter().enumerate() {
+            for (y, j) in i.iter().enumerate() {
+                if j.is_inside(pos) {
+                    return Some((x, y));
                 }
-                res.push((x, y - 1));
             }
         }
+        None
+    }
 
-        if y + 1 < size_y {
-            if y % 2 == 0 {
-                if x > 0 {
-                    res.push((x - 1, y + 1));
-                }
-                res.push((x, y + 1));
-            } else {
-                if x + 1 < size_x {
-                    res.push((x + 1, y + 1));
-                }
-                res.push((x, y + 1));
+    pub fn step(&self, x: usize, y: usize, dir: Direction) -> Option<(usize, usize)> {
+        step(self.size(), x, y, dir)
+    }
+
+    pub fn click(&mut self, pos: Vec2, player: &Rc<Player>) -> ClickResult {
+        match self.tile_at(pos) {
+            Some((x, y)) => self.reveal(x, y, player),
+            None => ClickResult::Invalid,
+        }
+    }
+
+    // Every cell of a flood fill is credited to the revealing player
+    pub fn reveal(&mut self, x: usize, y: usize, player: &Rc<Player>) -> ClickResult {
+        if !self.mines_loaded {
+            self.gen_mines(self.grid[x][y].pos);
+        }
+        let count = self.count_mines(x, y);
+        let tile = &mut self.grid[x][y];
+        if tile.marked || tile.display.is_some() {
+            return ClickResult::Invalid;
+        }
+        tile.display = Some(count);
+        let mine = tile.mine;
+        self.revealed.push((x, y));
+        if mine {
+            tile.hit_by = Some(Rc::downgrade(player));
+            return ClickResult::Mine;
+        }
+        tile.player = Some(player.clone());
+
+        if count == 0 {
+            for (nx, ny) in self.get_neighbours(x, y) {
+                self.reveal(nx, ny, player);
             }
         }
+        ClickResult::Ok
+    }
+
+    pub fn chord(&mut self, x: usize, y: usize, player: &Rc<Player>) -> ClickResult {
+        let num = match self.grid[x][y].display {
+            Some(num) if !self.grid[x][y].mine => num,
+            _ => return ClickResult::Invalid,
+        };
+        let neighbours = self.get_neighbours(x, y);
+        let marked = neighbours
+            .iter()
+            .filter(|(nx, ny)| self.is_flagged(*nx, *ny))
+            .count();
+        if marked != num {
+            return ClickResult::Invalid;
+        }
 
+        let mut res = ClickResult::Invalid;
+        for (nx, ny) in neighbours {
+            match self.reveal(nx, ny, player) {
+                ClickResult::Mine => return ClickResult::Mine,
+                ClickResult::Ok => res = ClickResult::Ok,
+                ClickResult::Invalid => {}
+            }
+        }
         res
     }
 
+    pub fn mark(&mut self, pos: Vec2) {
+        if let Some((x, y)) = self.tile_at(pos) {
+            self.toggle_mark(x, y);
+        }
+    }
+
+    pub fn toggle_mark(&mut self, x: usize, y: usize) {
+        let tile = &mut self.grid[x][y];
+        if tile.display.is_none() {
+            tile.marked ^= true;
+        }
+    }
+
+    pub fn get_neighbours(&self, x: usize, y: usize) -> Vec<(usize, usize)> {
+        neighbours(self.size(), x, y)
+    }
+
     pub fn count_mines(&self, x: usize, y: usize) -> usize {
         let mut res = 0;
 
@@ -189,10 +363,100 @@ impl HexGrid {
     }
 }
 
+// Centre of the cell in board coordinates; odd rows are shifted half a cell right
+pub(crate) fn tile_pos(x: usize, y: usize) -> Vec2 {
+    Vec2::new(
+        x as f32 * TILE_SIZE + TILE_SIZE / 2.0 * if y.is_multiple_of(2) { 0.0 } else { 1.0 } + 33.0,
+        y as f32 * (TILE_SIZE / 2.0 / (PI / 6.0).cos() + TILE_SIZE / 2.0 * (PI / 6.0).tan()) + 33.0,
+    )
+}
+
+pub(crate) fn step(
+    size: (usize, usize),
+    x: usize,
+    y: usize,
+    dir: Direction,
+) -> Option<(usize, usize)> {
+    let odd = !y.is_multiple_of(2);
+    let (dx, dy) = match dir {
+        Direction::Up => (0, -1),
+        Direction::Down => (0, 1),
+        Direction::Left => (-1, 0),
+        Direction::Right => (1, 0),
+        Direction::UpLeft => (if odd { 0 } else { -1 }, -1),
+        Direction::UpRight => (if odd { 1 } else { 0 }, -1),
+        Direction::DownLeft => (if odd { 0 } else { -1 }, 1),
+        Direction::DownRight => (if odd { 1 } else { 0 }, 1),
+    };
+    let (nx, ny) = (x.checked_add_signed(dx)?, y.checked_add_signed(dy)?);
+    let (size_x, size_y) = size;
+    if nx < size_x && ny < size_y {
+        Some((nx, ny))
+    } else {
+        None
+    }
+}
+
+pub(crate) fn neighbours(size: (usize, usize), x: usize, y: usize) -> Vec<(usize, usize)> {
+    let mut res = vec![];
+
+    let (size_x, size_y) = size;
+
+    if x > 0 {
+        res.push((x - 1, y));
+    }
+
+    if x + 1 < size_x {
+        res.push((x + 1, y));
+    }
+
+    if y > 0 {
+        if y.is_multiple_of(2) {
+            if x > 0 {
+                res.push((x - 1, y - 1));
+            }
+            res.push((x, y - 1));
+        } else {
+            if x + 1 < size_x {
+                res.push((x + 1, y - 1));
+            }
+            res.push((x, y - 1));
+        }
+    }
+
+    if y + 1 < size_y {
+        if y.is_multiple_of(2) {
+            if x > 0 {
+                res.push((x - 1, y + 1));
+            }
+            res.push((x, y + 1));
+        } else {
+            if x + 1 < size_x {
+                res.push((x + 1, y + 1));
+            }
+            res.push((x, y + 1));
+        }
+    }
+
+    res
+}
+
 #[cfg(test)]
 mod tests {
+    use crate::ClickResult;
+    use crate::Direction;
     use crate::HexGrid;
+    use crate::Player;
+    use crate::Rgba;
     use glam::Vec2;
+    use std::rc::Rc;
+
+    fn players() -> Vec<Rc<Player>> {
+        vec![
+            Rc::new(Player::new(0, Rgba::GREEN, "Player 1".to_string())),
+            Rc::new(Player::new(1, Rgba::BLUE, "Player 2".to_string())),
+        ]
+    }
 
     // TEST tile_number
     #[test]
@@ -427,6 +691,32 @@ mod tests {
         }
     }
 
+    #[test]
+    fn gen_mines_seeded() {
+        let mines = |seed| {
+            let mut grid = HexGrid::with_seed(10, 10, 10, seed);
+            grid.gen_mines(Vec2::new(80.0, 120.0));
+            grid.grid
+                .iter()
+                .flatten()
+                .map(|t| t.mine)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(mines(42), mines(42));
+        assert_ne!(mines(42), mines(43));
+        assert_eq!(HexGrid::with_seed(10, 10, 10, 42).seed(), 42);
+    }
+
+    #[test]
+    fn gen_mines_too_many() {
+        let mut grid = HexGrid::with_seed(3, 3, 20, 42);
+        assert_eq!(grid.mine_number(), 8);
+        grid.gen_mines(Vec2::new(80.0, 120.0));
+        let cnt = grid.grid.iter().flatten().filter(|t| t.mine).count();
+        assert_eq!(cnt, 8);
+        assert!(!grid.grid[1][2].mine);
+    }
+
     // TEST mark
     #[test]
     fn mark0() {
@@ -444,4 +734,216 @@ mod tests {
         grid.mark(Vec2::new(200.0, 160.0));
         assert!(!grid.grid[3][3].marked);
     }
+
+    // TEST tile_at
+    #[test]
+    fn tile_at0() {
+        let grid = HexGrid::new(42, 42, 42);
+        assert_eq!(grid.tile_at(Vec2::new(200.0, 160.0)), Some((3, 3)));
+    }
+
+    #[test]
+    fn tile_at1() {
+        let grid = HexGrid::new(10, 10, 10);
+        assert_eq!(grid.tile_at(Vec2::new(-100.0, -100.0)), None);
+    }
+
+    // TEST step
+    #[test]
+    fn step_even() {
+        let grid = HexGrid::new(10, 10, 10);
+        assert_eq!(grid.step(4, 4, Direction::UpLeft), Some((3, 3)));
+        assert_eq!(grid.step(4, 4, Direction::UpRight), Some((4, 3)));
+        assert_eq!(grid.step(4, 4, Direction::Left), Some((3, 4)));
+        assert_eq!(grid.step(4, 4, Direction::Right), Some((5, 4)));
+        assert_eq!(grid.step(4, 4, Direction::DownLeft), Some((3, 5)));
+        assert_eq!(grid.step(4, 4, Direction::DownRight), Some((4, 5)));
+    }
+
+    #[test]
+    fn step_odd() {
+        let grid = HexGrid::new(10, 10, 10);
+        assert_eq!(grid.step(5, 5, Direction::UpLeft), Some((5, 4)));
+        assert_eq!(grid.step(5, 5, Direction::UpRight), Some((6, 4)));
+        assert_eq!(grid.step(5, 5, Direction::Left), Some((4, 5)));
+        assert_eq!(grid.step(5, 5, Direction::Right), Some((6, 5)));
+        assert_eq!(grid.step(5, 5, Direction::DownLeft), Some((5, 6)));
+        assert_eq!(grid.step(5, 5, Direction::DownRight), Some((6, 6)));
+    }
+
+    #[test]
+    fn step_edge() {
+        let grid = HexGrid::new(10, 10, 10);
+        assert_eq!(grid.step(0, 0, Direction::UpLeft), None);
+        assert_eq!(grid.step(0, 2, Direction::Left), None);
+        assert_eq!(grid.step(9, 3, Direction::DownRight), None);
+        assert_eq!(grid.step(9, 9, Direction::DownLeft), None);
+    }
+
+    #[test]
+    fn step_column() {
+        let grid = HexGrid::new(10, 10, 10);
+        assert_eq!(grid.step(4, 4, Direction::Up), Some((4, 3)));
+        assert_eq!(grid.step(4, 4, Direction::Down), Some((4, 5)));
+        assert_eq!(grid.step(4, 0, Direction::Up), None);
+    }
+
+    // TEST bounds
+    #[test]
+    fn bounds0() {
+        let grid = HexGrid::new(2, 2, 1);
+        let (min, max) = grid.bounds();
+        assert!((min - Vec2::new(8.0, 8.0)).length() <= 0.001);
+        assert!((max.x - 133.0).abs() <= 0.001);
+    }
+
+    // TEST reveal
+    #[test]
+    fn reveal0() {
+        let players = players();
+        let mut grid = HexGrid::new(10, 10, 1);
+        grid.mines_loaded = true;
+        grid.grid[0][0].mine = true;
+
+        assert_eq!(grid.reveal(0, 1, &players[0]), ClickResult::Ok);
+        assert_eq!(grid.grid[0][1].display, Some(1));
+        assert_eq!(grid.grid[0][1].player.as_ref().map(|p| p.slot), Some(0));
+        assert_eq!(grid.reveal(0, 1, &players[0]), ClickResult::Invalid);
+        assert_eq!(grid.reveal(0, 0, &players[0]), ClickResult::Mine);
+    }
+
+    #[test]
+    fn reveal_flood() {
+        let players = players();
+        let mut grid = HexGrid::new(10, 10, 1);
+        grid.mines_loaded = true;
+        grid.grid[0][0].mine = true;
+
+        assert_eq!(grid.reveal(5, 5, &players[0]), ClickResult::Ok);
+        let mut cnt = 0;
+        for i in &grid.grid {
+            for j in i {
+                cnt += j.display.is_some() as usize;
+            }
+        }
+        assert_eq!(cnt, 99);
+        assert_eq!(Rc::strong_count(&players[0]), 100);
+        assert_eq!(Rc::strong_count(&players[1]), 1);
+    }
+
+    // TEST take_revealed
+    #[test]
+    fn take_revealed0() {
+        let players = players();
+        let mut grid = HexGrid::new(10, 10, 1);
+        grid.mines_loaded = true;
+        grid.grid[0][0].mine = true;
+
+        grid.reveal(0, 1, &players[0]);
+        assert_eq!(grid.take_revealed(), vec![(0, 1)]);
+        assert!(grid.take_revealed().is_empty());
+        grid.reveal(5, 5, &players[0]);
+        let revealed = grid.take_revealed();
+        assert_eq!(revealed.len(), 98);
+        assert_eq!(revealed[0], (5, 5));
+    }
+
+    // TEST hidden_mines
+    #[test]
+    fn hidden_mines0() {
+        let mut grid = HexGrid::new(10, 10, 3);
+        grid.mines_loaded = true;
+        grid.grid[0][0].mine = true;
+        grid.grid[1][0].mine = true;
+        grid.grid[2][0].mine = true;
+        grid.toggle_mark(1, 0);
+        grid.grid[2][0].display = Some(0);
+        assert_eq!(grid.hidden_mines(), vec![(0, 0)]);
+    }
+
+    // TEST is_wrong_flag
+    #[test]
+    fn is_wrong_flag0() {
+        let mut grid = HexGrid::new(10, 10, 1);
+        grid.place_mines(&[(0, 0)]);
+        grid.toggle_mark(0, 0);
+        grid.toggle_mark(5, 5);
+        assert!(!grid.is_wrong_flag(5, 5));
+
+        grid.end_game(None);
+        assert!(grid.is_wrong_flag(5, 5));
+        assert!(!grid.is_wrong_flag(0, 0));
+        assert!(!grid.is_wrong_flag(4, 4));
+    }
+
+    // TEST reveal
+    #[test]
+    fn reveal_records_hit() {
+        let players = players();
+        let mut grid = HexGrid::new(10, 10, 1);
+        grid.place_mines(&[(0, 0)]);
+        assert_eq!(grid.reveal(0, 0, &players[1]), ClickResult::Mine);
+        let hit_by = grid.grid[0][0].hit_by.as_ref().and_then(|p| p.upgrade());
+        assert_eq!(hit_by.map(|p| p.slot), Some(1));
+        assert_eq!(Rc::strong_count(&players[1]), 1);
+    }
+
+    // TEST is_satisfied
+    #[test]
+    fn is_satisfied0() {
+        let players = players();
+        let mut grid = HexGrid::new(10, 10, 1);
+        grid.mines_loaded = true;
+        grid.grid[4][4].mine = true;
+
+        assert!(!grid.is_satisfied(4, 5));
+        grid.reveal(4, 5, &players[0]);
+        assert!(!grid.is_satisfied(4, 5));
+        grid.toggle_mark(4, 4);
+        assert!(grid.is_satisfied(4, 5));
+        grid.toggle_mark(3, 5);
+        assert!(!grid.is_satisfied(4, 5));
+    }
+
+    // TEST chord
+    #[test]
+    fn chord0() {
+        let players = players();
+        let mut grid = HexGrid::new(10, 10, 1);
+        grid.mines_loaded = true;
+        grid.grid[4][4].mine = true;
+
+        grid.reveal(4, 5, &players[0]);
+        assert_eq!(grid.chord(4, 5, &players[0]), ClickResult::Invalid);
+        grid.toggle_mark(4, 4);
+        assert_eq!(grid.chord(4, 5, &players[0]), ClickResult::Ok);
+        assert_eq!(grid.grid[3][5].display, Some(1));
+        assert!(grid.grid[4][4].display.is_none());
+    }
+
+    #[test]
+    fn chord_wrong_mark() {
+        let players = players();
+        let mut grid = HexGrid::new(10, 10, 1);
+        grid.mines_loaded = true;
+        grid.grid[4][4].mine = true;
+
+        grid.reveal(4, 5, &players[0]);
+        grid.toggle_mark(3, 5);
+        assert_eq!(grid.chord(4, 5, &players[0]), ClickResult::Mine);
+    }
+
+    #[test]
+    fn chord_hit_mine() {
+        let players = players();
+        let mut grid = HexGrid::new(10, 10, 1);
+        grid.mines_loaded = true;
+        grid.grid[4][4].mine = true;
+
+        grid.reveal(4, 5, &players[0]);
+        assert_eq!(grid.reveal(4, 4, &players[0]), ClickResult::Mine);
+        assert!(grid.is_flagged(4, 4));
+        assert!(grid.is_satisfied(4, 5));
+        assert_eq!(grid.chord(4, 5, &players[0]), ClickResult::Ok);
+    }
 }
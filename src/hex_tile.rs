@@ -1,16 +1,8 @@
 use crate::Player;
-use ggez::graphics;
-use ggez::graphics::Color;
-use ggez::graphics::DrawMode;
-use ggez::graphics::Mesh;
-use ggez::graphics::PxScale;
-use ggez::graphics::Text;
-use ggez::graphics::TextFragment;
-use ggez::Context;
-use ggez::GameResult;
 use glam::Vec2;
 use std::f32::consts::PI;
 use std::rc::Rc;
+use std::rc::Weak;
 
 #[derive(Clone)]
 pub struct HexTile {
@@ -20,6 +12,8 @@ pub struct HexTile {
     pub size: f32,
     pub pos: Vec2,
     pub player: Option<Rc<Player>>,
+    // Weak so the mine does not count towards the player's score
+    pub hit_by: Option<Weak<Player>>,
 }
 
 impl HexTile {
@@ -31,10 +25,11 @@ impl HexTile {
             display: None,
             marked: false,
             player: None,
+            hit_by: None,
         }
     }
 
-    fn get_points(&self) -> Vec<Vec2> {
+    pub(crate) fn get_points(&self) -> Vec<Vec2> {
         let mut points = vec![];
 
         for i in 0..6 {
@@ -72,48 +67,6 @@ impl HexTile {
 
         true
     }
-
-    pub fn draw(&self, ctx: &mut Context) -> GameResult {
-        let points = self.get_points();
-
-        let inner = Mesh::new_polygon(
-            ctx,
-            DrawMode::fill(),
-            &points,
-            if self.display == None {
-                if self.marked {
-                    Color::RED
-                } else {
-                    Color::new(0.8, 0.8, 0.8, 1.0)
-                }
-            } else {
-                if let Some(p) = &self.player {
-                    p.color
-                } else {
-                    Color::BLACK
-                }
-            },
-        )?;
-        let border = Mesh::new_polygon(ctx, DrawMode::stroke(2.0), &points, Color::WHITE)?;
-        graphics::draw(ctx, &inner, (Vec2::new(0.0, 0.0),))?;
-        graphics::draw(ctx, &border, (Vec2::new(0.0, 0.0),))?;
-        if !self.mine {
-            if let Some(num) = self.display {
-                if num > 0 {
-                    let txt = Text::new(TextFragment {
-                        text: num.to_string(),
-                        color: Some(Color::BLACK),
-                        font: Some(graphics::Font::default()),
-                        scale: Some(PxScale::from(30.0)),
-                        ..Default::default()
-                    });
-                    graphics::draw(ctx, &txt, (self.pos - Vec2::new(7.0, 15.0),))?;
-                }
-            }
-        }
-
-        Ok(())
-    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,184 @@
+use crate::Direction;
+use crate::Emote;
+use ggez::event::Button;
+use ggez::input::keyboard::KeyCode;
+use std::f32::consts::PI;
+
+const STICK_DEADZONE: f32 = 0.5;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Action {
+    Move(Direction),
+    Reveal,
+    Flag,
+    Chord,
+    NextCamera,
+    PrevCamera,
+    NextTheme,
+    ToggleAnimations,
+    ToggleMute,
+    VolumeUp,
+    VolumeDown,
+    Pause,
+    // Network games only
+    Chat,
+    Emote(Emote),
+    ToggleChat,
+}
+
+impl Action {
+    pub fn from_key(key: KeyCode) -> Option<Self> {
+        match key {
+            KeyCode::Q | KeyCode::Numpad7 => Some(Action::Move(Direction::UpLeft)),
+            KeyCode::E | KeyCode::Numpad9 => Some(Action::Move(Direction::UpRight)),
+            KeyCode::A | KeyCode::Numpad4 => Some(Action::Move(Direction::Left)),
+            KeyCode::D | KeyCode::Numpad6 => Some(Action::Move(Direction::Right)),
+            KeyCode::Z | KeyCode::Numpad1 => Some(Action::Move(Direction::DownLeft)),
+            KeyCode::C | KeyCode::Numpad3 => Some(Action::Move(Direction::DownRight)),
+            KeyCode::S | KeyCode::Space | KeyCode::Numpad5 => Some(Action::Reveal),
+            KeyCode::W | KeyCode::F | KeyCode::Numpad8 | KeyCode::Numpad0 => Some(Action::Flag),
+            KeyCode::X | KeyCode::Numpad2 | KeyCode::NumpadEnter => Some(Action::Chord),
+            KeyCode::PageDown => Some(Action::NextCamera),
+            KeyCode::PageUp => Some(Action::PrevCamera),
+            KeyCode::T => Some(Action::NextTheme),
+            KeyCode::N => Some(Action::ToggleAnimations),
+            KeyCode::M => Some(Action::ToggleMute),
+            KeyCode::Equals | KeyCode::NumpadAdd => Some(Action::VolumeUp),
+            KeyCode::Minus | KeyCode::NumpadSubtract => Some(Action::VolumeDown),
+            KeyCode::Escape => Some(Action::Pause),
+            KeyCode::Return => Some(Action::Chat),
+            KeyCode::F1 => Some(Action::Emote(Emote::Hello)),
+            KeyCode::F2 => Some(Action::Emote(Emote::GoodLuck)),
+            KeyCode::F3 => Some(Action::Emote(Emote::NiceMove)),
+            KeyCode::F4 => Some(Action::Emote(Emote::Oops)),
+            KeyCode::F5 => Some(Action::Emote(Emote::Thanks)),
+            KeyCode::F6 => Some(Action::Emote(Emote::GoodGame)),
+            KeyCode::H => Some(Action::ToggleChat),
+            _ => None,
+        }
+    }
+
+    pub fn from_button(button: Button) -> Option<Self> {
+        match button {
+            Button::DPadUp => Some(Action::Move(Direction::Up)),
+            Button::DPadDown => Some(Action::Move(Direction::Down)),
+            Button::DPadLeft => Some(Action::Move(Direction::Left)),
+            Button::DPadRight => Some(Action::Move(Direction::Right)),
+            Button::South => Some(Action::Reveal),
+            Button::East => Some(Action::Flag),
+            Button::West | Button::North => Some(Action::Chord),
+            Button::RightTrigger => Some(Action::NextCamera),
+            Button::LeftTrigger => Some(Action::PrevCamera),
+            Button::Select => Some(Action::NextTheme),
+            Button::Start => Some(Action::Pause),
+            _ => None,
+        }
+    }
+
+    // y points up, as reported by gamepad sticks
+    pub fn from_stick(x: f32, y: f32) -> Option<Self> {
+        if x * x + y * y < STICK_DEADZONE * STICK_DEADZONE {
+            return None;
+        }
+        let sector = ((y.atan2(x) + 2.0 * PI + PI / 6.0) / (PI / 3.0)) as usize % 6;
+        Some(Action::Move(match sector {
+            0 => Direction::Right,
+            1 => Direction::UpRight,
+            2 => Direction::UpLeft,
+            3 => Direction::Left,
+            4 => Direction::DownLeft,
+            _ => Direction::DownRight,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Action;
+    use crate::Direction;
+    use crate::Emote;
+    use ggez::event::Button;
+    use ggez::input::keyboard::KeyCode;
+
+    // TEST from_key
+    #[test]
+    fn from_key_moves() {
+        assert_eq!(
+            Action::from_key(KeyCode::Q),
+            Some(Action::Move(Direction::UpLeft))
+        );
+        assert_eq!(
+            Action::from_key(KeyCode::Numpad3),
+            Some(Action::Move(Direction::DownRight))
+        );
+    }
+
+    #[test]
+    fn from_key_actions() {
+        assert_eq!(Action::from_key(KeyCode::S), Some(Action::Reveal));
+        assert_eq!(Action::from_key(KeyCode::W), Some(Action::Flag));
+        assert_eq!(Action::from_key(KeyCode::X), Some(Action::Chord));
+        assert_eq!(Action::from_key(KeyCode::Escape), Some(Action::Pause));
+        assert_eq!(Action::from_key(KeyCode::Return), Some(Action::Chat));
+        assert_eq!(
+            Action::from_key(KeyCode::F4),
+            Some(Action::Emote(Emote::Oops))
+        );
+        assert_eq!(Action::from_key(KeyCode::F12), None);
+    }
+
+    // TEST from_button
+    #[test]
+    fn from_button0() {
+        assert_eq!(
+            Action::from_button(Button::DPadUp),
+            Some(Action::Move(Direction::Up))
+        );
+        assert_eq!(Action::from_button(Button::South), Some(Action::Reveal));
+        assert_eq!(Action::from_button(Button::East), Some(Action::Flag));
+        assert_eq!(
+            Action::from_button(Button::RightTrigger),
+            Some(Action::NextCamera)
+        );
+        assert_eq!(Action::from_button(Button::Start), Some(Action::Pause));
+        assert_eq!(Action::from_button(Button::Mode), None);
+    }
+
+    // TEST from_stick
+    #[test]
+    fn from_stick_deadzone() {
+        assert_eq!(Action::from_stick(0.1, -0.2), None);
+    }
+
+    #[test]
+    fn from_stick_directions() {
+        assert_eq!(
+            Action::from_stick(1.0, 0.0),
+            Some(Action::Move(Direction::Right))
+        );
+        assert_eq!(
+            Action::from_stick(1.0, -0.1),
+            Some(Action::Move(Direction::Right))
+        );
+        assert_eq!(
+            Action::from_stick(0.5, 0.8),
+            Some(Action::Move(Direction::UpRight))
+        );
+        assert_eq!(
+            Action::from_stick(-0.5, 0.8),
+            Some(Action::Move(Direction::UpLeft))
+        );
+        assert_eq!(
+            Action::from_stick(-1.0, 0.0),
+            Some(Action::Move(Direction::Left))
+        );
+        assert_eq!(
+            Action::from_stick(-0.5, -0.8),
+            Some(Action::Move(Direction::DownLeft))
+        );
+        assert_eq!(
+            Action::from_stick(0.5, -0.8),
+            Some(Action::Move(Direction::DownRight))
+        );
+    }
+}
@@ -1,8 +1,145 @@
+#[cfg(feature = "gui")]
+mod animation;
+#[cfg(feature = "gui")]
+mod app;
+#[cfg(feature = "gui")]
+mod audio;
+#[cfg(feature = "gui")]
+mod camera;
+mod chat;
+mod color;
+mod game;
+#[cfg(feature = "gui")]
+mod game_scene;
 mod hex_grid;
 mod hex_tile;
+#[cfg(feature = "gui")]
+mod input;
+mod lobby;
+#[cfg(feature = "gui")]
+mod menu;
+#[cfg(feature = "gui")]
+mod menus;
+mod net;
 mod player;
+mod player_view;
+mod profile;
+mod protocol;
+#[cfg(feature = "gui")]
+mod scene;
+mod server;
+mod session;
+mod setup;
+#[cfg(feature = "gui")]
+mod symbol;
+#[cfg(feature = "gui")]
+mod theme;
+#[cfg(feature = "gui")]
+mod tile_batch;
 
+#[cfg(feature = "gui")]
+pub use animation::Animator;
+#[cfg(feature = "gui")]
+pub use app::manifest_resources;
+#[cfg(feature = "gui")]
+pub use app::App;
+#[cfg(feature = "gui")]
+pub use audio::AudioSettings;
+#[cfg(feature = "gui")]
+pub use audio::Sound;
+#[cfg(feature = "gui")]
+pub use audio::SoundBank;
+#[cfg(feature = "gui")]
+pub use camera::Camera;
+#[cfg(feature = "gui")]
+pub use camera::CameraMode;
+pub use chat::ChatLine;
+pub use chat::ChatLog;
+pub use chat::Emote;
+pub use chat::RateLimit;
+pub use chat::Said;
+pub use chat::MAX_CHAT;
+pub use color::Rgba;
+pub use game::Death;
+pub use game::Game;
+pub use game::GameEvent;
+pub use game::LifeRule;
+pub use game::TurnPolicy;
+#[cfg(feature = "gui")]
+pub use game_scene::GameScene;
 pub use hex_grid::ClickResult;
+pub use hex_grid::Direction;
 pub use hex_grid::HexGrid;
 pub use hex_tile::HexTile;
+#[cfg(feature = "gui")]
+pub use input::Action;
+#[cfg(feature = "gui")]
+pub use menu::Menu;
+#[cfg(feature = "gui")]
+pub use menu::MenuInput;
+#[cfg(feature = "gui")]
+pub use menus::HostMenu;
+#[cfg(feature = "gui")]
+pub use menus::JoinMenu;
+#[cfg(feature = "gui")]
+pub use menus::LobbyMenu;
+#[cfg(feature = "gui")]
+pub use menus::MainMenu;
+#[cfg(feature = "gui")]
+pub use menus::MenuScene;
+#[cfg(feature = "gui")]
+pub use menus::PauseMenu;
+#[cfg(feature = "gui")]
+pub use menus::ResultsMenu;
+#[cfg(feature = "gui")]
+pub use menus::RulesMenu;
+#[cfg(feature = "gui")]
+pub use menus::SettingsMenu;
+#[cfg(feature = "gui")]
+pub use menus::SetupMenu;
+#[cfg(feature = "gui")]
+pub use menus::WatchMenu;
+pub use net::Client;
+pub use net::Host;
+pub use net::Link;
 pub use player::Player;
+pub use player_view::PlayerView;
+#[cfg(feature = "gui")]
+pub use player_view::TileHints;
+pub use profile::Profile;
+pub use profile::MAX_NAME;
+pub use profile::PALETTE;
+pub use protocol::Cell;
+pub use protocol::CellState;
+pub use protocol::ClientMessage;
+pub use protocol::Move;
+pub use protocol::RoomInfo;
+pub use protocol::Seat;
+pub use protocol::ServerMessage;
+pub use protocol::Snapshot;
+pub use protocol::Update;
+pub use protocol::DEFAULT_PORT;
+pub use protocol::PROTOCOL_VERSION;
+#[cfg(feature = "gui")]
+pub use scene::Scene;
+#[cfg(feature = "gui")]
+pub use scene::SceneStack;
+#[cfg(feature = "gui")]
+pub use scene::SharedState;
+#[cfg(feature = "gui")]
+pub use scene::Transition;
+pub use server::Server;
+pub use server::ServerConfig;
+pub use session::Session;
+pub use setup::Difficulty;
+pub use setup::GameSetup;
+pub use setup::SetupError;
+pub use setup::MAX_PLAYERS;
+#[cfg(feature = "gui")]
+pub use symbol::Symbol;
+#[cfg(feature = "gui")]
+pub use theme::Theme;
+#[cfg(feature = "gui")]
+pub use theme::ThemeFonts;
+#[cfg(feature = "gui")]
+pub use tile_batch::TileBatch;
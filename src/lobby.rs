@@ -0,0 +1,216 @@
+use crate::profile::free_color;
+use crate::GameSetup;
+use crate::Profile;
+use crate::Rgba;
+use crate::Seat;
+
+// A player in a lobby, with whatever connection they came with
+pub(crate) trait Seated {
+    fn seat(&self) -> &Seat;
+    fn seat_mut(&mut self) -> &mut Seat;
+    // Lost the connection during the game and may still come back
+    fn away(&self) -> bool;
+}
+
+// Names, colours, readiness and the board of the next game, on a LAN host or a
+// server room. The first seat starts the game and so never has to get ready.
+pub(crate) struct Lobby<M> {
+    pub members: Vec<M>,
+    // A seat played on this machine instead of over a connection, always the first
+    pub local: Option<Seat>,
+    pub settings: Option<GameSetup>,
+}
+
+impl<M: Seated> Lobby<M> {
+    pub fn new(local: Option<Seat>) -> Self {
+        Self {
+            members: vec![],
+            local,
+            settings: None,
+        }
+    }
+
+    pub fn seats(&self) -> Vec<Seat> {
+        let members = self.members.iter().map(|m| Seat {
+            away: m.away(),
+            ..m.seat().clone()
+        });
+        let mut seats: Vec<_> = self.local.iter().cloned().chain(members).collect();
+        if let Some(first) = seats.first_mut() {
+            first.ready = true;
+        }
+        seats
+    }
+
+    // Names and colours indexed by slot, once the slots are renumbered
+    pub fn profiles(&self) -> Vec<Profile> {
+        self.seats().into_iter().map(|s| s.profile).collect()
+    }
+
+    pub fn all_ready(&self) -> bool {
+        self.seats().iter().all(|s| s.ready)
+    }
+
+    // The lowest free slot, with a colour nobody has yet
+    pub fn new_seat(&self) -> Seat {
+        let seats = self.seats();
+        let slot = (0..)
+            .find(|s| seats.iter().all(|seat| seat.slot != *s))
+            .unwrap_or(0);
+        let mut profile = Profile::new(slot);
+        profile.color = free_color(profile.color, &self.colors_except(slot), 1);
+        Seat {
+            slot,
+            profile,
+            ready: false,
+            away: false,
+        }
+    }
+
+    // Players keep their colour unless someone else already has it; true on a change
+    pub fn set_profile(&mut self, slot: usize, profile: Profile) -> bool {
+        let mut profile = profile.cleaned(slot);
+        profile.color = free_color(profile.color, &self.colors_except(slot), 1);
+        match self.seat_mut(slot) {
+            Some(seat) if seat.profile != profile => {
+                seat.profile = profile;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn set_ready(&mut self, slot: usize, ready: bool) -> bool {
+        match self.seat_mut(slot) {
+            Some(seat) if seat.ready != ready => {
+                seat.ready = ready;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    // Players have to get ready again for a different board
+    pub fn set_settings(&mut self, setup: GameSetup) -> bool {
+        if self.settings == Some(setup) {
+            return false;
+        }
+        self.settings = Some(setup);
+        self.members
+            .iter_mut()
+            .for_each(|m| m.seat_mut().ready = false);
+        true
+    }
+
+    // Slots are handed out again so they run from 0 to the number of players
+    pub fn renumber(&mut self) {
+        let first = usize::from(self.local.is_some());
+        for (i, member) in self.members.iter_mut().enumerate() {
+            member.seat_mut().slot = first + i;
+        }
+    }
+
+    fn seat_mut(&mut self, slot: usize) -> Option<&mut Seat> {
+        let members = self.members.iter_mut().map(|m| m.seat_mut());
+        self.local
+            .iter_mut()
+            .chain(members)
+            .find(|s| s.slot == slot)
+    }
+
+    fn colors_except(&self, slot: usize) -> Vec<Rgba> {
+        self.seats()
+            .into_iter()
+            .filter(|s| s.slot != slot)
+            .map(|s| s.profile.color)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::lobby::Lobby;
+    use crate::lobby::Seated;
+    use crate::GameSetup;
+    use crate::Profile;
+    use crate::Rgba;
+    use crate::Seat;
+
+    impl Seated for Seat {
+        fn seat(&self) -> &Seat {
+            self
+        }
+
+        fn seat_mut(&mut self) -> &mut Seat {
+            self
+        }
+
+        fn away(&self) -> bool {
+            false
+        }
+    }
+
+    fn lobby(players: usize) -> Lobby<Seat> {
+        let mut lobby = Lobby::new(None);
+        for _ in 0..players {
+            let seat = lobby.new_seat();
+            lobby.members.push(seat);
+        }
+        lobby
+    }
+
+    // TEST new_seat
+    #[test]
+    fn new_seat_free_slot() {
+        let mut lobby = lobby(3);
+        lobby.members.remove(1);
+        let seat = lobby.new_seat();
+        assert_eq!(seat.slot, 1);
+        assert_eq!(seat.profile.color, Rgba::BLUE);
+        assert!(!seat.ready);
+    }
+
+    // TEST set_profile
+    #[test]
+    fn set_profile_taken_color() {
+        let mut lobby = lobby(2);
+        let mut profile = Profile::new(1);
+        profile.color = Rgba::CYAN;
+        assert!(lobby.set_profile(1, profile.clone()));
+        profile.color = Rgba::GREEN;
+        assert!(lobby.set_profile(1, profile));
+        assert_eq!(lobby.seats()[1].profile.color, Rgba::BLUE);
+        assert!(!lobby.set_profile(1, Profile::new(1)));
+    }
+
+    // TEST set_settings
+    #[test]
+    fn set_settings_unready() {
+        let mut lobby = lobby(2);
+        lobby.set_ready(1, true);
+        assert!(lobby.all_ready());
+        assert!(lobby.set_settings(GameSetup::new(10, 10, 5, 2)));
+        assert!(!lobby.all_ready());
+        assert!(lobby.seats()[0].ready);
+        assert!(!lobby.set_settings(GameSetup::new(10, 10, 5, 2)));
+    }
+
+    // TEST renumber
+    #[test]
+    fn renumber_local_first() {
+        let mut lobby = Lobby::new(Some(Seat {
+            slot: 0,
+            profile: Profile::new(0),
+            ready: true,
+            away: false,
+        }));
+        for _ in 0..3 {
+            let seat = lobby.new_seat();
+            lobby.members.push(seat);
+        }
+        lobby.members.remove(0);
+        lobby.renumber();
+        let slots: Vec<_> = lobby.seats().iter().map(|s| s.slot).collect();
+        assert_eq!(slots, vec![0, 1, 2]);
+    }
+}
@@ -1,149 +1,54 @@
-use crate::event::MouseButton;
 use ggez::event;
-use ggez::graphics;
-use ggez::graphics::Color;
-use ggez::graphics::PxScale;
-use ggez::graphics::Text;
-use ggez::graphics::TextFragment;
-use ggez::{Context, GameResult};
-use glam::*;
-use hex_sweeper::ClickResult;
-use hex_sweeper::HexGrid;
-use hex_sweeper::Player;
-use std::rc::Rc;
+use ggez::GameResult;
+use hex_sweeper::manifest_resources;
+use hex_sweeper::App;
+use hex_sweeper::GameScene;
+use hex_sweeper::GameSetup;
+use hex_sweeper::MainMenu;
+use hex_sweeper::SceneStack;
+use hex_sweeper::Session;
+use hex_sweeper::Transition;
+use std::env;
+
+const USAGE: &str = "Usage: hex_sweeper [--difficulty beginner|intermediate|expert] \
+                     [--width N] [--height N] [--mines N] [--players N] [--best-of N] \
+                     [--lives N | --penalty N | --shield N] \
+                     [--turns one|opening|simultaneous] [--teams N]";
 
-struct MainState {
-    grid: HexGrid,
-    players: Vec<Rc<Player>>,
-    players_alive: usize,
-    curr_player: usize,
-}
-
-impl MainState {
-    fn new() -> GameResult<MainState> {
-        let grid = HexGrid::new(10, 10, 16);
-        let s = MainState {
-            grid,
-            players: vec![
-                Rc::new(Player::new(Color::GREEN, "Player 1".to_string())),
-                Rc::new(Player::new(Color::BLUE, "Player 2".to_string())),
-                Rc::new(Player::new(Color::YELLOW, "Player 3".to_string())),
-            ],
-            players_alive: 3,
-            curr_player: 0,
-        };
-        Ok(s)
-    }
-}
-
-impl event::EventHandler<ggez::GameError> for MainState {
-    fn update(&mut self, _ctx: &mut Context) -> GameResult {
-        Ok(())
+pub fn main() -> GameResult {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.iter().any(|a| a == "-h" || a == "--help") {
+        println!("{}", USAGE);
+        return Ok(());
     }
-
-    fn draw(&mut self, ctx: &mut Context) -> GameResult {
-        graphics::clear(ctx, [0.42, 0.42, 0.42, 1.0].into());
-        let mut cnt_revealed = 0;
-        for i in &self.players {
-            cnt_revealed += Rc::strong_count(&i) - 1;
+    let setup = match GameSetup::from_args(&args) {
+        Ok(setup) => setup,
+        Err(e) => {
+            eprintln!("{}", e);
+            eprintln!("{}", USAGE);
+            std::process::exit(2);
         }
+    };
 
-        self.grid.draw(ctx)?;
-        if self.players_alive > 0
-            && cnt_revealed < self.grid.tile_number() - self.grid.mine_number()
-        {
-            for i in 0..self.players.len() {
-                if i < self.players_alive {
-                    if i == self.curr_player {
-                        self.players[i].draw_active(
-                            ctx,
-                            Vec2::new(600 as f32, (i * 100) as f32 + 10.0),
-                            Rc::strong_count(&self.players[i]) - 1,
-                        )?;
-                    } else {
-                        self.players[i].draw_inactive(
-                            ctx,
-                            Vec2::new(600 as f32, (i * 100) as f32 + 10.0),
-                            Rc::strong_count(&self.players[i]) - 1,
-                        )?;
-                    }
-                } else {
-                    self.players[i].draw_dead(
-                        ctx,
-                        Vec2::new(600 as f32, (i * 100) as f32 + 10.0),
-                        Rc::strong_count(&self.players[i]) - 1,
-                    )?;
-                }
-            }
-        } else {
-            for i in 0..self.players.len() {
-                self.players[i].draw_active(
-                    ctx,
-                    Vec2::new(600 as f32, (i * 100) as f32 + 10.0),
-                    Rc::strong_count(&self.players[i]) - 1,
-                )?;
-            }
-            let txt = Text::new(TextFragment {
-                text: String::from("Game Over!"),
-                color: Some(Color::RED),
-                font: Some(graphics::Font::default()),
-                scale: Some(PxScale::from(50.0)),
-                ..Default::default()
-            });
-            graphics::draw(
-                ctx,
-                &txt,
-                (Vec2::new(
-                    610 as f32,
-                    (self.players.len() * 100) as f32 + 40.0,
-                ),),
-            )?;
-        }
-        graphics::present(ctx)?;
-        Ok(())
+    let mut cb = ggez::ContextBuilder::new("hexsweeper", "Dimo")
+        .window_setup(ggez::conf::WindowSetup::default().title("Hexsweeper"))
+        .window_mode(ggez::conf::WindowMode::default().dimensions(900.0, 600.0));
+    if let Some(dir) = manifest_resources() {
+        cb = cb.add_resource_path(dir);
     }
-
-    fn mouse_button_up_event(&mut self, _ctx: &mut Context, button: MouseButton, x: f32, y: f32) {
-        let mut cnt_revealed = 0;
-        for i in &self.players {
-            cnt_revealed += Rc::strong_count(&i) - 1;
-        }
-
-        if self.players_alive > 0
-            && cnt_revealed < self.grid.tile_number() - self.grid.mine_number()
-        {
-            if button == MouseButton::Left {
-                match self.grid.click(
-                    Vec2::new(x, y),
-                    &self.players,
-                    self.players_alive,
-                    &mut self.curr_player,
-                ) {
-                    ClickResult::Mine => {
-                        self.players_alive -= 1;
-                        let mut i = self.curr_player;
-                        while i < self.players_alive {
-                            self.players.swap(i, i + 1);
-                            i += 1;
-                        }
-                        if self.players_alive > 0 {
-                            self.curr_player %= self.players_alive;
-                        }
-                    }
-                    _ => {}
-                }
-            } else {
-                self.grid.mark(Vec2::new(x, y));
-            }
-        }
+    let (mut ctx, event_loop) = cb.build()?;
+    let mut app = App::new(&mut ctx)?;
+    let mut game = None;
+    // Options on the command line skip the menu and start a game right away
+    if let Some(setup) = setup {
+        let session = Session::new(setup);
+        game = Some(GameScene::new(&mut ctx, &app, session.new_game())?);
+        app.setup = setup;
+        app.session = Some(session);
+    }
+    let mut state = SceneStack::new(app, Box::new(MainMenu::new()));
+    if let Some(game) = game {
+        state.apply(Transition::Push(Box::new(game)));
     }
-}
-
-pub fn main() -> GameResult {
-    let cb = ggez::ContextBuilder::new("hexsweeper", "Dimo")
-        .window_setup(ggez::conf::WindowSetup::default().title("Hexsweeper"))
-        .window_mode(ggez::conf::WindowMode::default().dimensions(900.0, 460.0));
-    let (ctx, event_loop) = cb.build()?;
-    let state = MainState::new()?;
     event::run(ctx, event_loop, state)
 }
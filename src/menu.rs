@@ -0,0 +1,226 @@
+use crate::Theme;
+use ggez::event::Button;
+use ggez::graphics;
+use ggez::graphics::DrawMode;
+use ggez::graphics::PxScale;
+use ggez::graphics::Rect;
+use ggez::graphics::Text;
+use ggez::graphics::TextFragment;
+use ggez::input::keyboard::KeyCode;
+use ggez::Context;
+use ggez::GameResult;
+use glam::Vec2;
+
+const MENU_WIDTH: f32 = 480.0;
+const PADDING: f32 = 20.0;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum MenuInput {
+    Up,
+    Down,
+    Left,
+    Right,
+    Select,
+    Back,
+}
+
+impl MenuInput {
+    pub fn from_key(key: KeyCode) -> Option<Self> {
+        match key {
+            KeyCode::Up | KeyCode::W | KeyCode::Numpad8 => Some(MenuInput::Up),
+            KeyCode::Down | KeyCode::S | KeyCode::Numpad2 => Some(MenuInput::Down),
+            KeyCode::Left | KeyCode::A | KeyCode::Numpad4 => Some(MenuInput::Left),
+            KeyCode::Right | KeyCode::D | KeyCode::Numpad6 => Some(MenuInput::Right),
+            KeyCode::Return | KeyCode::Space | KeyCode::NumpadEnter => Some(MenuInput::Select),
+            KeyCode::Escape | KeyCode::Back => Some(MenuInput::Back),
+            _ => None,
+        }
+    }
+
+    pub fn from_button(button: Button) -> Option<Self> {
+        match button {
+            Button::DPadUp => Some(MenuInput::Up),
+            Button::DPadDown => Some(MenuInput::Down),
+            Button::DPadLeft => Some(MenuInput::Left),
+            Button::DPadRight => Some(MenuInput::Right),
+            Button::South | Button::Start => Some(MenuInput::Select),
+            Button::East | Button::Select => Some(MenuInput::Back),
+            _ => None,
+        }
+    }
+}
+
+// A vertical list of items; lines are shown between the title and the items and can't be selected
+pub struct Menu {
+    pub title: String,
+    pub lines: Vec<String>,
+    pub items: Vec<String>,
+    pub selected: usize,
+    rects: Vec<Rect>,
+}
+
+impl Menu {
+    pub fn new(title: &str, items: Vec<String>) -> Self {
+        Self {
+            title: title.to_string(),
+            lines: vec![],
+            items,
+            selected: 0,
+            rects: vec![],
+        }
+    }
+
+    // Moves the selection and passes every other input on to the scene
+    pub fn navigate(&mut self, input: MenuInput) -> Option<MenuInput> {
+        let len = self.items.len();
+        if len == 0 {
+            return Some(input);
+        }
+        match input {
+            MenuInput::Up => self.selected = (self.selected + len - 1) % len,
+            MenuInput::Down => self.selected = (self.selected + 1) % len,
+            _ => return Some(input),
+        }
+        None
+    }
+
+    // Uses the layout of the last draw
+    pub fn item_at(&self, pos: Vec2) -> Option<usize> {
+        self.rects.iter().position(|r| r.contains(pos))
+    }
+
+    pub fn hover(&mut self, pos: Vec2) {
+        if let Some(i) = self.item_at(pos) {
+            self.selected = i;
+        }
+    }
+
+    fn text(&self, theme: &Theme, text: &str, size: f32) -> Text {
+        Text::new(TextFragment {
+            text: text.to_string(),
+            color: Some(theme.text),
+            font: Some(theme.fonts.text),
+            scale: Some(PxScale::from(size)),
+        })
+    }
+
+    pub fn draw(&mut self, ctx: &mut Context, theme: &Theme, overlay: bool) -> GameResult {
+        let screen = graphics::screen_coordinates(ctx);
+        if overlay {
+            let cover =
+                graphics::Mesh::new_rectangle(ctx, DrawMode::fill(), screen, theme.panel_cover)?;
+            graphics::draw(ctx, &cover, (Vec2::new(0.0, 0.0),))?;
+        } else {
+            graphics::clear(ctx, theme.background);
+        }
+
+        let row = theme.text_size * 1.6;
+        let line_row = theme.text_size * 0.9;
+        let title_row = theme.title_size * 1.2;
+        let height = title_row
+            + self.lines.len() as f32 * line_row
+            + self.items.len() as f32 * row
+            + 2.0 * PADDING;
+        let left = (screen.w - MENU_WIDTH) / 2.0;
+        let mut y = ((screen.h - height) / 2.0).max(0.0);
+
+        let panel = graphics::Mesh::new_rectangle(
+            ctx,
+            DrawMode::fill(),
+            Rect::new(left, y, MENU_WIDTH, height),
+            theme.covered,
+        )?;
+        graphics::draw(ctx, &panel, (Vec2::new(0.0, 0.0),))?;
+        y += PADDING;
+
+        let title = self.text(theme, &self.title, theme.title_size);
+        let x = left + (MENU_WIDTH - title.width(ctx)) / 2.0;
+        graphics::draw(ctx, &title, (Vec2::new(x, y),))?;
+        y += title_row;
+
+        for line in &self.lines {
+            let txt = self.text(theme, line, theme.text_size * 0.7);
+            graphics::draw(ctx, &txt, (Vec2::new(left + PADDING, y),))?;
+            y += line_row;
+        }
+
+        self.rects.clear();
+        for (i, item) in self.items.iter().enumerate() {
+            let rect = Rect::new(left, y, MENU_WIDTH, row);
+            if i == self.selected {
+                let highlight =
+                    graphics::Mesh::new_rectangle(ctx, DrawMode::fill(), rect, theme.preview)?;
+                graphics::draw(ctx, &highlight, (Vec2::new(0.0, 0.0),))?;
+                let border =
+                    graphics::Mesh::new_rectangle(ctx, DrawMode::stroke(3.0), rect, theme.cursor)?;
+                graphics::draw(ctx, &border, (Vec2::new(0.0, 0.0),))?;
+            }
+            let txt = self.text(theme, item, theme.text_size);
+            let x = left + (MENU_WIDTH - txt.width(ctx)) / 2.0;
+            graphics::draw(
+                ctx,
+                &txt,
+                (Vec2::new(x, y + (row - txt.height(ctx)) / 2.0),),
+            )?;
+            self.rects.push(rect);
+            y += row;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Menu;
+    use crate::MenuInput;
+    use ggez::event::Button;
+    use ggez::input::keyboard::KeyCode;
+
+    fn menu() -> Menu {
+        Menu::new(
+            "Menu",
+            vec!["One".to_string(), "Two".to_string(), "Three".to_string()],
+        )
+    }
+
+    // TEST navigate
+    #[test]
+    fn navigate_wraps() {
+        let mut menu = menu();
+        assert_eq!(menu.navigate(MenuInput::Up), None);
+        assert_eq!(menu.selected, 2);
+        assert_eq!(menu.navigate(MenuInput::Down), None);
+        assert_eq!(menu.selected, 0);
+    }
+
+    #[test]
+    fn navigate_passes_on() {
+        let mut menu = menu();
+        menu.navigate(MenuInput::Down);
+        assert_eq!(menu.navigate(MenuInput::Select), Some(MenuInput::Select));
+        assert_eq!(menu.navigate(MenuInput::Left), Some(MenuInput::Left));
+        assert_eq!(menu.selected, 1);
+    }
+
+    // TEST from_key
+    #[test]
+    fn from_key0() {
+        assert_eq!(MenuInput::from_key(KeyCode::Up), Some(MenuInput::Up));
+        assert_eq!(
+            MenuInput::from_key(KeyCode::Return),
+            Some(MenuInput::Select)
+        );
+        assert_eq!(MenuInput::from_key(KeyCode::Escape), Some(MenuInput::Back));
+        assert_eq!(MenuInput::from_key(KeyCode::Q), None);
+    }
+
+    // TEST from_button
+    #[test]
+    fn from_button0() {
+        assert_eq!(
+            MenuInput::from_button(Button::South),
+            Some(MenuInput::Select)
+        );
+        assert_eq!(MenuInput::from_button(Button::East), Some(MenuInput::Back));
+    }
+}
@@ -0,0 +1,1141 @@
+use crate::App;
+use crate::Client;
+use crate::ClientMessage;
+use crate::Game;
+use crate::GameScene;
+use crate::GameSetup;
+use crate::Host;
+use crate::LifeRule;
+use crate::Link;
+use crate::Menu;
+use crate::MenuInput;
+use crate::Rgba;
+use crate::Scene;
+use crate::Seat;
+use crate::Session;
+use crate::Transition;
+use crate::TurnPolicy;
+use crate::DEFAULT_PORT;
+use crate::MAX_NAME;
+use crate::MAX_PLAYERS;
+use ggez::event::Button;
+use ggez::event::GamepadId;
+use ggez::event::MouseButton;
+use ggez::input::keyboard::KeyCode;
+use ggez::Context;
+use ggez::GameResult;
+use glam::Vec2;
+
+// Scenes that are a single menu; input is translated to MenuInput and labels are
+// refreshed from the shared state before every draw
+pub trait MenuScene {
+    fn menu(&mut self) -> &mut Menu;
+
+    fn activate(&mut self, ctx: &mut Context, app: &mut App, input: MenuInput) -> Transition<App>;
+
+    fn refresh(&mut self, _app: &App) {}
+
+    fn is_overlay(&self) -> bool {
+        false
+    }
+
+    // While editing, typed characters go to the scene instead of navigating the menu
+    fn is_editing(&self) -> bool {
+        false
+    }
+
+    fn type_char(&mut self, _app: &mut App, _character: char) {}
+
+    fn backspace(&mut self, _app: &mut App) {}
+
+    fn menu_input(
+        &mut self,
+        ctx: &mut Context,
+        app: &mut App,
+        input: MenuInput,
+    ) -> Transition<App> {
+        match self.menu().navigate(input) {
+            Some(input) => self.activate(ctx, app, input),
+            None => Transition::None,
+        }
+    }
+}
+
+impl<T: MenuScene> Scene<App> for T {
+    fn draw(&mut self, ctx: &mut Context, app: &mut App) -> GameResult {
+        self.refresh(app);
+        let overlay = MenuScene::is_overlay(self);
+        self.menu().draw(ctx, app.theme(), overlay)
+    }
+
+    fn is_overlay(&self) -> bool {
+        MenuScene::is_overlay(self)
+    }
+
+    fn mouse_button_up_event(
+        &mut self,
+        ctx: &mut Context,
+        app: &mut App,
+        button: MouseButton,
+        x: f32,
+        y: f32,
+    ) -> Transition<App> {
+        if self.menu().item_at(Vec2::new(x, y)).is_none() {
+            // Clicking next to an overlay closes it
+            return if MenuScene::is_overlay(self) && button == MouseButton::Left {
+                self.menu_input(ctx, app, MenuInput::Back)
+            } else {
+                Transition::None
+            };
+        }
+        self.menu().hover(Vec2::new(x, y));
+        match button {
+            MouseButton::Left => self.menu_input(ctx, app, MenuInput::Select),
+            MouseButton::Right => self.menu_input(ctx, app, MenuInput::Left),
+            _ => Transition::None,
+        }
+    }
+
+    fn mouse_motion_event(
+        &mut self,
+        _ctx: &mut Context,
+        _app: &mut App,
+        x: f32,
+        y: f32,
+        _dx: f32,
+        _dy: f32,
+    ) {
+        self.menu().hover(Vec2::new(x, y));
+    }
+
+    fn key_down_event(
+        &mut self,
+        ctx: &mut Context,
+        app: &mut App,
+        keycode: KeyCode,
+    ) -> Transition<App> {
+        if self.is_editing() {
+            match keycode {
+                KeyCode::Back => {
+                    self.backspace(app);
+                    return Transition::None;
+                }
+                KeyCode::Up | KeyCode::Down | KeyCode::Return | KeyCode::Escape => {}
+                KeyCode::NumpadEnter => {}
+                _ => return Transition::None,
+            }
+        }
+        match MenuInput::from_key(keycode) {
+            Some(input) => self.menu_input(ctx, app, input),
+            None => Transition::None,
+        }
+    }
+
+    fn text_input_event(&mut self, _ctx: &mut Context, app: &mut App, character: char) {
+        if self.is_editing() && !character.is_control() {
+            self.type_char(app, character);
+        }
+    }
+
+    fn gamepad_button_down_event(
+        &mut self,
+        ctx: &mut Context,
+        app: &mut App,
+        btn: Button,
+        _id: GamepadId,
+    ) -> Transition<App> {
+        match MenuInput::from_button(btn) {
+            Some(input) => self.menu_input(ctx, app, input),
+            None => Transition::None,
+        }
+    }
+}
+
+// Starts the next round of the session on top of the main menu
+fn start<F>(ctx: &mut Context, app: &mut App, next: F) -> Transition<App>
+where
+    F: FnOnce(&mut Session),
+{
+    let session = app.session.get_or_insert_with(|| Session::new(app.setup));
+    next(session);
+    // Players keep the names and colours they picked in the lobby
+    let game = match &mut app.net {
+        Some(Link::Host(host)) => {
+            let game = host.start(&session.setup, &session.order, session.seed);
+            session.profiles = host.profiles();
+            game
+        }
+        _ => session.new_game(),
+    };
+    match GameScene::new(ctx, app, game) {
+        Ok(scene) => Transition::Reset(vec![Box::new(MainMenu::new()), Box::new(scene)]),
+        Err(e) => {
+            eprintln!("Failed to start game: {}", e);
+            Transition::None
+        }
+    }
+}
+
+// Leaving a game also leaves the network game it was part of
+fn main_menu(app: &mut App) -> Transition<App> {
+    app.net = None;
+    Transition::Reset(vec![Box::new(MainMenu::new())])
+}
+
+fn labels(items: &[&str]) -> Vec<String> {
+    items.iter().map(|s| s.to_string()).collect()
+}
+
+fn on_off(on: bool) -> &'static str {
+    if on {
+        "On"
+    } else {
+        "Off"
+    }
+}
+
+fn board_line(setup: &GameSetup) -> String {
+    format!(
+        "{} x {} board, {} mines",
+        setup.width, setup.height, setup.mines
+    )
+}
+
+fn seat_lines(seats: &[Seat]) -> Vec<String> {
+    let line = |s: &Seat| {
+        let ready = match (s.away, s.ready) {
+            (true, _) => "Away",
+            (false, true) => "Ready",
+            (false, false) => "Not ready",
+        };
+        format!(
+            "{} ({}) - {}",
+            s.profile.name,
+            s.profile.color_name(),
+            ready
+        )
+    };
+    seats.iter().map(line).collect()
+}
+
+// Colours of everyone else in the lobby
+fn taken_colors(seats: &[Seat], slot: Option<usize>) -> Vec<Rgba> {
+    seats
+        .iter()
+        .filter(|s| Some(s.slot) != slot)
+        .map(|s| s.profile.color)
+        .collect()
+}
+
+fn type_name(app: &mut App, character: char) {
+    if app.profile.name.chars().count() < MAX_NAME {
+        app.profile.name.push(character);
+    }
+}
+
+// Sends to the host or server this client joined
+fn send(app: &mut App, msg: &ClientMessage) {
+    if let Some(Link::Client(client)) = &mut app.net {
+        client.send(msg);
+    }
+}
+
+// Left lowers a value, Right and Select raise it
+fn delta(input: MenuInput) -> isize {
+    if input == MenuInput::Left {
+        -1
+    } else {
+        1
+    }
+}
+
+pub struct MainMenu {
+    menu: Menu,
+}
+
+impl MainMenu {
+    pub fn new() -> Self {
+        Self {
+            menu: Menu::new(
+                "HexSweeper",
+                labels(&[
+                    "New game",
+                    "Host game",
+                    "Join game",
+                    "Watch game",
+                    "Settings",
+                    "Quit",
+                ]),
+            ),
+        }
+    }
+}
+
+impl Default for MainMenu {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MenuScene for MainMenu {
+    fn menu(&mut self) -> &mut Menu {
+        &mut self.menu
+    }
+
+    fn refresh(&mut self, app: &App) {
+        self.menu.lines = app.net_error.iter().cloned().collect();
+    }
+
+    fn activate(&mut self, _ctx: &mut Context, app: &mut App, input: MenuInput) -> Transition<App> {
+        if input != MenuInput::Select {
+            return Transition::None;
+        }
+        app.net_error = None;
+        match self.menu.selected {
+            0 => Transition::Push(Box::new(SetupMenu::new())),
+            1 => match Host::bind(("0.0.0.0", DEFAULT_PORT), MAX_PLAYERS) {
+                Ok(host) => {
+                    app.net = Some(Link::Host(host));
+                    Transition::Push(Box::new(HostMenu::new()))
+                }
+                Err(e) => {
+                    app.net_error = Some(format!("Failed to host: {}", e));
+                    Transition::None
+                }
+            },
+            2 => Transition::Push(Box::new(JoinMenu::new())),
+            3 => Transition::Push(Box::new(WatchMenu::new())),
+            4 => Transition::Push(Box::new(SettingsMenu::new())),
+            _ => Transition::Pop,
+        }
+    }
+}
+
+// Waits for players to join the game hosted on this machine and to get ready
+pub struct HostMenu {
+    menu: Menu,
+}
+
+impl HostMenu {
+    pub fn new() -> Self {
+        Self {
+            menu: Menu::new("Host game", vec![]),
+        }
+    }
+}
+
+impl Default for HostMenu {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MenuScene for HostMenu {
+    fn menu(&mut self) -> &mut Menu {
+        &mut self.menu
+    }
+
+    fn refresh(&mut self, app: &App) {
+        let setup = &app.setup;
+        let host = match &app.net {
+            Some(Link::Host(host)) => host,
+            _ => return,
+        };
+        self.menu.items = vec![
+            format!("Name: {}", app.profile.name),
+            format!("Colour: {}", host.profile().color_name()),
+            format!("Width: {}", setup.width),
+            format!("Height: {}", setup.height),
+            format!("Mines: {}", setup.mines),
+            "Rules".to_string(),
+            "Start".to_string(),
+            "Back".to_string(),
+        ];
+        self.menu.lines = vec![
+            format!("Listening on port {}", DEFAULT_PORT),
+            format!("{} of {} players connected", host.players(), MAX_PLAYERS),
+            board_line(setup),
+        ];
+        self.menu.lines.extend(seat_lines(&host.seats()));
+        if !host.all_ready() {
+            self.menu
+                .lines
+                .push("Waiting for everyone to get ready".to_string());
+        }
+    }
+
+    // The name is edited while it is selected
+    fn is_editing(&self) -> bool {
+        self.menu.selected == 0
+    }
+
+    fn type_char(&mut self, app: &mut App, character: char) {
+        type_name(app, character);
+    }
+
+    fn backspace(&mut self, app: &mut App) {
+        app.profile.name.pop();
+    }
+
+    fn activate(&mut self, ctx: &mut Context, app: &mut App, input: MenuInput) -> Transition<App> {
+        let (players, ready) = match &app.net {
+            Some(Link::Host(host)) => (host.players(), host.all_ready()),
+            _ => (0, false),
+        };
+        let setup = &mut app.setup;
+        match (self.menu.selected, input) {
+            (_, MenuInput::Back) | (7, MenuInput::Select) => {
+                app.net = None;
+                return Transition::Pop;
+            }
+            (1, _) => {
+                if let Some(Link::Host(host)) = &app.net {
+                    app.profile.color = host.profile().color;
+                    let taken = taken_colors(&host.seats(), Some(0));
+                    app.profile.cycle_color(&taken, delta(input));
+                }
+            }
+            (2, _) => setup.change_width(delta(input)),
+            (3, _) => setup.change_height(delta(input)),
+            (4, _) => setup.change_mines(delta(input)),
+            (5, MenuInput::Select) => return Transition::Push(Box::new(RulesMenu::new())),
+            (6, MenuInput::Select) if players > 1 && ready => {
+                // Everyone connected plays
+                app.setup.set_players(players);
+                app.session = Some(Session::new(app.setup));
+                return start(ctx, app, |_| {});
+            }
+            _ => {}
+        }
+        Transition::None
+    }
+}
+
+pub struct JoinMenu {
+    menu: Menu,
+    address: String,
+}
+
+impl JoinMenu {
+    pub fn new() -> Self {
+        Self {
+            menu: Menu::new("Join game", vec![]),
+            address: format!("127.0.0.1:{}", DEFAULT_PORT),
+        }
+    }
+}
+
+impl Default for JoinMenu {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MenuScene for JoinMenu {
+    fn menu(&mut self) -> &mut Menu {
+        &mut self.menu
+    }
+
+    fn refresh(&mut self, app: &App) {
+        self.menu.lines = app.net_error.iter().cloned().collect();
+        self.menu.items = vec![format!("Address: {}", self.address), "Join".to_string()];
+        if let Some((addr, _)) = &app.rejoin {
+            self.menu.items.push(format!("Rejoin {}", addr));
+        }
+        self.menu.items.push("Back".to_string());
+    }
+
+    // The address is edited while it is selected
+    fn is_editing(&self) -> bool {
+        self.menu.selected == 0
+    }
+
+    fn type_char(&mut self, _app: &mut App, character: char) {
+        self.address.push(character);
+    }
+
+    fn backspace(&mut self, _app: &mut App) {
+        self.address.pop();
+    }
+
+    fn activate(&mut self, _ctx: &mut Context, app: &mut App, input: MenuInput) -> Transition<App> {
+        let client = match (self.menu.selected, input, &app.rejoin) {
+            (0 | 1, MenuInput::Select, _) => Client::connect(&self.address).map(|mut client| {
+                client.send(&ClientMessage::Profile(app.profile.clone()));
+                client
+            }),
+            // Takes the place in the game this client lost the connection to
+            (2, MenuInput::Select, Some((addr, token))) => Client::resume(addr, token),
+            (_, MenuInput::Back, _) | (2 | 3, MenuInput::Select, _) => return Transition::Pop,
+            _ => return Transition::None,
+        };
+        match client {
+            Ok(client) => {
+                app.net_error = None;
+                app.rejoin = None;
+                app.net = Some(Link::Client(client));
+                Transition::Replace(Box::new(LobbyMenu::new()))
+            }
+            Err(e) => {
+                app.net_error = Some(format!("Failed to join: {}", e));
+                Transition::None
+            }
+        }
+    }
+}
+
+// Watches a room on a dedicated server; a caster key also shows the mines
+pub struct WatchMenu {
+    menu: Menu,
+    // Address, room and caster key, edited while selected
+    fields: [String; 3],
+}
+
+impl WatchMenu {
+    pub fn new() -> Self {
+        Self {
+            menu: Menu::new("Watch game", vec![]),
+            fields: [
+                format!("127.0.0.1:{}", DEFAULT_PORT),
+                String::new(),
+                String::new(),
+            ],
+        }
+    }
+}
+
+impl Default for WatchMenu {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MenuScene for WatchMenu {
+    fn menu(&mut self) -> &mut Menu {
+        &mut self.menu
+    }
+
+    fn refresh(&mut self, app: &App) {
+        self.menu.lines = app.net_error.iter().cloned().collect();
+        let [address, room, key] = &self.fields;
+        self.menu.items = vec![
+            format!("Address: {}", address),
+            format!("Room: {}", room),
+            format!("Caster key: {}", "*".repeat(key.chars().count())),
+            "Watch".to_string(),
+            "Back".to_string(),
+        ];
+    }
+
+    fn is_editing(&self) -> bool {
+        self.menu.selected < self.fields.len()
+    }
+
+    fn type_char(&mut self, _app: &mut App, character: char) {
+        self.fields[self.menu.selected].push(character);
+    }
+
+    fn backspace(&mut self, _app: &mut App) {
+        self.fields[self.menu.selected].pop();
+    }
+
+    fn activate(&mut self, _ctx: &mut Context, app: &mut App, input: MenuInput) -> Transition<App> {
+        match (self.menu.selected, input) {
+            (_, MenuInput::Back) | (4, MenuInput::Select) => Transition::Pop,
+            (0..=3, MenuInput::Select) => {
+                let [address, room, key] = &self.fields;
+                let key = Some(key.clone()).filter(|k| !k.is_empty());
+                match Client::watch(address, room, key) {
+                    Ok(client) => {
+                        app.net_error = None;
+                        app.net = Some(Link::Client(client));
+                        Transition::Replace(Box::new(LobbyMenu::new()))
+                    }
+                    Err(e) => {
+                        app.net_error = Some(format!("Failed to watch: {}", e));
+                        Transition::None
+                    }
+                }
+            }
+            _ => Transition::None,
+        }
+    }
+}
+
+// What a client is shown before the game starts
+#[derive(Clone, Copy, PartialEq)]
+enum LobbyView {
+    // Connecting, or watching a room
+    Waiting,
+    // The rooms of a dedicated server, before joining one
+    Rooms(usize),
+    // Joined; the owner of a room on a server picks the board and starts the game
+    Seated { owner: bool },
+}
+
+impl LobbyView {
+    fn of(client: &Client) -> Self {
+        match (client.slot, &client.rooms) {
+            _ if client.watching => LobbyView::Waiting,
+            (Some(slot), rooms) => LobbyView::Seated {
+                owner: rooms.is_some() && client.seats.first().map(|s| s.slot) == Some(slot),
+            },
+            (None, Some(rooms)) => LobbyView::Rooms(rooms.len()),
+            (None, None) => LobbyView::Waiting,
+        }
+    }
+}
+
+// Shown to a client until the game starts: the rooms of a server, then the lobby
+pub struct LobbyMenu {
+    menu: Menu,
+    view: LobbyView,
+    // Name of a new room on a server
+    room: String,
+}
+
+impl LobbyMenu {
+    pub fn new() -> Self {
+        Self {
+            menu: Menu::new("Lobby", labels(&["Leave"])),
+            view: LobbyView::Waiting,
+            room: String::new(),
+        }
+    }
+}
+
+impl Default for LobbyMenu {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MenuScene for LobbyMenu {
+    fn menu(&mut self) -> &mut Menu {
+        &mut self.menu
+    }
+
+    fn refresh(&mut self, app: &App) {
+        let client = match &app.net {
+            Some(Link::Client(client)) => client,
+            _ => return,
+        };
+        self.view = LobbyView::of(client);
+        let me = client.seats.iter().find(|s| Some(s.slot) == client.slot);
+        let (lines, items) = match self.view {
+            LobbyView::Waiting => (
+                vec![
+                    match client.watching {
+                        true => "Watching".to_string(),
+                        false => "Connecting".to_string(),
+                    },
+                    format!("{} players connected", client.players),
+                ],
+                vec!["Leave".to_string()],
+            ),
+            LobbyView::Rooms(_) => {
+                let rooms = client.rooms.iter().flatten().map(|r| {
+                    let state = if r.running { ", playing" } else { "" };
+                    format!("{}: {} players{}", r.name, r.players, state)
+                });
+                let mut items: Vec<_> = rooms.collect();
+                items.push(format!("New room: {}", self.room));
+                items.extend(labels(&["Refresh", "Leave"]));
+                (vec!["Pick a room or name a new one".to_string()], items)
+            }
+            LobbyView::Seated { owner } => {
+                let mut lines = seat_lines(&client.seats);
+                let mut items = vec![
+                    format!("Name: {}", app.profile.name),
+                    format!(
+                        "Colour: {}",
+                        me.map_or(app.profile.color_name(), |s| s.profile.color_name())
+                    ),
+                ];
+                if owner {
+                    let setup = &app.setup;
+                    lines.push(board_line(setup));
+                    items.extend([
+                        format!("Width: {}", setup.width),
+                        format!("Height: {}", setup.height),
+                        format!("Mines: {}", setup.mines),
+                        "Start".to_string(),
+                    ]);
+                } else {
+                    lines.extend(client.settings.as_ref().map(board_line));
+                    let ready = me.is_some_and(|s| s.ready);
+                    items.push(format!("Ready: {}", if ready { "Yes" } else { "No" }));
+                }
+                items.push("Leave".to_string());
+                (lines, items)
+            }
+        };
+        self.menu.lines = lines;
+        self.menu.lines.extend(client.error.iter().cloned());
+        self.menu.selected = self.menu.selected.min(items.len() - 1);
+        self.menu.items = items;
+    }
+
+    // The name of a new room and the player's own name are edited while selected
+    fn is_editing(&self) -> bool {
+        match self.view {
+            LobbyView::Rooms(rooms) => self.menu.selected == rooms,
+            LobbyView::Seated { .. } => self.menu.selected == 0,
+            LobbyView::Waiting => false,
+        }
+    }
+
+    fn type_char(&mut self, app: &mut App, character: char) {
+        match self.view {
+            LobbyView::Rooms(_) => self.room.push(character),
+            _ => {
+                type_name(app, character);
+                send(app, &ClientMessage::Profile(app.profile.clone()));
+            }
+        }
+    }
+
+    fn backspace(&mut self, app: &mut App) {
+        match self.view {
+            LobbyView::Rooms(_) => {
+                self.room.pop();
+            }
+            _ => {
+                app.profile.name.pop();
+                send(app, &ClientMessage::Profile(app.profile.clone()));
+            }
+        }
+    }
+
+    fn activate(&mut self, _ctx: &mut Context, app: &mut App, input: MenuInput) -> Transition<App> {
+        let client = match &mut app.net {
+            Some(Link::Client(client)) => client,
+            _ => return Transition::Pop,
+        };
+        let selected = self.menu.selected;
+        let leave = selected + 1 == self.menu.items.len();
+        if input == MenuInput::Back || (leave && input == MenuInput::Select) {
+            app.net = None;
+            return Transition::Pop;
+        }
+        let me = client.seats.iter().find(|s| Some(s.slot) == client.slot);
+        let msg = match (self.view, selected, input) {
+            (LobbyView::Rooms(rooms), _, MenuInput::Select) if selected < rooms => {
+                let room = client.rooms.iter().flatten().nth(selected);
+                ClientMessage::Join {
+                    room: room.map(|r| r.name.clone()).unwrap_or_default(),
+                }
+            }
+            (LobbyView::Rooms(rooms), _, MenuInput::Select) if selected == rooms => {
+                if self.room.trim().is_empty() {
+                    return Transition::None;
+                }
+                ClientMessage::Join {
+                    room: self.room.trim().to_string(),
+                }
+            }
+            (LobbyView::Rooms(_), _, MenuInput::Select) => ClientMessage::ListRooms,
+            (LobbyView::Seated { .. }, 1, _) => {
+                if let Some(seat) = me {
+                    app.profile.color = seat.profile.color;
+                }
+                let taken = taken_colors(&client.seats, client.slot);
+                app.profile.cycle_color(&taken, delta(input));
+                ClientMessage::Profile(app.profile.clone())
+            }
+            (LobbyView::Seated { owner: true }, 2..=4, _) => {
+                let setup = &mut app.setup;
+                match selected {
+                    2 => setup.change_width(delta(input)),
+                    3 => setup.change_height(delta(input)),
+                    _ => setup.change_mines(delta(input)),
+                }
+                ClientMessage::Settings { setup: *setup }
+            }
+            (LobbyView::Seated { owner: true }, 5, MenuInput::Select) => {
+                ClientMessage::Start { setup: app.setup }
+            }
+            (LobbyView::Seated { owner: false }, 2, _) => ClientMessage::Ready {
+                ready: !me.is_some_and(|s| s.ready),
+            },
+            _ => return Transition::None,
+        };
+        client.error = None;
+        client.send(&msg);
+        Transition::None
+    }
+}
+
+pub struct SetupMenu {
+    menu: Menu,
+}
+
+impl SetupMenu {
+    pub fn new() -> Self {
+        Self {
+            menu: Menu::new("New game", vec![]),
+        }
+    }
+}
+
+impl Default for SetupMenu {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MenuScene for SetupMenu {
+    fn menu(&mut self) -> &mut Menu {
+        &mut self.menu
+    }
+
+    fn refresh(&mut self, app: &App) {
+        let setup = &app.setup;
+        let difficulty = setup.difficulty.name();
+        self.menu.items = vec![
+            format!(
+                "Difficulty: {}{}",
+                difficulty[..1].to_uppercase(),
+                &difficulty[1..]
+            ),
+            format!("Width: {}", setup.width),
+            format!("Height: {}", setup.height),
+            format!("Mines: {}", setup.mines),
+            format!("Players: {}", setup.players),
+            format!("Best of: {}", setup.best_of),
+            "Rules".to_string(),
+            "Start".to_string(),
+            "Back".to_string(),
+        ];
+    }
+
+    fn activate(&mut self, ctx: &mut Context, app: &mut App, input: MenuInput) -> Transition<App> {
+        let setup = &mut app.setup;
+        match (self.menu.selected, input) {
+            (_, MenuInput::Back) | (8, MenuInput::Select) => return Transition::Pop,
+            (0, _) => setup.change_difficulty(delta(input)),
+            (1, _) => setup.change_width(delta(input)),
+            (2, _) => setup.change_height(delta(input)),
+            (3, _) => setup.change_mines(delta(input)),
+            (4, _) => setup.change_players(delta(input)),
+            (5, _) => setup.change_best_of(delta(input)),
+            (6, MenuInput::Select) => return Transition::Push(Box::new(RulesMenu::new())),
+            (7, MenuInput::Select) => {
+                app.session = Some(Session::new(app.setup));
+                return start(ctx, app, |_| {});
+            }
+            _ => {}
+        }
+        Transition::None
+    }
+}
+
+pub struct RulesMenu {
+    menu: Menu,
+}
+
+impl RulesMenu {
+    pub fn new() -> Self {
+        Self {
+            menu: Menu::new("Rules", vec![]),
+        }
+    }
+}
+
+impl Default for RulesMenu {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MenuScene for RulesMenu {
+    fn menu(&mut self) -> &mut Menu {
+        &mut self.menu
+    }
+
+    fn refresh(&mut self, app: &App) {
+        let setup = &app.setup;
+        self.menu.items = vec![
+            match setup.life_rule {
+                LifeRule::Lives(_) => "On mine: Lose a life".to_string(),
+                LifeRule::Penalty(_) => "On mine: Lose points".to_string(),
+                LifeRule::Shield(_) => "On mine: Use a shield".to_string(),
+            },
+            match setup.life_rule {
+                LifeRule::Lives(n) => format!("Lives: {}", n),
+                LifeRule::Penalty(n) => format!("Penalty: {} points", n),
+                LifeRule::Shield(n) => format!("Shield for opening: {} cells", n),
+            },
+            match setup.turn_policy {
+                TurnPolicy::OnePerAction => "Turns: One per move".to_string(),
+                TurnPolicy::ExtraOnOpening => "Turns: Extra on opening".to_string(),
+                TurnPolicy::Simultaneous => "Turns: Simultaneous".to_string(),
+            },
+            match setup.teams {
+                0 => "Teams: Off".to_string(),
+                n => format!("Teams: {}", n),
+            },
+            "Back".to_string(),
+        ];
+    }
+
+    fn activate(&mut self, _ctx: &mut Context, app: &mut App, input: MenuInput) -> Transition<App> {
+        let setup = &mut app.setup;
+        match (self.menu.selected, input) {
+            (_, MenuInput::Back) | (4, MenuInput::Select) => return Transition::Pop,
+            (0, _) => setup.change_life_rule(delta(input)),
+            (1, _) => setup.change_life_amount(delta(input)),
+            (2, _) => setup.change_turn_policy(delta(input)),
+            (3, _) => setup.change_teams(delta(input)),
+            _ => {}
+        }
+        Transition::None
+    }
+}
+
+pub struct SettingsMenu {
+    menu: Menu,
+}
+
+impl SettingsMenu {
+    pub fn new() -> Self {
+        Self {
+            menu: Menu::new("Settings", vec![]),
+        }
+    }
+}
+
+impl Default for SettingsMenu {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MenuScene for SettingsMenu {
+    fn menu(&mut self) -> &mut Menu {
+        &mut self.menu
+    }
+
+    fn refresh(&mut self, app: &App) {
+        let audio = &app.sounds.settings;
+        self.menu.items = vec![
+            format!("Theme: {}", app.theme().name),
+            format!("Animations: {}", on_off(app.animations)),
+            format!("Sound: {}", on_off(!audio.muted)),
+            format!("Volume: {}%", (audio.volume * 100.0).round()),
+            format!("Chat: {}", on_off(!app.chat_muted)),
+            "Back".to_string(),
+        ];
+    }
+
+    fn activate(&mut self, _ctx: &mut Context, app: &mut App, input: MenuInput) -> Transition<App> {
+        match (self.menu.selected, input) {
+            (_, MenuInput::Back) | (5, MenuInput::Select) => return Transition::Pop,
+            (0, _) => app.change_theme(delta(input)),
+            (1, _) => app.animations ^= true,
+            (2, _) => app.toggle_mute(),
+            (3, _) => app.change_volume(delta(input) as f32),
+            (4, _) => app.chat_muted ^= true,
+            _ => {}
+        }
+        Transition::None
+    }
+}
+
+pub struct PauseMenu {
+    menu: Menu,
+    // Players who joined a network game can't restart it
+    joined: bool,
+}
+
+impl PauseMenu {
+    pub fn new() -> Self {
+        Self {
+            menu: Menu::new(
+                "Paused",
+                labels(&["Resume", "Restart", "Settings", "Main menu"]),
+            ),
+            joined: false,
+        }
+    }
+
+    pub fn joined() -> Self {
+        Self {
+            menu: Menu::new("Paused", labels(&["Resume", "Settings", "Leave game"])),
+            joined: true,
+        }
+    }
+}
+
+impl Default for PauseMenu {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MenuScene for PauseMenu {
+    fn menu(&mut self) -> &mut Menu {
+        &mut self.menu
+    }
+
+    fn is_overlay(&self) -> bool {
+        true
+    }
+
+    fn activate(&mut self, ctx: &mut Context, app: &mut App, input: MenuInput) -> Transition<App> {
+        // The joined menu lacks Restart
+        let selected = match self.menu.selected {
+            i if self.joined && i > 0 => i + 1,
+            i => i,
+        };
+        match (selected, input) {
+            (_, MenuInput::Back) | (0, MenuInput::Select) => Transition::Pop,
+            (1, MenuInput::Select) => start(ctx, app, Session::restart),
+            (2, MenuInput::Select) => Transition::Push(Box::new(SettingsMenu::new())),
+            (3, MenuInput::Select) => main_menu(app),
+            _ => Transition::None,
+        }
+    }
+}
+
+pub struct ResultsMenu {
+    menu: Menu,
+    joined: bool,
+}
+
+impl ResultsMenu {
+    pub fn new(game: &Game, session: Option<&Session>, joined: bool) -> Self {
+        let mut title = results_title(game);
+        let mut lines = results_lines(game);
+        if let Some(session) = session.filter(|s| s.setup.best_of > 1) {
+            if let Some(name) = session.winner_name() {
+                title = format!("{} wins the match!", name);
+            }
+            lines.push(format!(
+                "Round {}, best of {}",
+                session.rounds, session.setup.best_of
+            ));
+            lines.extend(session.standings());
+        }
+        // Players who joined wait for the host to start the next round
+        let items = if joined {
+            lines.push("Waiting for the host".to_string());
+            labels(&["Leave game"])
+        } else {
+            labels(&[
+                "Restart",
+                "Rematch, shuffled order",
+                "Replay same board",
+                "Main menu",
+            ])
+        };
+        let mut menu = Menu::new(&title, items);
+        menu.lines = lines;
+        Self { menu, joined }
+    }
+}
+
+impl MenuScene for ResultsMenu {
+    fn menu(&mut self) -> &mut Menu {
+        &mut self.menu
+    }
+
+    fn is_overlay(&self) -> bool {
+        true
+    }
+
+    // Back closes the results to show the board
+    fn activate(&mut self, ctx: &mut Context, app: &mut App, input: MenuInput) -> Transition<App> {
+        match (self.menu.selected, input) {
+            (_, MenuInput::Back) => Transition::Pop,
+            (_, MenuInput::Select) if self.joined => main_menu(app),
+            (0, MenuInput::Select) => start(ctx, app, Session::restart),
+            (1, MenuInput::Select) => start(ctx, app, Session::rematch),
+            (2, MenuInput::Select) => start(ctx, app, Session::replay),
+            (3, MenuInput::Select) => main_menu(app),
+            _ => Transition::None,
+        }
+    }
+}
+
+pub fn results_title(game: &Game) -> String {
+    if game.is_team_game() {
+        return match game.winning_team() {
+            Some(team) => format!("Team {} wins!", team + 1),
+            None => "Draw!".to_string(),
+        };
+    }
+    match game.winner() {
+        Some(slot) => match game.players.iter().find(|p| p.slot == slot) {
+            Some(player) => format!("{} wins!", player.name),
+            None => "Game over".to_string(),
+        },
+        None => "Draw!".to_string(),
+    }
+}
+
+pub fn results_lines(game: &Game) -> Vec<String> {
+    let teams = game
+        .team_ranking()
+        .into_iter()
+        .filter(|_| game.is_team_game())
+        .map(|team| format!("Team {} - {}", team + 1, game.team_score(team)));
+    let players = game.ranking().into_iter().map(|i| {
+        let player = &game.players[i];
+        let mut line = format!("{} - {}", player.name, game.score(i));
+        if let Some(death) = game.death(player.slot) {
+            line += &match death.tile {
+                Some((x, y)) => format!(", died at {}, {}", x, y),
+                None => ", left the game".to_string(),
+            };
+        } else if game.hits[player.slot] > 0 {
+            line += &format!(", {} mines hit", game.hits[player.slot]);
+        }
+        line
+    });
+    teams.chain(players).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::menus::results_lines;
+    use crate::menus::results_title;
+    use crate::GameSetup;
+
+    // TEST results_title
+    #[test]
+    fn results_title0() {
+        let mut game = GameSetup::new(10, 10, 1, 2).new_game();
+        game.grid.place_mines(&[(0, 0)]);
+        assert_eq!(results_title(&game), "Draw!");
+        game.reveal(0, 1);
+        assert_eq!(results_title(&game), "Player 1 wins!");
+    }
+
+    #[test]
+    fn results_title_teams() {
+        let mut setup = GameSetup::new(10, 10, 1, 3);
+        setup.teams = 2;
+        let mut game = setup.new_game();
+        game.grid.place_mines(&[(0, 0)]);
+        game.reveal(0, 1);
+        assert_eq!(results_title(&game), "Team 1 wins!");
+        assert_eq!(results_lines(&game)[0], "Team 1 - 1");
+    }
+
+    // TEST results_lines
+    #[test]
+    fn results_lines0() {
+        let mut game = GameSetup::new(10, 10, 2, 2).new_game();
+        game.grid.place_mines(&[(0, 0), (9, 9)]);
+        game.reveal(0, 1);
+        game.reveal(0, 0);
+        assert_eq!(
+            results_lines(&game),
+            vec![
+                "Player 1 - 1".to_string(),
+                "Player 2 - 0, died at 0, 0".to_string()
+            ]
+        );
+    }
+}
@@ -0,0 +1,1223 @@
+use crate::lobby::Lobby;
+use crate::lobby::Seated;
+use crate::protocol::decode;
+use crate::protocol::encode;
+use crate::ChatLine;
+use crate::ChatLog;
+use crate::ClientMessage;
+use crate::Game;
+use crate::GameEvent;
+use crate::GameSetup;
+use crate::HexGrid;
+use crate::Move;
+use crate::Profile;
+use crate::RateLimit;
+use crate::RoomInfo;
+use crate::Said;
+use crate::Seat;
+use crate::ServerMessage;
+use crate::Snapshot;
+use crate::Update;
+use crate::PROTOCOL_VERSION;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::io;
+use std::io::Read;
+use std::io::Write;
+use std::net::SocketAddr;
+use std::net::TcpListener;
+use std::net::TcpStream;
+use std::net::ToSocketAddrs;
+use std::time::Duration;
+use std::time::Instant;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+// Longer lines are never valid and the peer is dropped
+const MAX_LINE: usize = 1 << 20;
+
+// Players who lose the connection during a game can rejoin it for this long
+pub(crate) const RESUME_TIME: Duration = Duration::from_secs(300);
+
+// A non-blocking TCP stream carrying one JSON message per line
+pub struct Connection {
+    stream: TcpStream,
+    inbox: Vec<u8>,
+    outbox: Vec<u8>,
+    closed: bool,
+}
+
+impl Connection {
+    pub fn new(stream: TcpStream) -> io::Result<Self> {
+        stream.set_nonblocking(true)?;
+        stream.set_nodelay(true)?;
+        Ok(Self {
+            stream,
+            inbox: vec![],
+            outbox: vec![],
+            closed: false,
+        })
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+
+    pub fn close(&mut self) {
+        self.flush();
+        self.closed = true;
+    }
+
+    // Queues the message and writes as much as the socket takes without blocking
+    pub fn send<T: Serialize>(&mut self, msg: &T) {
+        if !self.closed {
+            self.outbox.extend(encode(msg));
+            self.flush();
+        }
+    }
+
+    pub fn flush(&mut self) {
+        while !self.outbox.is_empty() && !self.closed {
+            match self.stream.write(&self.outbox) {
+                Ok(0) => self.closed = true,
+                Ok(n) => {
+                    self.outbox.drain(..n);
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(_) => self.closed = true,
+            }
+        }
+    }
+
+    // Every complete message received so far; lines that don't parse are returned as errors
+    pub fn recv<T: DeserializeOwned>(&mut self) -> Vec<io::Result<T>> {
+        self.flush();
+        let mut buf = [0; 4096];
+        while !self.closed {
+            match self.stream.read(&mut buf) {
+                Ok(0) => self.closed = true,
+                Ok(n) => self.inbox.extend_from_slice(&buf[..n]),
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(_) => self.closed = true,
+            }
+        }
+        let mut msgs = vec![];
+        while let Some(i) = self.inbox.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = self.inbox.drain(..=i).collect();
+            msgs.push(decode(&line[..i]));
+        }
+        if self.inbox.len() > MAX_LINE {
+            self.closed = true;
+        }
+        msgs
+    }
+}
+
+// Plays a reveal, chord or flag for the player in the slot
+pub(crate) fn play_move(
+    game: &mut Game,
+    slot: usize,
+    msg: &ClientMessage,
+) -> Result<Vec<GameEvent>, &'static str> {
+    let (size_x, size_y) = game.grid.size();
+    let events = match *msg {
+        ClientMessage::Reveal { x, y }
+        | ClientMessage::Chord { x, y }
+        | ClientMessage::Flag { x, y }
+            if x >= size_x || y >= size_y =>
+        {
+            return Err("cell out of range")
+        }
+        ClientMessage::Reveal { x, y } => game.reveal_by(slot, x, y),
+        ClientMessage::Chord { x, y } => game.chord_by(slot, x, y),
+        ClientMessage::Flag { x, y } => game.toggle_mark(x, y),
+        _ => return Err("unexpected message"),
+    };
+    if events.is_empty() {
+        Err("move rejected")
+    } else {
+        Ok(events)
+    }
+}
+
+// Handed to every player, who gives it back to rejoin after losing the connection;
+// 128 bits from the OS so other players can't guess it
+pub(crate) fn new_token() -> String {
+    let mut bytes = [0u8; 16];
+    getrandom::getrandom(&mut bytes).expect("no OS randomness");
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn version_error() -> String {
+    format!("protocol version {} is needed", PROTOCOL_VERSION)
+}
+
+struct Remote {
+    conn: Connection,
+    seat: Seat,
+    token: String,
+    chat: RateLimit,
+    // When the connection was lost, during the game
+    left: Option<Instant>,
+    // Rejoined and waits for a snapshot of the game
+    catch_up: bool,
+}
+
+impl Seated for Remote {
+    fn seat(&self) -> &Seat {
+        &self.seat
+    }
+
+    fn seat_mut(&mut self) -> &mut Seat {
+        &mut self.seat
+    }
+
+    fn away(&self) -> bool {
+        self.left.is_some()
+    }
+}
+
+// Runs the authoritative game; the host always plays slot 0
+pub struct Host {
+    listener: TcpListener,
+    // Connections that haven't said hello yet
+    pending: Vec<Connection>,
+    // The host's own seat comes first
+    lobby: Lobby<Remote>,
+    max_players: usize,
+    started: bool,
+    // The game being played, which the host's own window mirrors like a client
+    game: Option<Box<Game>>,
+    setup: Option<GameSetup>,
+    order: Vec<usize>,
+    profiles: Vec<Profile>,
+    // Updates and chat for the host's own window
+    updates: VecDeque<Update>,
+    chat: ChatLog,
+}
+
+impl Host {
+    pub fn bind<A: ToSocketAddrs>(addr: A, max_players: usize) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        Ok(Self {
+            listener,
+            pending: vec![],
+            lobby: Lobby::new(Some(Seat {
+                slot: 0,
+                profile: Profile::new(0),
+                ready: true,
+                away: false,
+            })),
+            max_players,
+            started: false,
+            game: None,
+            setup: None,
+            order: vec![],
+            profiles: vec![],
+            updates: VecDeque::new(),
+            chat: ChatLog::new(),
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    // Connected players, the host included
+    pub fn players(&self) -> usize {
+        1 + self
+            .lobby
+            .members
+            .iter()
+            .filter(|r| !r.conn.is_closed())
+            .count()
+    }
+
+    // Everyone in the lobby, the host first
+    pub fn seats(&self) -> Vec<Seat> {
+        self.lobby.seats()
+    }
+
+    // Names and colours indexed by slot
+    pub fn profiles(&self) -> Vec<Profile> {
+        self.lobby.profiles()
+    }
+
+    pub fn all_ready(&self) -> bool {
+        self.lobby.all_ready()
+    }
+
+    pub fn profile(&self) -> &Profile {
+        &self.lobby.local.as_ref().unwrap().profile
+    }
+
+    pub fn set_profile(&mut self, profile: Profile) {
+        if self.lobby.set_profile(0, profile) {
+            self.send_lobby();
+        }
+    }
+
+    pub fn set_settings(&mut self, setup: GameSetup) {
+        if !self.started && self.lobby.set_settings(setup) {
+            self.send_lobby();
+        }
+    }
+
+    fn send_lobby(&mut self) {
+        let msg = ServerMessage::Lobby {
+            seats: self.seats(),
+            setup: self.lobby.settings,
+        };
+        self.broadcast(&msg);
+    }
+
+    // Takes new players while in the lobby, lets players who dropped out back in
+    // and drops the ones who left the lobby
+    pub fn accept(&mut self) {
+        while let Ok((stream, _)) = self.listener.accept() {
+            if let Ok(conn) = Connection::new(stream) {
+                self.pending.push(conn);
+            }
+        }
+        let mut changed = false;
+        let mut i = 0;
+        while i < self.pending.len() {
+            let mut msgs = self.pending[i].recv::<ClientMessage>().into_iter();
+            let first = match msgs.next() {
+                Some(msg) => msg,
+                None if self.pending[i].is_closed() => {
+                    self.pending.swap_remove(i);
+                    continue;
+                }
+                None => {
+                    i += 1;
+                    continue;
+                }
+            };
+            let conn = self.pending.swap_remove(i);
+            if let Some(r) = self.admit(conn, first) {
+                changed = true;
+                // Whatever was sent right after hello
+                for msg in msgs {
+                    self.lobby_message(r, msg);
+                }
+            }
+        }
+        if !self.started {
+            let before = self.lobby.members.len();
+            self.lobby.members.retain(|r| !r.conn.is_closed());
+            changed |= self.lobby.members.len() != before;
+            // Lobby messages also show when a client has closed its side
+            for r in 0..self.lobby.members.len() {
+                for msg in self.lobby.members[r].conn.recv::<ClientMessage>() {
+                    changed |= self.lobby_message(r, msg);
+                }
+            }
+        }
+        if changed {
+            self.send_lobby();
+        }
+    }
+
+    // The first message decides whether a connection is a new player, one coming back or neither
+    fn admit(&mut self, mut conn: Connection, msg: io::Result<ClientMessage>) -> Option<usize> {
+        let refusal = match msg {
+            Ok(ClientMessage::Hello { version } | ClientMessage::Resume { version, .. })
+                if version != PROTOCOL_VERSION =>
+            {
+                version_error()
+            }
+            Ok(ClientMessage::Resume { token, .. }) => {
+                match self.lobby.members.iter().position(|r| r.token == token) {
+                    Some(r) => {
+                        // A dropped connection may not have been noticed yet, the token decides
+                        let remote = &mut self.lobby.members[r];
+                        conn.send(&ServerMessage::Welcome {
+                            slot: remote.seat.slot,
+                            token,
+                        });
+                        remote.conn = conn;
+                        remote.left = None;
+                        remote.catch_up = self.started;
+                        return Some(r);
+                    }
+                    None => "unknown token".to_string(),
+                }
+            }
+            Ok(ClientMessage::Hello { .. }) if self.started => {
+                "the game has already started".to_string()
+            }
+            Ok(ClientMessage::Hello { .. }) if self.players() >= self.max_players => {
+                "the game is full".to_string()
+            }
+            Ok(ClientMessage::Hello { .. }) => {
+                let seat = self.lobby.new_seat();
+                let token = new_token();
+                conn.send(&ServerMessage::Welcome {
+                    slot: seat.slot,
+                    token: token.clone(),
+                });
+                self.lobby.members.push(Remote {
+                    conn,
+                    seat,
+                    token,
+                    chat: RateLimit::new(),
+                    left: None,
+                    catch_up: false,
+                });
+                return Some(self.lobby.members.len() - 1);
+            }
+            Ok(_) => "say hello first".to_string(),
+            Err(e) => format!("invalid message: {}", e),
+        };
+        conn.send(&ServerMessage::Error { message: refusal });
+        conn.close();
+        None
+    }
+
+    // Handles a message from a player in the lobby; returns whether the lobby changed
+    fn lobby_message(&mut self, r: usize, msg: io::Result<ClientMessage>) -> bool {
+        if self.started {
+            return false;
+        }
+        match msg {
+            Ok(ClientMessage::Profile(profile)) => {
+                let slot = self.lobby.members[r].seat.slot;
+                self.lobby.set_profile(slot, profile)
+            }
+            Ok(ClientMessage::Ready { ready }) => {
+                let slot = self.lobby.members[r].seat.slot;
+                self.lobby.set_ready(slot, ready)
+            }
+            Ok(ClientMessage::Chat { said }) => {
+                self.remote_chat(r, said);
+                false
+            }
+            Ok(ClientMessage::Hello { .. }) => false,
+            Ok(_) => {
+                self.error(r, "the game hasn't started");
+                false
+            }
+            Err(e) => {
+                self.error(r, &format!("invalid message: {}", e));
+                false
+            }
+        }
+    }
+
+    // Builds the game and returns the mirror the host's window plays on
+    pub fn start(&mut self, setup: &GameSetup, order: &[usize], seed: u64) -> Game {
+        if !self.started {
+            self.started = true;
+            self.lobby.renumber();
+            for remote in &mut self.lobby.members {
+                remote.conn.send(&ServerMessage::Welcome {
+                    slot: remote.seat.slot,
+                    token: remote.token.clone(),
+                });
+            }
+        }
+        self.setup = Some(*setup);
+        self.order = order.to_vec();
+        self.profiles = self.profiles();
+        self.game = Some(Box::new(setup.build_with(order, seed, &self.profiles)));
+        self.updates.clear();
+        self.broadcast(&ServerMessage::Start {
+            setup: *setup,
+            order: order.to_vec(),
+            profiles: self.profiles.clone(),
+        });
+        mirror(setup, order, &self.profiles)
+    }
+
+    // Applies the moves of the remote players and sends every change to all of them,
+    // the host's own window included
+    pub fn poll(&mut self) {
+        self.accept();
+        let mut game = match self.game.take() {
+            Some(game) => game,
+            None => return,
+        };
+        self.catch_up(&game);
+        for i in 0..self.lobby.members.len() {
+            let slot = self.lobby.members[i].seat.slot;
+            for msg in self.lobby.members[i].conn.recv::<ClientMessage>() {
+                match msg {
+                    Ok(msg) => {
+                        let events = self.handle(i, slot, msg, &mut game);
+                        self.send_update(&game, &events);
+                    }
+                    Err(e) => self.error(i, &format!("invalid message: {}", e)),
+                }
+            }
+        }
+        // Players who drop out keep their place in the game for a while
+        let now = Instant::now();
+        for remote in self.lobby.members.iter_mut().filter(|r| r.conn.is_closed()) {
+            remote.left.get_or_insert(now);
+        }
+        let gone: Vec<_> = self
+            .lobby
+            .members
+            .iter()
+            .filter(|r| r.left.is_some_and(|t| now - t >= RESUME_TIME))
+            .map(|r| r.seat.slot)
+            .collect();
+        self.lobby
+            .members
+            .retain(|r| r.left.is_none_or(|t| now - t < RESUME_TIME));
+        for slot in gone {
+            let events = game.forfeit(slot);
+            self.send_update(&game, &events);
+        }
+        self.skip_away(&mut game);
+        self.game = Some(game);
+    }
+
+    // There is no turn clock, so turns of players who dropped out pass on
+    // as long as someone still playing is here to take them
+    fn skip_away(&mut self, game: &mut Game) {
+        let away: Vec<_> = self
+            .lobby
+            .members
+            .iter()
+            .filter(|r| r.left.is_some())
+            .map(|r| r.seat.slot)
+            .collect();
+        let alive = &game.players[..game.players_alive];
+        if alive.iter().all(|p| away.contains(&p.slot)) {
+            return;
+        }
+        while game.current().is_some_and(|p| away.contains(&p.slot)) {
+            let events = game.timeout();
+            if events.is_empty() {
+                break;
+            }
+            self.send_update(game, &events);
+        }
+    }
+
+    // The host's own moves, checked like everyone else's
+    pub fn play(&mut self, msg: &ClientMessage) {
+        let mut game = match self.game.take() {
+            Some(game) => game,
+            None => return,
+        };
+        if let Ok(events) = play_move(&mut game, 0, msg) {
+            self.send_update(&game, &events);
+        }
+        self.game = Some(game);
+    }
+
+    pub fn take_updates(&mut self) -> Vec<Update> {
+        self.updates.drain(..).collect()
+    }
+
+    // Players who rejoined get the game as it is now
+    fn catch_up(&mut self, game: &Game) {
+        let setup = match self.setup {
+            Some(setup) => setup,
+            None => return,
+        };
+        for remote in self.lobby.members.iter_mut().filter(|r| r.catch_up) {
+            remote.catch_up = false;
+            remote
+                .conn
+                .send(&ServerMessage::Snapshot(Box::new(Snapshot {
+                    setup,
+                    order: self.order.clone(),
+                    profiles: self.profiles.clone(),
+                    state: Update::snapshot(game),
+                    moves: vec![],
+                    mines: vec![],
+                    time_left: None,
+                })));
+        }
+    }
+
+    fn handle(
+        &mut self,
+        i: usize,
+        slot: usize,
+        msg: ClientMessage,
+        game: &mut Game,
+    ) -> Vec<GameEvent> {
+        match msg {
+            ClientMessage::Hello { .. } => return vec![],
+            ClientMessage::Chat { said } => {
+                self.remote_chat(i, said);
+                return vec![];
+            }
+            _ => {}
+        }
+        match play_move(game, slot, &msg) {
+            Ok(events) => events,
+            Err(message) => {
+                self.error(i, message);
+                vec![]
+            }
+        }
+    }
+
+    fn remote_chat(&mut self, i: usize, said: Said) {
+        if self.lobby.members[i].chat.allow(Instant::now()) {
+            self.chat(self.lobby.members[i].seat.slot, said);
+        } else {
+            self.error(i, "slow down");
+        }
+    }
+
+    // The host's own chat, which isn't rate limited
+    pub fn say(&mut self, said: Said) {
+        self.chat(0, said);
+    }
+
+    fn chat(&mut self, slot: usize, said: Said) {
+        if let Some(said) = said.cleaned() {
+            let line = ChatLine { slot, said };
+            self.broadcast(&ServerMessage::Chat(line.clone()));
+            self.chat.push(line);
+        }
+    }
+
+    pub fn take_chat(&mut self) -> Vec<ChatLine> {
+        self.chat.take()
+    }
+
+    fn error(&mut self, i: usize, message: &str) {
+        self.lobby.members[i].conn.send(&ServerMessage::Error {
+            message: message.to_string(),
+        });
+    }
+
+    // Sends the result of a move, whoever made it
+    fn send_update(&mut self, game: &Game, events: &[GameEvent]) {
+        if !events.is_empty() {
+            let update = Update::new(game, events);
+            self.broadcast(&ServerMessage::Update(update.clone()));
+            self.updates.push_back(update);
+        }
+    }
+
+    fn broadcast(&mut self, msg: &ServerMessage) {
+        for remote in &mut self.lobby.members {
+            remote.conn.send(msg);
+        }
+    }
+}
+
+pub struct Client {
+    conn: Connection,
+    pub addr: String,
+    pub slot: Option<usize>,
+    // Rejoins the game after losing the connection
+    pub token: Option<String>,
+    pub players: usize,
+    // Who sits in the lobby and the board the owner picked
+    pub seats: Vec<Seat>,
+    pub settings: Option<GameSetup>,
+    // Rooms on a dedicated server; a LAN host never lists any
+    pub rooms: Option<Vec<RoomInfo>>,
+    // Names and colours of the current game by slot
+    pub profiles: Vec<Profile>,
+    chat: ChatLog,
+    pub error: Option<String>,
+    updates: VecDeque<Update>,
+    // Spectators never get a slot and can't move
+    pub watching: bool,
+    // Moves of the current game, only sent to spectators
+    pub feed: Vec<Move>,
+    // Every mine, only sent to casters
+    pub mines: Vec<(usize, usize)>,
+    deadline: Option<Instant>,
+}
+
+impl Client {
+    pub fn connect(addr: &str) -> io::Result<Self> {
+        Self::open(
+            addr,
+            ClientMessage::Hello {
+                version: PROTOCOL_VERSION,
+            },
+        )
+    }
+
+    // Takes the place in the game given with the token again
+    pub fn resume(addr: &str, token: &str) -> io::Result<Self> {
+        Self::open(
+            addr,
+            ClientMessage::Resume {
+                version: PROTOCOL_VERSION,
+                token: token.to_string(),
+            },
+        )
+    }
+
+    fn open(addr: &str, first: ClientMessage) -> io::Result<Self> {
+        let socket = addr
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no address found"))?;
+        let stream = TcpStream::connect_timeout(&socket, CONNECT_TIMEOUT)?;
+        let mut conn = Connection::new(stream)?;
+        conn.send(&first);
+        Ok(Self {
+            conn,
+            addr: addr.to_string(),
+            slot: None,
+            token: None,
+            players: 0,
+            seats: vec![],
+            settings: None,
+            rooms: None,
+            profiles: vec![],
+            chat: ChatLog::new(),
+            error: None,
+            updates: VecDeque::new(),
+            watching: false,
+            feed: vec![],
+            mines: vec![],
+            deadline: None,
+        })
+    }
+
+    // Watches a room on a dedicated server, as a caster when a key is given
+    pub fn watch(addr: &str, room: &str, key: Option<String>) -> io::Result<Self> {
+        let mut client = Self::connect(addr)?;
+        client.watching = true;
+        client.send(&ClientMessage::Watch {
+            room: room.to_string(),
+            key,
+        });
+        Ok(client)
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.conn.is_closed()
+    }
+
+    pub fn send(&mut self, msg: &ClientMessage) {
+        self.conn.send(msg);
+    }
+
+    // Handles lobby messages and queues updates; returns the setup and turn order
+    // when the host starts a game
+    pub fn poll(&mut self) -> Option<(GameSetup, Vec<usize>)> {
+        let mut start = None;
+        for msg in self.conn.recv::<ServerMessage>() {
+            match msg {
+                Ok(ServerMessage::Welcome { slot, token }) => {
+                    self.slot = Some(slot);
+                    self.token = Some(token);
+                }
+                Ok(ServerMessage::Lobby { seats, setup }) => {
+                    self.players = seats.len();
+                    self.seats = seats;
+                    self.settings = setup;
+                }
+                Ok(ServerMessage::Rooms { rooms }) => self.rooms = Some(rooms),
+                Ok(ServerMessage::Start {
+                    setup,
+                    order,
+                    profiles,
+                }) => {
+                    // Updates before a start belong to the previous game
+                    self.profiles = profiles;
+                    self.updates.clear();
+                    self.feed.clear();
+                    self.mines.clear();
+                    start = Some((setup, order));
+                }
+                // A game joined while running starts from its snapshot
+                Ok(ServerMessage::Snapshot(snapshot)) => {
+                    let snapshot = *snapshot;
+                    self.updates.clear();
+                    self.updates.push_back(snapshot.state);
+                    self.feed = snapshot.moves;
+                    self.mines = snapshot.mines;
+                    self.profiles = snapshot.profiles;
+                    self.set_clock(snapshot.time_left);
+                    start = Some((snapshot.setup, snapshot.order));
+                }
+                Ok(ServerMessage::Update(update)) => self.updates.push_back(update),
+                Ok(ServerMessage::Chat(line)) => self.chat.push(line),
+                Ok(ServerMessage::Move(played)) => self.feed.push(played),
+                Ok(ServerMessage::Clock { time_left }) => self.set_clock(Some(time_left)),
+                Ok(ServerMessage::Mines { mines }) => self.mines = mines,
+                Ok(ServerMessage::Error { message }) => self.error = Some(message),
+                Err(e) => self.error = Some(format!("invalid message from host: {}", e)),
+            }
+        }
+        start
+    }
+
+    fn set_clock(&mut self, time_left: Option<u64>) {
+        self.deadline = time_left.map(|ms| Instant::now() + Duration::from_millis(ms));
+    }
+
+    // Time the current player has left, on a server with turn times
+    pub fn time_left(&self) -> Option<Duration> {
+        self.deadline
+            .map(|d| d.saturating_duration_since(Instant::now()))
+    }
+
+    pub fn take_updates(&mut self) -> Vec<Update> {
+        self.updates.drain(..).collect()
+    }
+
+    pub fn take_chat(&mut self) -> Vec<ChatLine> {
+        self.chat.take()
+    }
+
+    // The client's copy of a game, without mines until the host reveals them
+    pub fn mirror(&self, setup: &GameSetup, order: &[usize]) -> Game {
+        mirror(setup, order, &self.profiles)
+    }
+}
+
+// A game without mines that only changes through updates
+fn mirror(setup: &GameSetup, order: &[usize], profiles: &[Profile]) -> Game {
+    let mut game = setup.build_with(order, 0, profiles);
+    game.grid = HexGrid::remote(setup.width, setup.height, setup.mines);
+    game
+}
+
+// The network side of a hosted or joined game
+pub enum Link {
+    Host(Host),
+    Client(Client),
+}
+
+impl Link {
+    // The slot played on this machine
+    pub fn slot(&self) -> Option<usize> {
+        match self {
+            Link::Host(_) => Some(0),
+            Link::Client(client) => client.slot,
+        }
+    }
+
+    // Everyone sees a line once the host has sent it back
+    pub fn say(&mut self, said: Said) {
+        match self {
+            Link::Host(host) => host.say(said),
+            Link::Client(client) => client.send(&ClientMessage::Chat { said }),
+        }
+    }
+
+    pub fn take_chat(&mut self) -> Vec<ChatLine> {
+        match self {
+            Link::Host(host) => host.take_chat(),
+            Link::Client(client) => client.take_chat(),
+        }
+    }
+
+    pub fn take_updates(&mut self) -> Vec<Update> {
+        match self {
+            Link::Host(host) => host.take_updates(),
+            Link::Client(client) => client.take_updates(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::net::new_token;
+    use crate::net::Client;
+    use crate::net::Host;
+    use crate::net::RESUME_TIME;
+    use crate::ChatLine;
+    use crate::ClientMessage;
+    use crate::Emote;
+    use crate::Game;
+    use crate::GameEvent;
+    use crate::GameSetup;
+    use crate::Profile;
+    use crate::Rgba;
+    use crate::Said;
+    use crate::TurnPolicy;
+    use std::thread;
+    use std::time::Duration;
+    use std::time::Instant;
+
+    // Headless harness: a host and its clients on localhost, pumped until a condition holds
+    struct Harness {
+        host: Host,
+        // The host's own window
+        view: Option<Game>,
+        clients: Vec<Client>,
+        mirrors: Vec<Option<Game>>,
+        events: Vec<GameEvent>,
+    }
+
+    impl Harness {
+        fn new(clients: usize) -> Self {
+            let host = Host::bind("127.0.0.1:0", 4).unwrap();
+            let addr = host.local_addr().unwrap().to_string();
+            let mut harness = Self {
+                host,
+                view: None,
+                clients: (0..clients)
+                    .map(|_| Client::connect(&addr).unwrap())
+                    .collect(),
+                mirrors: (0..clients).map(|_| None).collect(),
+                events: vec![],
+            };
+            harness.pump_until(|h| h.clients.iter().all(|c| c.players == clients + 1));
+            harness
+        }
+
+        fn start(&mut self, setup: GameSetup, mines: &[(usize, usize)]) {
+            let order: Vec<_> = (0..setup.players).collect();
+            self.view = Some(self.host.start(&setup, &order, 0));
+            self.host.game.as_mut().unwrap().grid.place_mines(mines);
+            self.pump_until(|h| h.mirrors.iter().all(|m| m.is_some()));
+        }
+
+        fn pump(&mut self) {
+            self.host.poll();
+            for update in self.host.take_updates() {
+                if let Some(view) = &mut self.view {
+                    update.apply(view);
+                }
+                self.events.extend(update.events);
+            }
+            for (client, mirror) in self.clients.iter_mut().zip(&mut self.mirrors) {
+                if let Some((setup, order)) = client.poll() {
+                    *mirror = Some(client.mirror(&setup, &order));
+                }
+                if let Some(mirror) = mirror {
+                    for update in client.take_updates() {
+                        update.apply(mirror);
+                    }
+                }
+            }
+        }
+
+        fn pump_until<F: Fn(&Self) -> bool>(&mut self, done: F) {
+            let start = Instant::now();
+            while !done(self) {
+                assert!(start.elapsed() < Duration::from_secs(5), "timed out");
+                self.pump();
+                thread::sleep(Duration::from_millis(1));
+            }
+        }
+
+        // The host plays its own move and sends the result
+        fn host_reveal(&mut self, x: usize, y: usize) {
+            self.host.play(&ClientMessage::Reveal { x, y });
+        }
+
+        fn game(&self) -> &Game {
+            self.host.game.as_ref().unwrap()
+        }
+
+        fn mirror(&self, i: usize) -> &Game {
+            self.mirrors[i].as_ref().unwrap()
+        }
+
+        fn in_sync(&self) -> bool {
+            let game = self.game();
+            let views = self.mirrors.iter().chain(std::iter::once(&self.view));
+            views.map(|m| m.as_ref().unwrap()).all(|mirror| {
+                mirror.curr_player == game.curr_player
+                    && mirror.players_alive == game.players_alive
+                    && mirror.cnt_revealed() == game.cnt_revealed()
+                    && mirror.grid.is_game_over() == game.grid.is_game_over()
+            })
+        }
+    }
+
+    // TEST accept
+    #[test]
+    fn accept_welcome() {
+        let harness = Harness::new(2);
+        let mut slots: Vec<_> = harness.clients.iter().map(|c| c.slot.unwrap()).collect();
+        slots.sort();
+        assert_eq!(slots, vec![1, 2]);
+        assert_eq!(harness.host.players(), 3);
+    }
+
+    #[test]
+    fn accept_full() {
+        let mut harness = Harness::new(1);
+        harness.host.max_players = 2;
+        let addr = harness.host.local_addr().unwrap().to_string();
+        let mut late = Client::connect(&addr).unwrap();
+        let start = Instant::now();
+        while late.error.is_none() {
+            assert!(start.elapsed() < Duration::from_secs(5));
+            harness.pump();
+            late.poll();
+        }
+        assert_eq!(late.error.as_deref(), Some("the game is full"));
+        assert_eq!(harness.host.players(), 2);
+    }
+
+    #[test]
+    fn accept_profiles() {
+        let mut harness = Harness::new(2);
+        let first = harness
+            .clients
+            .iter()
+            .position(|c| c.slot == Some(1))
+            .unwrap();
+        let second = 1 - first;
+        let wanted = Profile {
+            name: "  Ann  ".to_string(),
+            color: Rgba::MAGENTA,
+        };
+        harness.clients[second].send(&ClientMessage::Profile(wanted.clone()));
+        harness.pump_until(|h| h.clients.iter().all(|c| c.seats[2].profile.name == "Ann"));
+
+        // A taken colour moves on to the next free one
+        harness.clients[first].send(&ClientMessage::Profile(wanted));
+        harness.clients[first].send(&ClientMessage::Ready { ready: true });
+        harness.pump_until(|h| h.clients[second].seats[1].ready);
+        let colors: Vec<_> = harness.host.profiles().iter().map(|p| p.color).collect();
+        assert_eq!(colors, vec![Rgba::GREEN, Rgba::ORANGE, Rgba::MAGENTA]);
+        assert!(!harness.host.all_ready());
+        harness.clients[second].send(&ClientMessage::Ready { ready: true });
+        harness.pump_until(|h| h.host.all_ready());
+
+        harness.start(GameSetup::new(10, 10, 2, 3), &[(0, 0), (9, 9)]);
+        let player = &harness.mirror(0).players[2];
+        assert_eq!((player.name.as_str(), player.color), ("Ann", Rgba::MAGENTA));
+    }
+
+    // TEST poll
+    #[test]
+    fn poll_turns() {
+        let mut harness = Harness::new(2);
+        harness.start(GameSetup::new(10, 10, 2, 3), &[(0, 0), (9, 9)]);
+        harness.host_reveal(0, 1);
+        harness.pump_until(|h| h.in_sync() && h.mirror(0).cnt_revealed() == 1);
+
+        // Only the player whose turn it is may move
+        let slot = harness.clients[0].slot.unwrap();
+        let other = harness.clients[1].slot.unwrap();
+        let (mover, waiter) = if slot == 1 { (0, 1) } else { (1, 0) };
+        assert_eq!(harness.game().current().map(|p| p.slot), Some(1));
+        harness.clients[waiter].send(&ClientMessage::Reveal { x: 0, y: 2 });
+        harness.pump_until(|h| h.clients[waiter].error.is_some());
+        assert_eq!(
+            harness.clients[waiter].error.as_deref(),
+            Some("move rejected")
+        );
+        assert_eq!(harness.game().cnt_revealed(), 1);
+
+        harness.clients[mover].send(&ClientMessage::Reveal { x: 0, y: 0 });
+        harness.pump_until(|h| h.game().players_alive == 2 && h.in_sync());
+        assert!(harness.mirror(0).grid.is_flagged(0, 0));
+        assert_eq!(
+            harness.mirror(1).death(1).and_then(|d| d.tile),
+            Some((0, 0))
+        );
+        assert!(harness.events.contains(&GameEvent::MineHit {
+            tile: (0, 0),
+            player: 1
+        }));
+        assert_ne!(slot, other);
+    }
+
+    #[test]
+    fn poll_hides_mines() {
+        let mut harness = Harness::new(1);
+        harness.start(GameSetup::new(10, 10, 2, 2), &[(0, 0), (9, 9)]);
+        harness.host_reveal(0, 1);
+        harness.pump_until(|h| h.in_sync() && h.mirror(0).cnt_revealed() == 1);
+        assert!(!harness.mirror(0).grid.tile(0, 0).mine);
+        assert!(!harness.mirror(0).grid.tile(9, 9).mine);
+
+        // At the end of the game every mine is shown
+        harness.clients[0].send(&ClientMessage::Reveal { x: 9, y: 9 });
+        harness.pump_until(|h| h.mirror(0).players_alive == 1);
+        assert!(!harness.mirror(0).grid.tile(0, 0).mine);
+        harness.host_reveal(0, 0);
+        harness.pump_until(|h| h.mirror(0).grid.is_game_over());
+        assert!(harness.mirror(0).grid.tile(0, 0).mine);
+        assert_eq!(harness.mirror(0).grid.fatal(), Some((0, 0)));
+    }
+
+    #[test]
+    fn poll_simultaneous() {
+        let mut harness = Harness::new(2);
+        let mut setup = GameSetup::new(10, 10, 2, 3);
+        setup.turn_policy = TurnPolicy::Simultaneous;
+        harness.start(setup, &[(0, 0), (9, 9)]);
+        harness.clients[0].send(&ClientMessage::Reveal { x: 0, y: 1 });
+        harness.clients[1].send(&ClientMessage::Reveal { x: 1, y: 0 });
+        harness.clients[1].send(&ClientMessage::Flag { x: 0, y: 0 });
+        harness.pump_until(|h| h.game().cnt_revealed() == 2 && h.in_sync());
+        harness.pump_until(|h| h.mirror(0).grid.tile(0, 0).marked);
+        assert_eq!(harness.game().score(0), 0);
+    }
+
+    #[test]
+    fn poll_invalid() {
+        let mut harness = Harness::new(1);
+        harness.start(GameSetup::new(10, 10, 2, 2), &[(0, 0), (9, 9)]);
+        harness.clients[0].send(&ClientMessage::Reveal { x: 10, y: 0 });
+        harness.pump_until(|h| h.clients[0].error.is_some());
+        assert_eq!(
+            harness.clients[0].error.as_deref(),
+            Some("cell out of range")
+        );
+    }
+
+    #[test]
+    fn poll_disconnect() {
+        let mut harness = Harness::new(2);
+        harness.start(GameSetup::new(10, 10, 2, 3), &[(0, 0), (9, 9)]);
+        harness.clients.pop();
+        harness.mirrors.pop();
+        harness.pump_until(|h| h.host.players() == 2);
+    }
+
+    #[test]
+    fn poll_chat() {
+        let mut harness = Harness::new(2);
+        harness.start(GameSetup::new(10, 10, 2, 3), &[(0, 0), (9, 9)]);
+        let slot = harness.clients[0].slot.unwrap();
+        harness.clients[0].send(&ClientMessage::Chat {
+            said: Said::Text("gl hf".to_string()),
+        });
+        harness.host.say(Said::Emote(Emote::Thanks));
+
+        // The host relays every line, its own included, to everyone
+        let mut heard = vec![];
+        let start = Instant::now();
+        while heard.len() < 2 {
+            assert!(start.elapsed() < Duration::from_secs(5), "timed out");
+            harness.pump();
+            heard.extend(harness.clients[1].take_chat());
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert!(heard.contains(&ChatLine {
+            slot: 0,
+            said: Said::Emote(Emote::Thanks),
+        }));
+        assert!(heard.contains(&ChatLine {
+            slot,
+            said: Said::Text("gl hf".to_string()),
+        }));
+        assert_eq!(harness.host.take_chat().len(), 2);
+    }
+
+    #[test]
+    fn poll_dropped_turn() {
+        let mut harness = Harness::new(2);
+        harness.start(GameSetup::new(10, 10, 2, 3), &[(0, 0), (9, 9)]);
+        harness.host_reveal(0, 1);
+        harness.pump_until(|h| h.in_sync() && h.mirror(0).cnt_revealed() == 1);
+
+        // The player whose turn it is drops out and the others don't wait for them
+        let i = harness
+            .clients
+            .iter()
+            .position(|c| c.slot == Some(1))
+            .unwrap();
+        harness.clients.remove(i);
+        harness.mirrors.remove(i);
+        harness.pump_until(|h| h.game().current().map(|p| p.slot) == Some(2) && h.in_sync());
+        assert!(harness.events.contains(&GameEvent::Timeout { player: 1 }));
+        assert_eq!(harness.game().players_alive, 3);
+    }
+
+    // TEST resume
+    #[test]
+    fn resume_after_disconnect() {
+        let mut harness = Harness::new(2);
+        harness.start(GameSetup::new(10, 10, 2, 3), &[(0, 0), (9, 9)]);
+        harness.host_reveal(0, 1);
+        harness.pump_until(|h| h.in_sync() && h.mirror(0).cnt_revealed() == 1);
+
+        // A player waiting for their turn drops out; their place is kept
+        let i = harness
+            .clients
+            .iter()
+            .position(|c| c.slot == Some(2))
+            .unwrap();
+        let token = harness.clients[i].token.clone().unwrap();
+        let addr = harness.host.local_addr().unwrap().to_string();
+        harness.clients.remove(i);
+        harness.mirrors.remove(i);
+        harness.pump_until(|h| h.host.players() == 2);
+        assert!(harness.host.seats()[2].away);
+
+        harness.clients.push(Client::resume(&addr, &token).unwrap());
+        harness.mirrors.push(None);
+        harness.pump_until(|h| h.mirrors[1].is_some() && h.in_sync());
+        assert_eq!(harness.clients[1].slot, Some(2));
+        assert_eq!(harness.host.players(), 3);
+        assert!(!harness.host.seats()[2].away);
+        assert_eq!(harness.mirror(1).current().map(|p| p.slot), Some(1));
+        assert_eq!(harness.mirror(1).score(0), harness.game().score(0));
+
+        // Player 2 hits a mine and the turn comes to the player who rejoined
+        harness.clients[0].send(&ClientMessage::Reveal { x: 0, y: 0 });
+        harness.pump_until(|h| h.game().players_alive == 2 && h.in_sync());
+        assert!(harness.mirror(1).can_act(2));
+        harness.clients[1].send(&ClientMessage::Reveal { x: 9, y: 9 });
+        harness.pump_until(|h| h.game().players_alive == 1 && h.in_sync());
+        assert_eq!(
+            harness.mirror(1).death(2).and_then(|d| d.tile),
+            Some((9, 9))
+        );
+    }
+
+    #[test]
+    fn resume_unknown_token() {
+        let mut harness = Harness::new(1);
+        let addr = harness.host.local_addr().unwrap().to_string();
+        let mut stranger = Client::resume(&addr, "nope").unwrap();
+        let start = Instant::now();
+        while stranger.error.is_none() {
+            assert!(start.elapsed() < Duration::from_secs(5));
+            harness.pump();
+            stranger.poll();
+        }
+        assert_eq!(stranger.error.as_deref(), Some("unknown token"));
+        assert_eq!(harness.host.players(), 2);
+    }
+
+    #[test]
+    fn resume_too_late() {
+        let mut harness = Harness::new(2);
+        harness.start(GameSetup::new(10, 10, 2, 3), &[(0, 0), (9, 9)]);
+        harness.host_reveal(0, 1);
+        harness.pump_until(|h| h.in_sync() && h.mirror(0).cnt_revealed() == 1);
+
+        // The player whose turn it is never comes back; they are out and the turn passes on
+        let i = harness
+            .clients
+            .iter()
+            .position(|c| c.slot == Some(1))
+            .unwrap();
+        harness.clients.remove(i);
+        harness.mirrors.remove(i);
+        harness.pump_until(|h| h.host.lobby.members.iter().any(|r| r.left.is_some()));
+        for remote in &mut harness.host.lobby.members {
+            remote.left = remote.left.map(|_| Instant::now() - RESUME_TIME);
+        }
+        harness.pump_until(|h| h.game().players_alive == 2 && h.in_sync());
+        assert!(harness
+            .events
+            .contains(&GameEvent::Eliminated { player: 1 }));
+        assert_eq!(harness.mirror(0).current().map(|p| p.slot), Some(2));
+        assert_eq!(harness.mirror(0).death(1).map(|d| d.tile), Some(None));
+        assert_eq!(harness.host.players(), 2);
+    }
+
+    // TEST new_token
+    #[test]
+    fn new_token_random() {
+        let token = new_token();
+        assert_eq!(token.len(), 32);
+        assert!(token.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_ne!(token, new_token());
+    }
+}
@@ -1,79 +1,158 @@
+use crate::Rgba;
+#[cfg(feature = "gui")]
+use crate::Symbol;
+#[cfg(feature = "gui")]
+use crate::Theme;
+#[cfg(feature = "gui")]
 use ggez::graphics;
-use ggez::graphics::Color;
+#[cfg(feature = "gui")]
 use ggez::graphics::DrawMode;
+#[cfg(feature = "gui")]
+use ggez::graphics::MeshBuilder;
+#[cfg(feature = "gui")]
 use ggez::graphics::PxScale;
+#[cfg(feature = "gui")]
 use ggez::graphics::Rect;
+#[cfg(feature = "gui")]
 use ggez::graphics::Text;
+#[cfg(feature = "gui")]
 use ggez::graphics::TextFragment;
+#[cfg(feature = "gui")]
 use ggez::Context;
+#[cfg(feature = "gui")]
 use ggez::GameResult;
+#[cfg(feature = "gui")]
 use glam::Vec2;
 
 pub struct Player {
-    pub color: Color,
+    pub slot: usize,
+    pub color: Rgba,
     pub name: String,
-    txt: Text,
+    // Without teams every player is their own team
+    pub team: usize,
+    // Position within the team, picks the shade of the team colour
+    pub member: usize,
 }
 
 impl Player {
-    pub fn new(color: Color, name: String) -> Self {
-        let txt = Text::new(TextFragment {
-            text: String::from(&name) + " - " + &100.to_string(),
-            color: Some(Color::BLACK),
-            font: Some(graphics::Font::default()),
-            scale: Some(PxScale::from(30.0)),
-            ..Default::default()
-        });
-        Self { color, name, txt }
+    pub fn new(slot: usize, color: Rgba, name: String) -> Self {
+        Self {
+            slot,
+            color,
+            name,
+            team: slot,
+            member: 0,
+        }
     }
 
-    fn draw(&self, ctx: &mut Context, pos: Vec2, score: usize) -> GameResult {
-        let rect = graphics::Mesh::new_rectangle(
-            ctx,
+    pub fn with_team(mut self, team: usize, member: usize) -> Self {
+        self.team = team;
+        self.member = member;
+        self
+    }
+}
+
+#[cfg(feature = "gui")]
+impl Player {
+    pub fn symbol(&self) -> Symbol {
+        Symbol::for_slot(self.slot)
+    }
+
+    fn text(&self, theme: &Theme, score: usize) -> Text {
+        Text::new(TextFragment {
+            text: self.name.to_string() + " - " + &score.to_string(),
+            color: Some(theme.text),
+            font: Some(theme.fonts.text),
+            scale: Some(PxScale::from(theme.text_size)),
+        })
+    }
+
+    fn width(&self, ctx: &Context, theme: &Theme) -> f32 {
+        self.text(theme, 100).width(ctx) + 60.0
+    }
+
+    fn draw(&self, ctx: &mut Context, theme: &Theme, pos: Vec2, score: usize) -> GameResult {
+        let mut mb = MeshBuilder::new();
+        mb.rectangle(
             DrawMode::fill(),
-            Rect::new(0.0, 0.0, self.txt.width(ctx) + 60.0, 100.0),
-            self.color,
+            Rect::new(0.0, 0.0, self.width(ctx, theme), 100.0),
+            theme.player_color(self),
         )?;
+        if theme.symbols {
+            self.symbol()
+                .build(&mut mb, Vec2::new(15.0, 50.0), 8.0, theme.symbol)?;
+        }
+        let rect = mb.build(ctx)?;
         graphics::draw(ctx, &rect, (pos,))?;
 
-        let txt = Text::new(TextFragment {
-            text: self.name.to_string() + " - " + &score.to_string(),
-            color: Some(Color::BLACK),
-            font: Some(graphics::Font::default()),
-            scale: Some(PxScale::from(30.0)),
-            ..Default::default()
-        });
-        graphics::draw(ctx, &txt, (pos + Vec2::new(30.0, 35.0),))
+        let txt = self.text(theme, score);
+        let height = txt.height(ctx);
+        graphics::draw(ctx, &txt, (pos + Vec2::new(30.0, (100.0 - height) / 2.0),))
     }
 
-    pub fn draw_active(&self, ctx: &mut Context, pos: Vec2, score: usize) -> GameResult {
-        self.draw(ctx, pos, score)
+    pub fn draw_active(
+        &self,
+        ctx: &mut Context,
+        theme: &Theme,
+        pos: Vec2,
+        score: usize,
+    ) -> GameResult {
+        self.draw(ctx, theme, pos, score)
     }
 
-    pub fn draw_inactive(&self, ctx: &mut Context, pos: Vec2, score: usize) -> GameResult {
-        self.draw(ctx, pos, score)?;
+    pub fn draw_inactive(
+        &self,
+        ctx: &mut Context,
+        theme: &Theme,
+        pos: Vec2,
+        score: usize,
+    ) -> GameResult {
+        self.draw(ctx, theme, pos, score)?;
 
         let cover = graphics::Mesh::new_rectangle(
             ctx,
             DrawMode::fill(),
-            Rect::new(0.0, 0.0, self.txt.width(ctx) + 60.0, 100.0),
-            Color::new(0.0, 0.0, 0.0, 0.9),
+            Rect::new(0.0, 0.0, self.width(ctx, theme), 100.0),
+            theme.panel_cover,
         )?;
         graphics::draw(ctx, &cover, (pos,))
     }
 
-    pub fn draw_dead(&self, ctx: &mut Context, pos: Vec2, score: usize) -> GameResult {
-        self.draw_inactive(ctx, pos, score)?;
+    pub fn draw_dead(
+        &self,
+        ctx: &mut Context,
+        theme: &Theme,
+        pos: Vec2,
+        score: usize,
+    ) -> GameResult {
+        self.draw_inactive(ctx, theme, pos, score)?;
 
         let line = graphics::Mesh::new_line(
             ctx,
-            &vec![
+            &[
                 Vec2::new(0.0, 0.0),
-                Vec2::new(self.txt.width(ctx) + 60.0, 100.0),
+                Vec2::new(self.width(ctx, theme), 100.0),
             ],
             2.0,
-            Color::RED,
+            theme.panel_dead,
         )?;
         graphics::draw(ctx, &line, (pos,))
     }
+
+    // Lives, penalties or shields, below the name
+    pub fn draw_status(
+        &self,
+        ctx: &mut Context,
+        theme: &Theme,
+        pos: Vec2,
+        status: &str,
+    ) -> GameResult {
+        let txt = Text::new(TextFragment {
+            text: status.to_string(),
+            color: Some(theme.text),
+            font: Some(theme.fonts.text),
+            scale: Some(PxScale::from(theme.text_size * 0.6)),
+        });
+        graphics::draw(ctx, &txt, (pos + Vec2::new(30.0, 72.0),))
+    }
 }
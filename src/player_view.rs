@@ -0,0 +1,364 @@
+use crate::hex_grid::neighbours;
+use crate::hex_grid::step;
+use crate::hex_grid::tile_pos;
+use crate::hex_grid::TILE_SIZE;
+#[cfg(feature = "gui")]
+use crate::Animator;
+use crate::CellState;
+use crate::Direction;
+use crate::HexTile;
+#[cfg(feature = "gui")]
+use crate::Player;
+#[cfg(feature = "gui")]
+use crate::Theme;
+#[cfg(feature = "gui")]
+use crate::TileBatch;
+use glam::Vec2;
+use serde::Deserialize;
+use serde::Serialize;
+#[cfg(feature = "gui")]
+use std::rc::Rc;
+
+#[cfg(feature = "gui")]
+#[derive(Clone, Copy, Default)]
+pub struct TileHints {
+    pub hovered: bool,
+    pub preview: bool,
+    pub satisfied: bool,
+    pub wrong_flag: bool,
+    pub fatal: bool,
+    pub flip: Option<f32>,
+}
+
+// The board as a player may know it: covered, flagged and revealed cells with their numbers.
+// Mines only appear once hit or when the game is over, so a view is safe to draw, send or export.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct PlayerView {
+    cells: Vec<Vec<CellState>>,
+    mines: usize,
+    game_over: bool,
+    fatal: Option<(usize, usize)>,
+}
+
+impl PlayerView {
+    pub fn new(cnt_x: usize, cnt_y: usize, mines: usize) -> Self {
+        Self {
+            cells: vec![vec![CellState::Covered; cnt_y]; cnt_x],
+            mines,
+            game_over: false,
+            fatal: None,
+        }
+    }
+
+    pub fn size(&self) -> (usize, usize) {
+        (self.cells.len(), self.cells[0].len())
+    }
+
+    pub fn mine_number(&self) -> usize {
+        self.mines
+    }
+
+    pub fn cell(&self, x: usize, y: usize) -> CellState {
+        self.cells[x][y]
+    }
+
+    pub fn set_cell(&mut self, x: usize, y: usize, state: CellState) {
+        self.cells[x][y] = state;
+    }
+
+    pub fn end_game(&mut self, fatal: Option<(usize, usize)>) {
+        self.game_over = true;
+        self.fatal = fatal;
+    }
+
+    pub fn is_game_over(&self) -> bool {
+        self.game_over
+    }
+
+    pub fn fatal(&self) -> Option<(usize, usize)> {
+        self.fatal
+    }
+
+    pub fn neighbours(&self, x: usize, y: usize) -> Vec<(usize, usize)> {
+        neighbours(self.size(), x, y)
+    }
+
+    pub fn step(&self, x: usize, y: usize, dir: Direction) -> Option<(usize, usize)> {
+        step(self.size(), x, y, dir)
+    }
+
+    pub fn pos(&self, x: usize, y: usize) -> Vec2 {
+        tile_pos(x, y)
+    }
+
+    pub fn bounds(&self) -> (Vec2, Vec2) {
+        let half = Vec2::splat(TILE_SIZE / 2.0);
+        let mut min = Vec2::splat(f32::MAX);
+        let mut max = Vec2::splat(f32::MIN);
+        for (x, y) in self.cells_where(|_| true) {
+            min = min.min(tile_pos(x, y) - half);
+            max = max.max(tile_pos(x, y) + half);
+        }
+        (min, max)
+    }
+
+    pub fn cell_at(&self, pos: Vec2) -> Option<(usize, usize)> {
+        let (size_x, size_y) = self.size();
+        (0..size_x)
+            .flat_map(|x| (0..size_y).map(move |y| (x, y)))
+            .find(|(x, y)| HexTile::new(TILE_SIZE, tile_pos(*x, *y)).is_inside(pos))
+    }
+
+    // The number on a revealed cell
+    pub fn number(&self, x: usize, y: usize) -> Option<usize> {
+        match self.cells[x][y] {
+            CellState::Revealed { count, .. } => Some(count),
+            _ => None,
+        }
+    }
+
+    // Mines that were hit stay revealed and count as flags
+    pub fn is_flagged(&self, x: usize, y: usize) -> bool {
+        matches!(
+            self.cells[x][y],
+            CellState::Flagged | CellState::Mine { .. }
+        )
+    }
+
+    pub fn is_satisfied(&self, x: usize, y: usize) -> bool {
+        match self.number(x, y) {
+            Some(num) => {
+                self.neighbours(x, y)
+                    .iter()
+                    .filter(|(nx, ny)| self.is_flagged(*nx, *ny))
+                    .count()
+                    == num
+            }
+            None => false,
+        }
+    }
+
+    // At the end of the game, flagged mines are shown as mines
+    pub fn is_wrong_flag(&self, x: usize, y: usize) -> bool {
+        self.game_over && self.cells[x][y] == CellState::Flagged
+    }
+
+    // Cells that are neither revealed nor flagged
+    pub fn covered(&self) -> Vec<(usize, usize)> {
+        self.cells_where(|state| state == CellState::Covered)
+    }
+
+    // Mines shown at the end of the game that nobody hit or flagged
+    pub fn exposed_mines(&self) -> Vec<(usize, usize)> {
+        self.cells_where(|state| {
+            state
+                == CellState::Mine {
+                    hit_by: None,
+                    flagged: false,
+                }
+        })
+    }
+
+    pub(crate) fn cells_where<F: Fn(CellState) -> bool>(&self, f: F) -> Vec<(usize, usize)> {
+        let mut res = vec![];
+        for (x, i) in self.cells.iter().enumerate() {
+            for (y, state) in i.iter().enumerate() {
+                if f(*state) {
+                    res.push((x, y));
+                }
+            }
+        }
+        res
+    }
+
+    #[cfg(feature = "gui")]
+    pub fn draw(
+        &self,
+        batch: &mut TileBatch,
+        theme: &Theme,
+        players: &[Rc<Player>],
+        hover: Option<(usize, usize)>,
+        animator: &Animator,
+    ) {
+        let preview = match hover {
+            Some((x, y)) if self.number(x, y).is_some() => self.neighbours(x, y),
+            _ => vec![],
+        };
+        let (size_x, size_y) = self.size();
+        for x in 0..size_x {
+            for y in 0..size_y {
+                let hints = TileHints {
+                    hovered: hover == Some((x, y)),
+                    preview: preview.contains(&(x, y)),
+                    satisfied: self.is_satisfied(x, y),
+                    wrong_flag: self.is_wrong_flag(x, y),
+                    fatal: self.fatal == Some((x, y)),
+                    flip: animator.flip_progress((x, y)),
+                };
+                self.draw_cell(batch, theme, players, (x, y), hints);
+            }
+        }
+    }
+
+    #[cfg(feature = "gui")]
+    fn draw_cell(
+        &self,
+        batch: &mut TileBatch,
+        theme: &Theme,
+        players: &[Rc<Player>],
+        (x, y): (usize, usize),
+        hints: TileHints,
+    ) {
+        let pos = tile_pos(x, y);
+        // Flagged mines keep their flag at the end of the game
+        let (shown, flagged, mine, owner) = match self.cells[x][y] {
+            CellState::Covered => (false, false, false, None),
+            CellState::Flagged => (false, true, false, None),
+            CellState::Revealed { by, .. } => (true, false, false, Some(by)),
+            CellState::Mine { hit_by, flagged } => (
+                hit_by.is_some() || !flagged,
+                flagged && hit_by.is_none(),
+                true,
+                hit_by,
+            ),
+        };
+        let owner = owner.and_then(|slot| players.iter().find(|p| p.slot == slot));
+        // A flip shows the covered face for its first half and the revealed face after
+        let progress = hints.flip.unwrap_or(1.0).clamp(0.0, 1.0);
+        let revealed = shown && progress >= 0.5;
+        batch.add_tile(
+            pos,
+            Vec2::new((2.0 * progress - 1.0).abs(), 1.0),
+            if !revealed {
+                if flagged {
+                    theme.flag
+                } else if hints.preview {
+                    theme.preview
+                } else {
+                    theme.covered
+                }
+            } else if let Some(p) = owner {
+                theme.player_color(p)
+            } else {
+                theme.mine
+            },
+            theme.border,
+        );
+        if flagged && !revealed {
+            batch.add_flag(pos, theme.flag_glyph);
+            if hints.wrong_flag {
+                batch.add_cross(pos, theme.wrong_flag);
+            }
+        }
+        if revealed && mine {
+            batch.add_mine(pos, theme.mine_glyph);
+        }
+        if revealed && !mine {
+            if let Some(p) = owner.filter(|_| theme.symbols) {
+                batch.add_symbol(p.symbol(), pos, theme.symbol);
+            }
+            if let Some(num) = self.number(x, y).filter(|n| *n > 0) {
+                batch.add_number(pos, num, theme.number_color(num, hints.satisfied));
+            }
+        }
+        if hints.fatal {
+            batch.add_highlight(pos, theme.game_over);
+        } else if hints.hovered {
+            batch.add_highlight(pos, theme.hover);
+        } else if hints.preview {
+            let mut color = theme.hover;
+            color.a *= 0.5;
+            batch.add_highlight(pos, color);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::CellState;
+    use crate::GameSetup;
+    use crate::HexGrid;
+    use crate::PlayerView;
+
+    // TEST view
+    #[test]
+    fn view_hides_mines() {
+        let mut game = GameSetup::new(10, 10, 2, 2).new_game();
+        game.grid.place_mines(&[(0, 0), (9, 9)]);
+        game.toggle_mark(5, 5);
+        game.reveal(0, 1);
+        let view = game.grid.view();
+        assert_eq!(view.cell(0, 0), CellState::Covered);
+        assert_eq!(view.cell(5, 5), CellState::Flagged);
+        assert_eq!(view.number(0, 1), Some(1));
+        assert_eq!(view.covered().len(), 98);
+        assert!(view.exposed_mines().is_empty());
+        assert!(!serde_json::to_string(&view)
+            .unwrap()
+            .contains(r#""state":"mine""#));
+    }
+
+    #[test]
+    fn view_game_over() {
+        let mut game = GameSetup::new(10, 10, 2, 1).new_game();
+        game.grid.place_mines(&[(0, 0), (9, 9)]);
+        game.toggle_mark(5, 5);
+        game.toggle_mark(0, 0);
+        game.reveal(9, 9);
+        let view = game.grid.view();
+        assert!(view.is_game_over());
+        assert_eq!(view.fatal(), Some((9, 9)));
+        assert_eq!(
+            view.cell(0, 0),
+            CellState::Mine {
+                hit_by: None,
+                flagged: true
+            }
+        );
+        assert!(view.is_wrong_flag(5, 5));
+        assert!(!view.is_wrong_flag(0, 0));
+        assert!(view.exposed_mines().is_empty());
+    }
+
+    // TEST is_satisfied
+    #[test]
+    fn is_satisfied_hit_mine() {
+        let mut game = GameSetup::new(10, 10, 2, 3).new_game();
+        game.grid.place_mines(&[(0, 0), (9, 9)]);
+        game.reveal(0, 1);
+        let view = game.grid.view();
+        assert!(!view.is_satisfied(0, 1));
+        game.reveal(0, 0);
+        assert!(game.grid.view().is_satisfied(0, 1));
+    }
+
+    // TEST neighbours
+    #[test]
+    fn neighbours_match_grid() {
+        let grid = HexGrid::new(7, 5, 3);
+        let view = PlayerView::new(7, 5, 3);
+        for x in 0..7 {
+            for y in 0..5 {
+                assert_eq!(view.neighbours(x, y), grid.get_neighbours(x, y));
+                assert_eq!(view.pos(x, y), grid.tile(x, y).pos);
+            }
+        }
+    }
+
+    // TEST cell_at
+    #[test]
+    fn cell_at_pos() {
+        let view = PlayerView::new(6, 6, 3);
+        assert_eq!(view.cell_at(view.pos(4, 3)), Some((4, 3)));
+        assert_eq!(view.cell_at(view.pos(0, 0) - view.pos(1, 0)), None);
+    }
+
+    // TEST bounds
+    #[test]
+    fn bounds_grid() {
+        for (x, y) in [(5, 5), (6, 1), (9, 14)] {
+            let grid = HexGrid::new(x, y, 1);
+            assert_eq!(PlayerView::new(x, y, 1).bounds(), grid.bounds());
+        }
+    }
+}
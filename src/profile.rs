@@ -0,0 +1,116 @@
+use crate::Rgba;
+use serde::Deserialize;
+use serde::Serialize;
+
+pub const MAX_NAME: usize = 16;
+
+// Colours to pick from in the lobby; the first ones are the defaults by slot
+pub const PALETTE: [(&str, Rgba); 8] = [
+    ("Green", Rgba::GREEN),
+    ("Blue", Rgba::BLUE),
+    ("Yellow", Rgba::YELLOW),
+    ("Cyan", Rgba::CYAN),
+    ("Magenta", Rgba::MAGENTA),
+    ("Orange", Rgba::ORANGE),
+    ("Purple", Rgba::PURPLE),
+    ("Pink", Rgba::PINK),
+];
+
+// The name and colour a player picked in the lobby
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Profile {
+    pub name: String,
+    pub color: Rgba,
+}
+
+impl Profile {
+    pub fn new(slot: usize) -> Self {
+        Self {
+            name: format!("Player {}", slot + 1),
+            color: PALETTE[slot % PALETTE.len()].1,
+        }
+    }
+
+    // Names are trimmed and shortened, and empty ones fall back to the slot's default
+    pub fn cleaned(mut self, slot: usize) -> Self {
+        self.name = self.name.trim().chars().take(MAX_NAME).collect();
+        if self.name.is_empty() {
+            self.name = Self::new(slot).name;
+        }
+        self.color = PALETTE[palette_index(self.color)].1;
+        self
+    }
+
+    pub fn color_name(&self) -> &'static str {
+        PALETTE[palette_index(self.color)].0
+    }
+
+    // Moves on to the next colour nobody else has
+    pub fn cycle_color(&mut self, taken: &[Rgba], step: isize) {
+        let len = PALETTE.len() as isize;
+        let next = (palette_index(self.color) as isize + step.signum()).rem_euclid(len);
+        self.color = free_color(PALETTE[next as usize].1, taken, step);
+    }
+}
+
+// Colours outside the palette count as its first
+fn palette_index(color: Rgba) -> usize {
+    PALETTE.iter().position(|(_, c)| *c == color).unwrap_or(0)
+}
+
+// The wanted colour if nobody has it, otherwise the next free one in the palette,
+// going backwards for a negative step
+pub fn free_color(wanted: Rgba, taken: &[Rgba], step: isize) -> Rgba {
+    let len = PALETTE.len() as isize;
+    let start = palette_index(wanted) as isize;
+    let step = if step < 0 { -1 } else { 1 };
+    (0..len)
+        .map(|i| PALETTE[(start + i * step).rem_euclid(len) as usize].1)
+        .find(|c| !taken.contains(c))
+        .unwrap_or(wanted)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::profile::free_color;
+    use crate::Profile;
+    use crate::Rgba;
+
+    // TEST cleaned
+    #[test]
+    fn cleaned_name() {
+        let profile = Profile {
+            name: "  A very long player name  ".to_string(),
+            color: Rgba::new(0.1, 0.2, 0.3, 1.0),
+        };
+        let profile = profile.cleaned(2);
+        assert_eq!(profile.name, "A very long play");
+        assert_eq!(profile.color, Rgba::GREEN);
+        let blank = Profile {
+            name: " ".to_string(),
+            color: Rgba::BLUE,
+        };
+        assert_eq!(blank.cleaned(2).name, "Player 3");
+    }
+
+    // TEST cycle_color
+    #[test]
+    fn cycle_color_skips_taken() {
+        let mut profile = Profile::new(0);
+        profile.cycle_color(&[Rgba::BLUE], 1);
+        assert_eq!(profile.color, Rgba::YELLOW);
+        profile.cycle_color(&[Rgba::BLUE], -1);
+        assert_eq!(profile.color, Rgba::GREEN);
+        profile.cycle_color(&[], -1);
+        assert_eq!(profile.color, Rgba::PINK);
+    }
+
+    // TEST free_color
+    #[test]
+    fn free_color_skips_taken() {
+        let taken = [Rgba::BLUE, Rgba::YELLOW];
+        assert_eq!(free_color(Rgba::GREEN, &taken, 1), Rgba::GREEN);
+        assert_eq!(free_color(Rgba::BLUE, &taken, 1), Rgba::CYAN);
+        assert_eq!(free_color(Rgba::YELLOW, &taken, -1), Rgba::GREEN);
+    }
+}
@@ -0,0 +1,394 @@
+use crate::ChatLine;
+use crate::Death;
+use crate::Game;
+use crate::GameEvent;
+use crate::GameSetup;
+use crate::PlayerView;
+use crate::Profile;
+use crate::Said;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde::Serialize;
+use std::io;
+
+// See PROTOCOL.md for the message flow
+pub const PROTOCOL_VERSION: u32 = 1;
+pub const DEFAULT_PORT: u16 = 7878;
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    Hello {
+        version: u32,
+    },
+    // Sent instead of hello to take a player's place again after losing the connection
+    Resume {
+        version: u32,
+        token: String,
+    },
+    Reveal {
+        x: usize,
+        y: usize,
+    },
+    Chord {
+        x: usize,
+        y: usize,
+    },
+    Flag {
+        x: usize,
+        y: usize,
+    },
+    // A line of chat or an emote, for everyone in the game
+    Chat {
+        said: Said,
+    },
+    // Lobby only: the name and colour to play with
+    Profile(Profile),
+    Ready {
+        ready: bool,
+    },
+    // Only on the dedicated server: asks for the rooms
+    ListRooms,
+    // Only on the dedicated server: joins a room, creating it if needed
+    Join {
+        room: String,
+    },
+    // Only on the dedicated server, from the first player in the room: the board shown
+    // in the lobby, then the game to start once everyone is ready
+    Settings {
+        setup: GameSetup,
+    },
+    Start {
+        setup: GameSetup,
+    },
+    // Only on the dedicated server: watches a room; the caster key also shows the mines
+    Watch {
+        room: String,
+        #[serde(default)]
+        key: Option<String>,
+    },
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage {
+    // The slot this client plays and the token to rejoin with
+    Welcome {
+        slot: usize,
+        token: String,
+    },
+    // Players connected so far, the host included, and the board the host picked
+    Lobby {
+        seats: Vec<Seat>,
+        setup: Option<GameSetup>,
+    },
+    // Names and colours are indexed by slot
+    Start {
+        setup: GameSetup,
+        order: Vec<usize>,
+        #[serde(default)]
+        profiles: Vec<Profile>,
+    },
+    Update(Update),
+    Chat(ChatLine),
+    Error {
+        message: String,
+    },
+    // The game so far, for players rejoining it and spectators
+    Snapshot(Box<Snapshot>),
+    // The rest is only sent by the dedicated server
+    Rooms {
+        rooms: Vec<RoomInfo>,
+    },
+    // A move as it is played, sent to spectators before its update
+    Move(Move),
+    // Milliseconds the current player has left, whenever the turn's time restarts
+    Clock {
+        time_left: u64,
+    },
+    // Every mine on the board, for casters only, once the mines are placed
+    Mines {
+        mines: Vec<(usize, usize)>,
+    },
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Seat {
+    pub slot: usize,
+    pub profile: Profile,
+    pub ready: bool,
+    // Lost the connection during the game and may still come back
+    #[serde(default)]
+    pub away: bool,
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct RoomInfo {
+    pub name: String,
+    pub players: usize,
+    pub running: bool,
+}
+
+// One entry of the move feed; a move of `None` is a turn lost to the clock
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Move {
+    pub slot: usize,
+    pub message: Option<ClientMessage>,
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub setup: GameSetup,
+    pub order: Vec<usize>,
+    pub profiles: Vec<Profile>,
+    // Every cell shown so far and the state of the players, with no events
+    pub state: Update,
+    pub moves: Vec<Move>,
+    // Empty unless watching as a caster
+    pub mines: Vec<(usize, usize)>,
+    pub time_left: Option<u64>,
+}
+
+// What a player may know about a cell
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum CellState {
+    Covered,
+    Flagged,
+    Revealed {
+        count: usize,
+        by: usize,
+    },
+    // Mines are only sent once hit or when the game is over
+    Mine {
+        hit_by: Option<usize>,
+        flagged: bool,
+    },
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Cell {
+    pub x: usize,
+    pub y: usize,
+    pub state: CellState,
+}
+
+// The cells changed by a move and the game state after it
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Update {
+    pub cells: Vec<Cell>,
+    pub events: Vec<GameEvent>,
+    // Player slots in turn order, the first `alive` of them still playing
+    pub order: Vec<usize>,
+    pub alive: usize,
+    // Index into order of the player whose turn it is
+    pub turn: usize,
+    pub hits: Vec<usize>,
+    pub shields: Vec<usize>,
+    pub deaths: Vec<Death>,
+    pub game_over: bool,
+    pub fatal: Option<(usize, usize)>,
+}
+
+impl Update {
+    pub fn new(game: &Game, events: &[GameEvent]) -> Self {
+        let mut tiles = vec![];
+        for event in events {
+            match event {
+                GameEvent::Revealed { cells, .. } => tiles.extend(cells.iter().copied()),
+                GameEvent::Flagged { tile, .. } => tiles.push(*tile),
+                _ => {}
+            }
+        }
+        // Cells are taken from the view, so mines are only ever sent once shown
+        let view = game.grid.view();
+        // Once the game is over every mine is shown, and flags are marked right or wrong
+        if view.is_game_over() {
+            tiles.extend(
+                view.cells_where(|state| {
+                    matches!(state, CellState::Mine { .. } | CellState::Flagged)
+                }),
+            );
+        }
+        Self::with_cells(game, &view, tiles, events)
+    }
+
+    // The whole board as a player sees it, to catch up with a running game
+    pub fn snapshot(game: &Game) -> Self {
+        let view = game.grid.view();
+        let tiles = view.cells_where(|state| state != CellState::Covered);
+        Self::with_cells(game, &view, tiles, &[])
+    }
+
+    fn with_cells(
+        game: &Game,
+        view: &PlayerView,
+        mut tiles: Vec<(usize, usize)>,
+        events: &[GameEvent],
+    ) -> Self {
+        tiles.sort();
+        tiles.dedup();
+        Self {
+            cells: tiles
+                .into_iter()
+                .map(|(x, y)| Cell {
+                    x,
+                    y,
+                    state: view.cell(x, y),
+                })
+                .collect(),
+            events: events.to_vec(),
+            order: game.players.iter().map(|p| p.slot).collect(),
+            alive: game.players_alive,
+            turn: game.curr_player,
+            hits: game.hits.clone(),
+            shields: game.shields.clone(),
+            deaths: game.deaths.clone(),
+            game_over: view.is_game_over(),
+            fatal: view.fatal(),
+        }
+    }
+
+    // Brings a client's copy of the game up to date
+    pub fn apply(&self, game: &mut Game) {
+        for cell in &self.cells {
+            game.grid
+                .set_cell_state(cell.x, cell.y, cell.state, &game.players);
+        }
+        let players = self
+            .order
+            .iter()
+            .filter_map(|slot| game.players.iter().find(|p| p.slot == *slot).cloned())
+            .collect();
+        game.players = players;
+        game.players_alive = self.alive;
+        game.curr_player = self.turn;
+        game.hits = self.hits.clone();
+        game.shields = self.shields.clone();
+        game.deaths = self.deaths.clone();
+        if self.game_over && !game.grid.is_game_over() {
+            game.grid.end_game(self.fatal);
+        }
+    }
+}
+
+// Messages are sent as one line of JSON each
+pub fn encode<T: Serialize>(msg: &T) -> Vec<u8> {
+    let mut line = serde_json::to_vec(msg).unwrap_or_default();
+    line.push(b'\n');
+    line
+}
+
+pub fn decode<T: DeserializeOwned>(line: &[u8]) -> io::Result<T> {
+    serde_json::from_slice(line).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::protocol::decode;
+    use crate::protocol::encode;
+    use crate::CellState;
+    use crate::ClientMessage;
+    use crate::GameSetup;
+    use crate::HexGrid;
+    use crate::ServerMessage;
+    use crate::Update;
+
+    // TEST encode
+    #[test]
+    fn encode_line() {
+        let line = encode(&ClientMessage::Reveal { x: 1, y: 2 });
+        assert_eq!(line, b"{\"type\":\"reveal\",\"x\":1,\"y\":2}\n".to_vec());
+        let msg: ClientMessage = decode(&line[..line.len() - 1]).unwrap();
+        assert_eq!(msg, ClientMessage::Reveal { x: 1, y: 2 });
+    }
+
+    // TEST decode
+    #[test]
+    fn decode_invalid() {
+        assert!(decode::<ServerMessage>(b"{\"type\":\"nope\"}").is_err());
+        assert!(decode::<ServerMessage>(b"not json").is_err());
+    }
+
+    // TEST new
+    #[test]
+    fn new_hides_mines() {
+        let mut game = GameSetup::new(10, 10, 2, 2).new_game();
+        game.grid.place_mines(&[(0, 0), (9, 9)]);
+        let events = game.reveal(0, 1);
+        let update = Update::new(&game, &events);
+        assert_eq!(update.cells.len(), 1);
+        assert_eq!(
+            update.cells[0].state,
+            CellState::Revealed { count: 1, by: 0 }
+        );
+        assert_eq!(update.turn, 1);
+        let json = String::from_utf8(encode(&ServerMessage::Update(update))).unwrap();
+        assert!(!json.contains("mine"));
+    }
+
+    #[test]
+    fn new_game_over_shows_mines() {
+        let mut game = GameSetup::new(10, 10, 2, 1).new_game();
+        game.grid.place_mines(&[(0, 0), (9, 9)]);
+        game.toggle_mark(5, 5);
+        let events = game.reveal(9, 9);
+        let update = Update::new(&game, &events);
+        assert!(update.game_over);
+        assert_eq!(update.fatal, Some((9, 9)));
+        assert_eq!(update.cells.len(), 3);
+        assert!(update.cells.iter().any(|c| c.state
+            == CellState::Mine {
+                hit_by: Some(0),
+                flagged: false
+            }));
+    }
+
+    // TEST apply
+    #[test]
+    fn apply_mirror() {
+        let setup = GameSetup::new(10, 10, 2, 3);
+        let mut game = setup.new_game();
+        game.grid.place_mines(&[(0, 0), (9, 9)]);
+        let mut mirror = setup.build(&[0, 1, 2], 0);
+        mirror.grid = HexGrid::remote(10, 10, 2);
+
+        for (x, y) in [(0, 1), (0, 0), (1, 1)] {
+            let events = game.reveal(x, y);
+            Update::new(&game, &events).apply(&mut mirror);
+        }
+        assert_eq!(mirror.players_alive, 2);
+        assert_eq!(
+            mirror.current().map(|p| p.slot),
+            game.current().map(|p| p.slot)
+        );
+        for i in 0..3 {
+            assert_eq!(mirror.players[i].slot, game.players[i].slot);
+            assert_eq!(mirror.score(i), game.score(i));
+        }
+        assert!(mirror.grid.is_flagged(0, 0));
+        assert_eq!(mirror.death(1), game.death(1));
+    }
+
+    // TEST snapshot
+    #[test]
+    fn snapshot_catches_up() {
+        let setup = GameSetup::new(10, 10, 2, 2);
+        let mut game = setup.new_game();
+        game.grid.place_mines(&[(0, 0), (9, 9)]);
+        game.toggle_mark(9, 9);
+        game.reveal(0, 1);
+        game.reveal(5, 5);
+        let mut mirror = setup.build(&[0, 1], 0);
+        mirror.grid = HexGrid::remote(10, 10, 2);
+        let snapshot = Update::snapshot(&game);
+        assert!(snapshot.events.is_empty());
+        snapshot.apply(&mut mirror);
+        assert_eq!(mirror.grid.view(), game.grid.view());
+        assert_eq!(mirror.score(1), game.score(1));
+        assert_eq!(
+            mirror.current().map(|p| p.slot),
+            game.current().map(|p| p.slot)
+        );
+    }
+}
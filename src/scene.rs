@@ -0,0 +1,348 @@
+use ggez::event;
+use ggez::event::Axis;
+use ggez::event::Button;
+use ggez::event::GamepadId;
+use ggez::event::MouseButton;
+use ggez::graphics;
+use ggez::input::keyboard::KeyCode;
+use ggez::input::keyboard::KeyMods;
+use ggez::timer;
+use ggez::Context;
+use ggez::GameResult;
+use std::time::Duration;
+use std::time::Instant;
+
+const ACTIVE_FPS: u32 = 60;
+const IDLE_FPS: u32 = 10;
+const IDLE_AFTER: Duration = Duration::from_secs(1);
+
+pub enum Transition<S> {
+    None,
+    Push(Box<dyn Scene<S>>),
+    Pop,
+    Replace(Box<dyn Scene<S>>),
+    // Replaces the whole stack, bottom scene first
+    Reset(Vec<Box<dyn Scene<S>>>),
+}
+
+// State shared by every scene, e.g. themes and audio; it can also change scenes,
+// e.g. when a network game starts
+pub trait SharedState: Sized {
+    fn update(&mut self, _ctx: &mut Context) -> Transition<Self> {
+        Transition::None
+    }
+}
+
+impl SharedState for () {}
+
+// Mirrors the ggez EventHandler, with access to the shared state and a transition result
+pub trait Scene<S> {
+    fn update(&mut self, _ctx: &mut Context, _shared: &mut S) -> GameResult<Transition<S>> {
+        Ok(Transition::None)
+    }
+
+    fn draw(&mut self, ctx: &mut Context, shared: &mut S) -> GameResult;
+
+    // Overlays are drawn on top of the scene below them
+    fn is_overlay(&self) -> bool {
+        false
+    }
+
+    // Keeps the frame rate up while nothing is being input
+    fn is_animating(&self) -> bool {
+        false
+    }
+
+    fn mouse_button_up_event(
+        &mut self,
+        _ctx: &mut Context,
+        _shared: &mut S,
+        _button: MouseButton,
+        _x: f32,
+        _y: f32,
+    ) -> Transition<S> {
+        Transition::None
+    }
+
+    fn mouse_motion_event(
+        &mut self,
+        _ctx: &mut Context,
+        _shared: &mut S,
+        _x: f32,
+        _y: f32,
+        _dx: f32,
+        _dy: f32,
+    ) {
+    }
+
+    fn mouse_wheel_event(&mut self, _ctx: &mut Context, _shared: &mut S, _x: f32, _y: f32) {}
+
+    fn key_down_event(
+        &mut self,
+        _ctx: &mut Context,
+        _shared: &mut S,
+        _keycode: KeyCode,
+    ) -> Transition<S> {
+        Transition::None
+    }
+
+    // Characters typed, after keyboard layout and modifiers are applied
+    fn text_input_event(&mut self, _ctx: &mut Context, _shared: &mut S, _character: char) {}
+
+    fn gamepad_button_down_event(
+        &mut self,
+        _ctx: &mut Context,
+        _shared: &mut S,
+        _btn: Button,
+        _id: GamepadId,
+    ) -> Transition<S> {
+        Transition::None
+    }
+
+    fn gamepad_axis_event(
+        &mut self,
+        _ctx: &mut Context,
+        _shared: &mut S,
+        _axis: Axis,
+        _value: f32,
+        _id: GamepadId,
+    ) -> Transition<S> {
+        Transition::None
+    }
+
+    fn resize_event(&mut self, _ctx: &mut Context, _shared: &mut S) {}
+}
+
+pub struct SceneStack<S> {
+    pub shared: S,
+    scenes: Vec<Box<dyn Scene<S>>>,
+    last_input: Instant,
+    last_frame: Instant,
+}
+
+impl<S> SceneStack<S> {
+    pub fn new(shared: S, root: Box<dyn Scene<S>>) -> Self {
+        Self {
+            shared,
+            scenes: vec![root],
+            last_input: Instant::now(),
+            last_frame: Instant::now(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.scenes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scenes.is_empty()
+    }
+
+    pub fn apply(&mut self, transition: Transition<S>) {
+        match transition {
+            Transition::None => return,
+            Transition::Push(scene) => self.scenes.push(scene),
+            Transition::Pop => {
+                self.scenes.pop();
+            }
+            Transition::Replace(scene) => {
+                self.scenes.pop();
+                self.scenes.push(scene);
+            }
+            Transition::Reset(scenes) => self.scenes = scenes,
+        }
+        self.last_input = Instant::now();
+    }
+
+    // Index of the lowest scene that is visible under the overlays on top of it
+    fn first_visible(&self) -> usize {
+        self.scenes
+            .iter()
+            .rposition(|s| !s.is_overlay())
+            .unwrap_or(0)
+    }
+
+    fn handle(&mut self, ctx: &mut Context, transition: Transition<S>) {
+        self.apply(transition);
+        if self.scenes.is_empty() {
+            event::quit(ctx);
+        }
+    }
+
+    fn input<F>(&mut self, ctx: &mut Context, f: F)
+    where
+        F: FnOnce(&mut dyn Scene<S>, &mut Context, &mut S) -> Transition<S>,
+    {
+        self.last_input = Instant::now();
+        let transition = match self.scenes.last_mut() {
+            Some(scene) => f(scene.as_mut(), ctx, &mut self.shared),
+            None => return,
+        };
+        self.handle(ctx, transition);
+    }
+}
+
+impl<S: SharedState> event::EventHandler<ggez::GameError> for SceneStack<S> {
+    fn update(&mut self, ctx: &mut Context) -> GameResult {
+        let transition = self.shared.update(ctx);
+        self.handle(ctx, transition);
+        let transition = match self.scenes.last_mut() {
+            Some(scene) => scene.update(ctx, &mut self.shared)?,
+            None => Transition::None,
+        };
+        self.handle(ctx, transition);
+
+        let animating = self
+            .scenes
+            .iter()
+            .skip(self.first_visible())
+            .any(|s| s.is_animating());
+        let fps = if animating || self.last_input.elapsed() < IDLE_AFTER {
+            ACTIVE_FPS
+        } else {
+            IDLE_FPS
+        };
+        let frame_time = Duration::from_secs(1) / fps;
+        let elapsed = self.last_frame.elapsed();
+        if elapsed < frame_time {
+            timer::sleep(frame_time - elapsed);
+        }
+        self.last_frame = Instant::now();
+        Ok(())
+    }
+
+    fn draw(&mut self, ctx: &mut Context) -> GameResult {
+        let first = self.first_visible();
+        for scene in self.scenes.iter_mut().skip(first) {
+            scene.draw(ctx, &mut self.shared)?;
+        }
+        graphics::present(ctx)
+    }
+
+    fn mouse_button_up_event(&mut self, ctx: &mut Context, button: MouseButton, x: f32, y: f32) {
+        self.input(ctx, |scene, ctx, shared| {
+            scene.mouse_button_up_event(ctx, shared, button, x, y)
+        });
+    }
+
+    fn mouse_motion_event(&mut self, ctx: &mut Context, x: f32, y: f32, dx: f32, dy: f32) {
+        self.input(ctx, |scene, ctx, shared| {
+            scene.mouse_motion_event(ctx, shared, x, y, dx, dy);
+            Transition::None
+        });
+    }
+
+    fn mouse_wheel_event(&mut self, ctx: &mut Context, x: f32, y: f32) {
+        self.input(ctx, |scene, ctx, shared| {
+            scene.mouse_wheel_event(ctx, shared, x, y);
+            Transition::None
+        });
+    }
+
+    fn key_down_event(
+        &mut self,
+        ctx: &mut Context,
+        keycode: KeyCode,
+        _keymods: KeyMods,
+        _repeat: bool,
+    ) {
+        self.input(ctx, |scene, ctx, shared| {
+            scene.key_down_event(ctx, shared, keycode)
+        });
+    }
+
+    fn text_input_event(&mut self, ctx: &mut Context, character: char) {
+        self.input(ctx, |scene, ctx, shared| {
+            scene.text_input_event(ctx, shared, character);
+            Transition::None
+        });
+    }
+
+    fn gamepad_button_down_event(&mut self, ctx: &mut Context, btn: Button, id: GamepadId) {
+        self.input(ctx, |scene, ctx, shared| {
+            scene.gamepad_button_down_event(ctx, shared, btn, id)
+        });
+    }
+
+    fn gamepad_axis_event(&mut self, ctx: &mut Context, axis: Axis, value: f32, id: GamepadId) {
+        self.input(ctx, |scene, ctx, shared| {
+            scene.gamepad_axis_event(ctx, shared, axis, value, id)
+        });
+    }
+
+    fn resize_event(&mut self, ctx: &mut Context, width: f32, height: f32) {
+        if graphics::set_screen_coordinates(ctx, graphics::Rect::new(0.0, 0.0, width, height))
+            .is_err()
+        {
+            return;
+        }
+        for scene in &mut self.scenes {
+            scene.resize_event(ctx, &mut self.shared);
+        }
+        self.last_input = Instant::now();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Scene;
+    use crate::SceneStack;
+    use crate::Transition;
+    use ggez::Context;
+    use ggez::GameResult;
+
+    struct Dummy(bool);
+
+    impl Scene<()> for Dummy {
+        fn draw(&mut self, _ctx: &mut Context, _shared: &mut ()) -> GameResult {
+            Ok(())
+        }
+
+        fn is_overlay(&self) -> bool {
+            self.0
+        }
+    }
+
+    fn stack() -> SceneStack<()> {
+        SceneStack::new((), Box::new(Dummy(false)))
+    }
+
+    // TEST apply
+    #[test]
+    fn apply_push_pop() {
+        let mut stack = stack();
+        stack.apply(Transition::Push(Box::new(Dummy(true))));
+        assert_eq!(stack.len(), 2);
+        stack.apply(Transition::None);
+        assert_eq!(stack.len(), 2);
+        stack.apply(Transition::Pop);
+        stack.apply(Transition::Pop);
+        assert!(stack.is_empty());
+    }
+
+    #[test]
+    fn apply_replace_reset() {
+        let mut stack = stack();
+        stack.apply(Transition::Replace(Box::new(Dummy(true))));
+        assert_eq!(stack.len(), 1);
+        assert!(stack.scenes[0].is_overlay());
+
+        stack.apply(Transition::Reset(vec![
+            Box::new(Dummy(false)),
+            Box::new(Dummy(false)),
+            Box::new(Dummy(true)),
+        ]));
+        assert_eq!(stack.len(), 3);
+    }
+
+    // TEST first_visible
+    #[test]
+    fn first_visible_overlays() {
+        let mut stack = stack();
+        assert_eq!(stack.first_visible(), 0);
+        stack.apply(Transition::Push(Box::new(Dummy(false))));
+        stack.apply(Transition::Push(Box::new(Dummy(true))));
+        stack.apply(Transition::Push(Box::new(Dummy(true))));
+        assert_eq!(stack.first_visible(), 1);
+    }
+}
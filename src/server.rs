@@ -0,0 +1,1253 @@
+use crate::lobby::Lobby;
+use crate::lobby::Seated;
+use crate::net::new_token;
+use crate::net::play_move;
+use crate::net::Connection;
+use crate::net::RESUME_TIME;
+use crate::ChatLine;
+use crate::ClientMessage;
+use crate::Game;
+use crate::GameEvent;
+use crate::GameSetup;
+use crate::Move;
+use crate::Profile;
+use crate::RateLimit;
+use crate::RoomInfo;
+use crate::Said;
+use crate::Seat;
+use crate::ServerMessage;
+use crate::Snapshot;
+use crate::Update;
+use crate::MAX_PLAYERS;
+use crate::PROTOCOL_VERSION;
+use serde::Serialize;
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::Write;
+use std::net::SocketAddr;
+use std::net::TcpListener;
+use std::net::ToSocketAddrs;
+use std::path::PathBuf;
+use std::time::Duration;
+use std::time::Instant;
+use std::time::SystemTime;
+
+#[derive(Clone, Default)]
+pub struct ServerConfig {
+    // Time a player has for each move before the turn passes on
+    pub turn_time: Option<Duration>,
+    // Every game is logged to its own file here
+    pub log_dir: Option<PathBuf>,
+    // Spectators who give this key see every mine
+    pub caster_key: Option<String>,
+}
+
+// What happened in a game, one JSON line per entry
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum LogEntry<'a> {
+    Start {
+        room: &'a str,
+        setup: &'a GameSetup,
+        order: &'a [usize],
+        seed: u64,
+    },
+    Move {
+        slot: usize,
+        message: &'a ClientMessage,
+        events: &'a [GameEvent],
+    },
+    Timeout {
+        events: &'a [GameEvent],
+    },
+    Left {
+        slot: usize,
+    },
+    Rejoined {
+        slot: usize,
+    },
+    GaveUp {
+        slot: usize,
+        events: &'a [GameEvent],
+    },
+    Chat {
+        slot: usize,
+        said: &'a Said,
+    },
+    End {
+        mines: Vec<(usize, usize)>,
+        scores: Vec<usize>,
+    },
+}
+
+struct GameLog {
+    file: File,
+}
+
+impl GameLog {
+    fn create(dir: &PathBuf, name: &str) -> io::Result<Self> {
+        fs::create_dir_all(dir)?;
+        Ok(Self {
+            file: File::create(dir.join(name))?,
+        })
+    }
+
+    fn write(&mut self, entry: &LogEntry) {
+        let mut line = serde_json::to_vec(entry).unwrap_or_default();
+        line.push(b'\n');
+        if let Err(e) = self.file.write_all(&line) {
+            eprintln!("Failed to write game log: {}", e);
+        }
+    }
+}
+
+struct Member {
+    conn: Connection,
+    seat: Seat,
+    token: String,
+    chat: RateLimit,
+    // When the connection was lost, during a game
+    left: Option<Instant>,
+}
+
+impl Seated for Member {
+    fn seat(&self) -> &Seat {
+        &self.seat
+    }
+
+    fn seat_mut(&mut self) -> &mut Seat {
+        &mut self.seat
+    }
+
+    fn away(&self) -> bool {
+        self.left.is_some()
+    }
+}
+
+// A connection that hasn't joined a room yet, with the profile to join with
+struct Waiting {
+    conn: Connection,
+    profile: Option<Profile>,
+}
+
+struct Spectator {
+    conn: Connection,
+    caster: bool,
+}
+
+// Players who joined under the same name; the first of them starts the games
+struct Room {
+    name: String,
+    lobby: Lobby<Member>,
+    spectators: Vec<Spectator>,
+    game: Option<Game>,
+    setup: Option<GameSetup>,
+    order: Vec<usize>,
+    profiles: Vec<Profile>,
+    // The move feed of the current game
+    moves: Vec<Move>,
+    mines_sent: bool,
+    log: Option<GameLog>,
+    turn_time: Option<Duration>,
+    turn_started: Instant,
+}
+
+impl Room {
+    fn new(name: String, turn_time: Option<Duration>) -> Self {
+        Self {
+            name,
+            lobby: Lobby::new(None),
+            spectators: vec![],
+            game: None,
+            setup: None,
+            order: vec![],
+            profiles: vec![],
+            moves: vec![],
+            mines_sent: false,
+            log: None,
+            turn_time,
+            turn_started: Instant::now(),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.lobby.members.is_empty() && self.spectators.is_empty()
+    }
+
+    fn is_running(&self) -> bool {
+        self.game.as_ref().is_some_and(|g| g.is_running())
+    }
+
+    fn info(&self) -> RoomInfo {
+        RoomInfo {
+            name: self.name.clone(),
+            players: self.lobby.members.len(),
+            running: self.is_running(),
+        }
+    }
+
+    fn seats(&self) -> ServerMessage {
+        ServerMessage::Lobby {
+            seats: self.lobby.seats(),
+            setup: self.lobby.settings,
+        }
+    }
+
+    fn send_lobby(&mut self) {
+        let msg = self.seats();
+        self.broadcast(&msg);
+    }
+
+    // Sends to the players and the spectators
+    fn broadcast(&mut self, msg: &ServerMessage) {
+        for member in &mut self.lobby.members {
+            member.conn.send(msg);
+        }
+        self.spectate(msg);
+    }
+
+    fn spectate(&mut self, msg: &ServerMessage) {
+        for spectator in &mut self.spectators {
+            spectator.conn.send(msg);
+        }
+    }
+
+    // Milliseconds left in the current turn
+    fn time_left(&self) -> Option<u64> {
+        let left = self.turn_time?.saturating_sub(self.turn_started.elapsed());
+        Some(left.as_millis() as u64)
+    }
+
+    fn restart_clock(&mut self) {
+        self.turn_started = Instant::now();
+        if let Some(time_left) = self.time_left().filter(|_| self.is_running()) {
+            self.broadcast(&ServerMessage::Clock { time_left });
+        }
+    }
+
+    // Casters see the mines as soon as the first reveal has placed them
+    fn send_mines(&mut self) {
+        let mines = match &self.game {
+            Some(game) if !self.mines_sent => game.grid.mines(),
+            _ => return,
+        };
+        if mines.is_empty() {
+            return;
+        }
+        self.mines_sent = true;
+        let msg = ServerMessage::Mines { mines };
+        for spectator in self.spectators.iter_mut().filter(|s| s.caster) {
+            spectator.conn.send(&msg);
+        }
+    }
+
+    // The game so far, with the mines for casters
+    fn snapshot(&self, caster: bool) -> Option<ServerMessage> {
+        let (game, setup) = (self.game.as_ref()?, self.setup?);
+        let mines = match caster {
+            true => game.grid.mines(),
+            false => vec![],
+        };
+        Some(ServerMessage::Snapshot(Box::new(Snapshot {
+            setup,
+            order: self.order.clone(),
+            profiles: self.profiles.clone(),
+            state: Update::snapshot(game),
+            moves: self.moves.clone(),
+            mines,
+            time_left: self.time_left().filter(|_| game.is_running()),
+        })))
+    }
+
+    // Spectators can come in at any time and catch up from a snapshot of the game
+    fn watch(&mut self, mut conn: Connection, caster: bool) {
+        conn.send(&self.seats());
+        if let Some(snapshot) = self.snapshot(caster) {
+            conn.send(&snapshot);
+        }
+        self.spectators.push(Spectator { conn, caster });
+    }
+
+    fn log(&mut self, entry: &LogEntry) {
+        if let Some(log) = &mut self.log {
+            log.write(entry);
+        }
+    }
+
+    fn error(&mut self, i: usize, message: &str) {
+        self.lobby.members[i].conn.send(&ServerMessage::Error {
+            message: message.to_string(),
+        });
+    }
+
+    // Returns the index of the new member
+    fn add(&mut self, mut conn: Connection) -> usize {
+        let seat = self.lobby.new_seat();
+        let token = new_token();
+        conn.send(&ServerMessage::Welcome {
+            slot: seat.slot,
+            token: token.clone(),
+        });
+        self.lobby.members.push(Member {
+            conn,
+            seat,
+            token,
+            chat: RateLimit::new(),
+            left: None,
+        });
+        self.send_lobby();
+        self.lobby.members.len() - 1
+    }
+
+    // A player who dropped out takes their place again and catches up from a snapshot
+    fn resume(&mut self, i: usize, mut conn: Connection) {
+        let member = &mut self.lobby.members[i];
+        conn.send(&ServerMessage::Welcome {
+            slot: member.seat.slot,
+            token: member.token.clone(),
+        });
+        if let Some(snapshot) = self.snapshot(false) {
+            conn.send(&snapshot);
+        }
+        let member = &mut self.lobby.members[i];
+        member.conn = conn;
+        member.left = None;
+        let slot = member.seat.slot;
+        self.log(&LogEntry::Rejoined { slot });
+        self.send_lobby();
+    }
+
+    fn start(&mut self, mut setup: GameSetup, config: &ServerConfig, id: &str) {
+        setup.players = self.lobby.members.len();
+        if let Err(e) = setup.validate() {
+            self.error(0, &e.to_string());
+            return;
+        }
+        self.lobby.renumber();
+        for member in &mut self.lobby.members {
+            member.conn.send(&ServerMessage::Welcome {
+                slot: member.seat.slot,
+                token: member.token.clone(),
+            });
+        }
+        let order: Vec<_> = (0..setup.players).collect();
+        let seed = fastrand::u64(..);
+        self.log = config.log_dir.as_ref().and_then(|dir| {
+            GameLog::create(dir, &format!("{}.jsonl", id))
+                .map_err(|e| eprintln!("Failed to create game log: {}", e))
+                .ok()
+        });
+        let name = self.name.clone();
+        self.log(&LogEntry::Start {
+            room: &name,
+            setup: &setup,
+            order: &order,
+            seed,
+        });
+        self.profiles = self.lobby.profiles();
+        self.game = Some(setup.build_with(&order, seed, &self.profiles));
+        self.setup = Some(setup);
+        self.order = order.clone();
+        self.moves.clear();
+        self.mines_sent = false;
+        self.broadcast(&ServerMessage::Start {
+            setup,
+            order,
+            profiles: self.profiles.clone(),
+        });
+        self.restart_clock();
+    }
+
+    // Sends the result of a move and closes the log once the game is over
+    fn update(&mut self, events: &[GameEvent]) {
+        let msg = match &self.game {
+            Some(game) => ServerMessage::Update(Update::new(game, events)),
+            None => return,
+        };
+        self.broadcast(&msg);
+        self.send_mines();
+        // Anyone can flag at any time, so flags don't restart the turn's time
+        if events
+            .iter()
+            .any(|e| !matches!(e, GameEvent::Flagged { .. }))
+        {
+            self.restart_clock();
+        }
+        if let Some(game) = self.game.as_ref().filter(|g| !g.is_running()) {
+            let end = LogEntry::End {
+                mines: game.grid.mines(),
+                scores: (0..game.players.len()).map(|i| game.score(i)).collect(),
+            };
+            self.log(&end);
+            self.log = None;
+        }
+    }
+
+    // A player who didn't come back in time is out of the game
+    fn give_up(&mut self, slot: usize) {
+        let events = match &mut self.game {
+            Some(game) => game.forfeit(slot),
+            None => return,
+        };
+        if !events.is_empty() {
+            self.log(&LogEntry::GaveUp {
+                slot,
+                events: &events,
+            });
+            self.update(&events);
+        }
+    }
+
+    // Chat is sent to the room and logged with the moves
+    fn chat(&mut self, i: usize, said: Said) {
+        if !self.lobby.members[i].chat.allow(Instant::now()) {
+            return self.error(i, "slow down");
+        }
+        if let Some(said) = said.cleaned() {
+            let slot = self.lobby.members[i].seat.slot;
+            self.log(&LogEntry::Chat { slot, said: &said });
+            self.broadcast(&ServerMessage::Chat(ChatLine { slot, said }));
+        }
+    }
+
+    fn handle(&mut self, i: usize, msg: ClientMessage, config: &ServerConfig, id: &str) {
+        let slot = self.lobby.members[i].seat.slot;
+        match &msg {
+            ClientMessage::Hello { .. } => return,
+            ClientMessage::Chat { said } => return self.chat(i, said.clone()),
+            ClientMessage::Join { .. }
+            | ClientMessage::Watch { .. }
+            | ClientMessage::Resume { .. } => return self.error(i, "already in a room"),
+            ClientMessage::Start { .. } if i != 0 => {
+                return self.error(i, "only the first player can start")
+            }
+            ClientMessage::Settings { .. } if i != 0 => {
+                return self.error(i, "only the first player can change the settings")
+            }
+            ClientMessage::Profile(_)
+            | ClientMessage::Ready { .. }
+            | ClientMessage::Settings { .. }
+            | ClientMessage::Start { .. }
+                if self.is_running() =>
+            {
+                return self.error(i, "the game is still running")
+            }
+            ClientMessage::Profile(profile) => {
+                if self.lobby.set_profile(slot, profile.clone()) {
+                    self.send_lobby();
+                }
+                return;
+            }
+            ClientMessage::Ready { ready } => {
+                if self.lobby.set_ready(slot, *ready) {
+                    self.send_lobby();
+                }
+                return;
+            }
+            ClientMessage::Settings { setup } => {
+                if self.lobby.set_settings(*setup) {
+                    self.send_lobby();
+                }
+                return;
+            }
+            ClientMessage::Start { .. } if !self.lobby.all_ready() => {
+                return self.error(i, "not everyone is ready")
+            }
+            ClientMessage::Start { setup } => return self.start(*setup, config, id),
+            _ => {}
+        }
+        let game = match &mut self.game {
+            Some(game) => game,
+            None => return self.error(i, "no game is running"),
+        };
+        match play_move(game, slot, &msg) {
+            Ok(events) => {
+                self.log(&LogEntry::Move {
+                    slot,
+                    message: &msg,
+                    events: &events,
+                });
+                let played = Move {
+                    slot,
+                    message: Some(msg),
+                };
+                self.spectate(&ServerMessage::Move(played.clone()));
+                self.moves.push(played);
+                self.update(&events);
+            }
+            Err(message) => self.error(i, message),
+        }
+    }
+
+    // Players who take too long lose their turn
+    fn check_timer(&mut self) {
+        if !self.is_running() || self.time_left() != Some(0) {
+            return;
+        }
+        let (slot, events) = match &mut self.game {
+            Some(game) => (game.current().map(|p| p.slot), game.timeout()),
+            None => return,
+        };
+        self.turn_started = Instant::now();
+        if let (Some(slot), false) = (slot, events.is_empty()) {
+            self.log(&LogEntry::Timeout { events: &events });
+            let played = Move {
+                slot,
+                message: None,
+            };
+            self.spectate(&ServerMessage::Move(played.clone()));
+            self.moves.push(played);
+            self.update(&events);
+        }
+    }
+}
+
+// Hosts any number of rooms; it only runs games and never plays itself
+pub struct Server {
+    listener: TcpListener,
+    config: ServerConfig,
+    // Connections that haven't joined a room yet
+    waiting: Vec<Waiting>,
+    rooms: Vec<Room>,
+    games: usize,
+    started: u64,
+}
+
+impl Server {
+    pub fn bind<A: ToSocketAddrs>(addr: A, config: ServerConfig) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        Ok(Self {
+            listener,
+            config,
+            waiting: vec![],
+            rooms: vec![],
+            games: 0,
+            started: SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    pub fn rooms(&self) -> usize {
+        self.rooms.len()
+    }
+
+    // Handles everything received since the last call without blocking
+    pub fn poll(&mut self) {
+        while let Ok((stream, _)) = self.listener.accept() {
+            match Connection::new(stream) {
+                Ok(conn) => self.waiting.push(Waiting {
+                    conn,
+                    profile: None,
+                }),
+                Err(e) => eprintln!("Failed to accept connection: {}", e),
+            }
+        }
+        self.poll_waiting();
+        for r in 0..self.rooms.len() {
+            self.poll_room(r);
+        }
+        self.rooms.retain(|r| !r.is_empty());
+    }
+
+    fn room_list(&self) -> ServerMessage {
+        ServerMessage::Rooms {
+            rooms: self.rooms.iter().map(|r| r.info()).collect(),
+        }
+    }
+
+    fn poll_waiting(&mut self) {
+        let mut i = 0;
+        while i < self.waiting.len() {
+            let mut msgs = self.waiting[i].conn.recv::<ClientMessage>().into_iter();
+            let mut entered = None;
+            for msg in msgs.by_ref() {
+                let error = match msg {
+                    Ok(
+                        ClientMessage::Hello { version } | ClientMessage::Resume { version, .. },
+                    ) if version != PROTOCOL_VERSION => {
+                        format!("protocol version {} is needed", PROTOCOL_VERSION)
+                    }
+                    // A dedicated server answers hello with its rooms
+                    Ok(ClientMessage::Hello { .. } | ClientMessage::ListRooms) => {
+                        let rooms = self.room_list();
+                        self.waiting[i].conn.send(&rooms);
+                        continue;
+                    }
+                    Ok(ClientMessage::Profile(profile)) => {
+                        self.waiting[i].profile = Some(profile);
+                        continue;
+                    }
+                    Ok(
+                        msg @ (ClientMessage::Resume { .. }
+                        | ClientMessage::Join { .. }
+                        | ClientMessage::Watch { .. }),
+                    ) => {
+                        entered = Some(msg);
+                        break;
+                    }
+                    Ok(_) => "join a room first".to_string(),
+                    Err(e) => format!("invalid message: {}", e),
+                };
+                self.waiting[i]
+                    .conn
+                    .send(&ServerMessage::Error { message: error });
+            }
+            let entered = match entered {
+                Some(msg) => msg,
+                None if self.waiting[i].conn.is_closed() => {
+                    self.waiting.swap_remove(i);
+                    continue;
+                }
+                None => {
+                    i += 1;
+                    continue;
+                }
+            };
+            let Waiting { conn, profile } = self.waiting.swap_remove(i);
+            let member = match entered {
+                ClientMessage::Resume { token, .. } => self.resume(conn, token),
+                ClientMessage::Join { room } => self.join(conn, room),
+                ClientMessage::Watch { room, key } => {
+                    self.watch(conn, room, key);
+                    None
+                }
+                _ => None,
+            };
+            // The profile and whatever was sent right after joining go to the room
+            if let Some((r, m)) = member {
+                let profile = profile.filter(|_| !self.rooms[r].is_running());
+                for msg in profile
+                    .map(|p| Ok(ClientMessage::Profile(p)))
+                    .into_iter()
+                    .chain(msgs)
+                {
+                    self.handle(r, m, msg);
+                }
+            }
+        }
+    }
+
+    fn room(&mut self, name: String) -> usize {
+        match self.rooms.iter().position(|r| r.name == name) {
+            Some(r) => r,
+            None => {
+                self.rooms.push(Room::new(name, self.config.turn_time));
+                self.rooms.len() - 1
+            }
+        }
+    }
+
+    // Returns the room and the index of the new member
+    fn join(&mut self, mut conn: Connection, name: String) -> Option<(usize, usize)> {
+        let r = self.room(name);
+        let room = &mut self.rooms[r];
+        let refusal = if room.is_running() {
+            Some("the game has already started")
+        } else if room.lobby.members.len() >= MAX_PLAYERS {
+            Some("the room is full")
+        } else {
+            None
+        };
+        match refusal {
+            Some(message) => {
+                conn.send(&ServerMessage::Error {
+                    message: message.to_string(),
+                });
+                conn.close();
+                None
+            }
+            None => Some((r, room.add(conn))),
+        }
+    }
+
+    fn resume(&mut self, mut conn: Connection, token: String) -> Option<(usize, usize)> {
+        for (r, room) in self.rooms.iter_mut().enumerate() {
+            if let Some(i) = room.lobby.members.iter().position(|m| m.token == token) {
+                room.resume(i, conn);
+                return Some((r, i));
+            }
+        }
+        conn.send(&ServerMessage::Error {
+            message: "unknown token".to_string(),
+        });
+        conn.close();
+        None
+    }
+
+    fn watch(&mut self, mut conn: Connection, name: String, key: Option<String>) {
+        let caster = key.is_some();
+        if caster && key != self.config.caster_key {
+            conn.send(&ServerMessage::Error {
+                message: "wrong caster key".to_string(),
+            });
+            conn.close();
+            return;
+        }
+        let r = self.room(name);
+        self.rooms[r].watch(conn, caster);
+    }
+
+    fn handle(&mut self, r: usize, i: usize, msg: io::Result<ClientMessage>) {
+        match msg {
+            Ok(ClientMessage::ListRooms) => {
+                let rooms = self.room_list();
+                self.rooms[r].lobby.members[i].conn.send(&rooms);
+            }
+            Ok(msg) => {
+                // Only count the game once the room has really started it
+                let running = self.rooms[r].is_running();
+                let id = format!("{}-{}", self.started, self.games + 1);
+                self.rooms[r].handle(i, msg, &self.config, &id);
+                if !running && self.rooms[r].is_running() {
+                    self.games += 1;
+                }
+            }
+            Err(e) => self.rooms[r].error(i, &format!("invalid message: {}", e)),
+        }
+    }
+
+    fn poll_room(&mut self, r: usize) {
+        for i in 0..self.rooms[r].lobby.members.len() {
+            for msg in self.rooms[r].lobby.members[i].conn.recv::<ClientMessage>() {
+                self.handle(r, i, msg);
+            }
+        }
+        let room = &mut self.rooms[r];
+        room.check_timer();
+        for spectator in &mut room.spectators {
+            for _ in spectator.conn.recv::<ClientMessage>() {
+                spectator.conn.send(&ServerMessage::Error {
+                    message: "spectators can't play".to_string(),
+                });
+            }
+        }
+        room.spectators.retain(|s| !s.conn.is_closed());
+
+        let before = room.lobby.members.len();
+        for member in room.lobby.members.iter_mut() {
+            if member.conn.is_closed() && member.left.is_none() {
+                member.left = Some(Instant::now());
+                if let Some(log) = &mut room.log {
+                    log.write(&LogEntry::Left {
+                        slot: member.seat.slot,
+                    });
+                }
+            }
+        }
+        // Players who drop out of a running game keep their place for a while
+        let running = room.is_running();
+        let now = Instant::now();
+        let gone: Vec<_> = room
+            .lobby
+            .members
+            .iter()
+            .filter(|m| m.left.is_some_and(|t| running && now - t >= RESUME_TIME))
+            .map(|m| m.seat.slot)
+            .collect();
+        room.lobby
+            .members
+            .retain(|m| m.left.is_none_or(|t| running && now - t < RESUME_TIME));
+        for slot in gone {
+            room.give_up(slot);
+        }
+        if room.lobby.members.len() != before && !room.is_running() {
+            room.send_lobby();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::net::RESUME_TIME;
+    use crate::server::Server;
+    use crate::server::ServerConfig;
+    use crate::ChatLine;
+    use crate::Client;
+    use crate::ClientMessage;
+    use crate::Emote;
+    use crate::Game;
+    use crate::GameEvent;
+    use crate::GameSetup;
+    use crate::Profile;
+    use crate::Rgba;
+    use crate::RoomInfo;
+    use crate::Said;
+    use std::fs;
+    use std::thread;
+    use std::time::Duration;
+    use std::time::Instant;
+
+    // A scripted client with its copy of the game
+    struct Script {
+        client: Client,
+        game: Option<Game>,
+        events: Vec<GameEvent>,
+        chat: Vec<ChatLine>,
+    }
+
+    fn join(server: &Server, room: &str) -> Script {
+        let addr = server.local_addr().unwrap().to_string();
+        let mut client = Client::connect(&addr).unwrap();
+        client.send(&ClientMessage::Join {
+            room: room.to_string(),
+        });
+        client.send(&ClientMessage::Ready { ready: true });
+        Script {
+            client,
+            game: None,
+            events: vec![],
+            chat: vec![],
+        }
+    }
+
+    fn watch(server: &Server, room: &str, key: Option<&str>) -> Script {
+        let addr = server.local_addr().unwrap().to_string();
+        Script {
+            client: Client::watch(&addr, room, key.map(String::from)).unwrap(),
+            game: None,
+            events: vec![],
+            chat: vec![],
+        }
+    }
+
+    fn pump_until<F: Fn(&[Script]) -> bool>(server: &mut Server, scripts: &mut [Script], done: F) {
+        let start = Instant::now();
+        while !done(scripts) {
+            assert!(start.elapsed() < Duration::from_secs(5), "timed out");
+            server.poll();
+            for script in scripts.iter_mut() {
+                if let Some((setup, order)) = script.client.poll() {
+                    script.game = Some(script.client.mirror(&setup, &order));
+                }
+                script.chat.extend(script.client.take_chat());
+                if let Some(game) = &mut script.game {
+                    for update in script.client.take_updates() {
+                        update.apply(game);
+                        script.events.extend(update.events);
+                    }
+                }
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    fn setup() -> GameSetup {
+        GameSetup::new(10, 10, 10, 2)
+    }
+
+    fn started(scripts: &[Script]) -> bool {
+        scripts.iter().all(|s| s.game.is_some())
+    }
+
+    fn current(scripts: &[Script], i: usize) -> Option<usize> {
+        scripts[i].game.as_ref()?.current().map(|p| p.slot)
+    }
+
+    // TEST poll
+    #[test]
+    fn poll_rooms() {
+        let mut server = Server::bind("127.0.0.1:0", ServerConfig::default()).unwrap();
+        let mut scripts = vec![
+            join(&server, "a"),
+            join(&server, "b"),
+            join(&server, "a"),
+            join(&server, "b"),
+        ];
+        pump_until(&mut server, &mut scripts, |s| {
+            s.iter().all(|s| s.client.players == 2)
+        });
+        assert_eq!(server.rooms(), 2);
+
+        // Only the first player in a room starts it
+        scripts[2]
+            .client
+            .send(&ClientMessage::Start { setup: setup() });
+        pump_until(&mut server, &mut scripts, |s| s[2].client.error.is_some());
+        scripts[0]
+            .client
+            .send(&ClientMessage::Start { setup: setup() });
+        pump_until(&mut server, &mut scripts, |s| {
+            s[0].game.is_some() && s[2].game.is_some()
+        });
+        assert!(scripts[1].game.is_none());
+
+        scripts[0]
+            .client
+            .send(&ClientMessage::Reveal { x: 5, y: 5 });
+        pump_until(&mut server, &mut scripts, |s| {
+            s[2].game.as_ref().unwrap().cnt_revealed() > 0
+        });
+        assert!(scripts[1].game.is_none() && scripts[3].game.is_none());
+    }
+
+    #[test]
+    fn poll_lobby() {
+        let mut server = Server::bind("127.0.0.1:0", ServerConfig::default()).unwrap();
+        let addr = server.local_addr().unwrap().to_string();
+        let mut scripts = vec![join(&server, "a")];
+        pump_until(&mut server, &mut scripts, |s| s[0].client.players == 1);
+
+        // A new connection gets the rooms, and the profile it sends is kept for its room
+        scripts.push(Script {
+            client: Client::connect(&addr).unwrap(),
+            game: None,
+            events: vec![],
+            chat: vec![],
+        });
+        pump_until(&mut server, &mut scripts, |s| s[1].client.rooms.is_some());
+        let room = RoomInfo {
+            name: "a".to_string(),
+            players: 1,
+            running: false,
+        };
+        assert_eq!(scripts[1].client.rooms, Some(vec![room]));
+        scripts[1].client.send(&ClientMessage::Profile(Profile {
+            name: "Bo".to_string(),
+            color: Rgba::GREEN,
+        }));
+        scripts[1].client.send(&ClientMessage::Join {
+            room: "a".to_string(),
+        });
+        pump_until(&mut server, &mut scripts, |s| {
+            s[0].client
+                .seats
+                .get(1)
+                .is_some_and(|s| s.profile.name == "Bo")
+        });
+        let seat = &scripts[0].client.seats[1];
+        assert_eq!((seat.profile.color, seat.ready), (Rgba::BLUE, false));
+
+        // Only the owner picks the board, and only once everyone is ready can it start
+        scripts[1]
+            .client
+            .send(&ClientMessage::Settings { setup: setup() });
+        pump_until(&mut server, &mut scripts, |s| s[1].client.error.is_some());
+        assert_eq!(
+            scripts[1].client.error.as_deref(),
+            Some("only the first player can change the settings")
+        );
+        scripts[0]
+            .client
+            .send(&ClientMessage::Start { setup: setup() });
+        pump_until(&mut server, &mut scripts, |s| s[0].client.error.is_some());
+        assert_eq!(
+            scripts[0].client.error.as_deref(),
+            Some("not everyone is ready")
+        );
+
+        // A different board needs everyone to get ready again
+        scripts[1]
+            .client
+            .send(&ClientMessage::Ready { ready: true });
+        pump_until(&mut server, &mut scripts, |s| s[0].client.seats[1].ready);
+        scripts[0]
+            .client
+            .send(&ClientMessage::Settings { setup: setup() });
+        pump_until(&mut server, &mut scripts, |s| {
+            s[1].client.settings == Some(setup())
+        });
+        assert!(!scripts[1].client.seats[1].ready);
+        scripts[1]
+            .client
+            .send(&ClientMessage::Ready { ready: true });
+        pump_until(&mut server, &mut scripts, |s| s[0].client.seats[1].ready);
+        scripts[0]
+            .client
+            .send(&ClientMessage::Start { setup: setup() });
+        pump_until(&mut server, &mut scripts, started);
+        let player = &scripts[0].game.as_ref().unwrap().players[1];
+        assert_eq!((player.name.as_str(), player.color), ("Bo", Rgba::BLUE));
+
+        scripts[1].client.rooms = None;
+        scripts[1].client.send(&ClientMessage::ListRooms);
+        pump_until(&mut server, &mut scripts, |s| s[1].client.rooms.is_some());
+        assert!(scripts[1].client.rooms.as_ref().unwrap()[0].running);
+    }
+
+    #[test]
+    fn poll_turns() {
+        let mut server = Server::bind("127.0.0.1:0", ServerConfig::default()).unwrap();
+        let mut scripts = vec![join(&server, "a"), join(&server, "a")];
+        pump_until(&mut server, &mut scripts, |s| s[1].client.players == 2);
+        scripts[0]
+            .client
+            .send(&ClientMessage::Start { setup: setup() });
+        pump_until(&mut server, &mut scripts, started);
+
+        scripts[1]
+            .client
+            .send(&ClientMessage::Reveal { x: 5, y: 5 });
+        pump_until(&mut server, &mut scripts, |s| s[1].client.error.is_some());
+        assert_eq!(scripts[1].client.error.as_deref(), Some("move rejected"));
+        assert_eq!(current(&scripts, 1), Some(0));
+
+        scripts[0]
+            .client
+            .send(&ClientMessage::Reveal { x: 5, y: 5 });
+        pump_until(&mut server, &mut scripts, |s| {
+            s.iter()
+                .all(|s| s.game.as_ref().unwrap().cnt_revealed() > 0)
+        });
+    }
+
+    #[test]
+    fn poll_hides_mines() {
+        let mut server = Server::bind("127.0.0.1:0", ServerConfig::default()).unwrap();
+        let mut scripts = vec![join(&server, "a"), join(&server, "a")];
+        pump_until(&mut server, &mut scripts, |s| s[1].client.players == 2);
+        scripts[0]
+            .client
+            .send(&ClientMessage::Start { setup: setup() });
+        pump_until(&mut server, &mut scripts, started);
+        scripts[0]
+            .client
+            .send(&ClientMessage::Reveal { x: 5, y: 5 });
+        pump_until(&mut server, &mut scripts, |s| {
+            s[1].game.as_ref().unwrap().cnt_revealed() > 0
+        });
+        let game = scripts[1].game.as_ref().unwrap();
+        assert!(game.grid.mines().is_empty());
+    }
+
+    #[test]
+    fn poll_timer() {
+        let config = ServerConfig {
+            turn_time: Some(Duration::from_millis(50)),
+            ..ServerConfig::default()
+        };
+        let mut server = Server::bind("127.0.0.1:0", config).unwrap();
+        let mut scripts = vec![join(&server, "a"), join(&server, "a")];
+        pump_until(&mut server, &mut scripts, |s| s[1].client.players == 2);
+        scripts[0]
+            .client
+            .send(&ClientMessage::Start { setup: setup() });
+        pump_until(&mut server, &mut scripts, started);
+        pump_until(&mut server, &mut scripts, |s| current(s, 1) == Some(1));
+        assert!(scripts[1]
+            .events
+            .contains(&GameEvent::Timeout { player: 0 }));
+    }
+
+    #[test]
+    fn poll_log() {
+        let dir = std::env::temp_dir().join(format!("hexsweeper-log-{}", fastrand::u64(..)));
+        let config = ServerConfig {
+            log_dir: Some(dir.clone()),
+            ..ServerConfig::default()
+        };
+        let mut server = Server::bind("127.0.0.1:0", config).unwrap();
+        let mut scripts = vec![join(&server, "a")];
+        pump_until(&mut server, &mut scripts, |s| s[0].client.players == 1);
+        let setup = GameSetup::new(10, 10, 10, 1);
+        scripts[0].client.send(&ClientMessage::Start { setup });
+        pump_until(&mut server, &mut scripts, started);
+        scripts[0].client.send(&ClientMessage::Flag { x: 1, y: 1 });
+        pump_until(&mut server, &mut scripts, |s| !s[0].events.is_empty());
+        scripts[0].client.send(&ClientMessage::Chat {
+            said: Said::Text("gl".to_string()),
+        });
+        pump_until(&mut server, &mut scripts, |s| !s[0].chat.is_empty());
+
+        let files: Vec<_> = fs::read_dir(&dir).unwrap().collect();
+        assert_eq!(files.len(), 1);
+        let log = fs::read_to_string(files[0].as_ref().unwrap().path()).unwrap();
+        let lines: Vec<_> = log.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("{\"type\":\"start\",\"room\":\"a\""));
+        assert!(lines[1].starts_with("{\"type\":\"move\",\"slot\":0"));
+        assert_eq!(
+            lines[2],
+            "{\"type\":\"chat\",\"slot\":0,\"said\":{\"text\":\"gl\"}}"
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn poll_chat() {
+        let mut server = Server::bind("127.0.0.1:0", ServerConfig::default()).unwrap();
+        let mut scripts = vec![
+            join(&server, "a"),
+            join(&server, "a"),
+            watch(&server, "a", None),
+        ];
+        pump_until(&mut server, &mut scripts, |s| s[2].client.players == 2);
+
+        // Everyone in the room hears every line, up to the rate limit
+        for _ in 0..6 {
+            scripts[1].client.send(&ClientMessage::Chat {
+                said: Said::Emote(Emote::Hello),
+            });
+        }
+        pump_until(&mut server, &mut scripts, |s| s[1].client.error.is_some());
+        pump_until(&mut server, &mut scripts, |s| s[2].chat.len() == 5);
+        assert_eq!(scripts[1].client.error.as_deref(), Some("slow down"));
+        assert_eq!(scripts[0].chat.len(), 5);
+        assert_eq!(scripts[0].chat[0].slot, 1);
+
+        // Blank lines are dropped and spectators can't chat
+        for text in ["   ", " hi "] {
+            scripts[0].client.send(&ClientMessage::Chat {
+                said: Said::Text(text.to_string()),
+            });
+        }
+        scripts[2].client.send(&ClientMessage::Chat {
+            said: Said::Text("hi".to_string()),
+        });
+        pump_until(&mut server, &mut scripts, |s| {
+            s[1].chat.len() == 6 && s[2].client.error.is_some()
+        });
+        let line = &scripts[1].chat[5];
+        assert_eq!((line.slot, line.said.text()), (0, "hi"));
+    }
+
+    #[test]
+    fn poll_spectators() {
+        let config = ServerConfig {
+            turn_time: Some(Duration::from_secs(60)),
+            caster_key: Some("secret".to_string()),
+            ..ServerConfig::default()
+        };
+        let mut server = Server::bind("127.0.0.1:0", config).unwrap();
+        let mut scripts = vec![join(&server, "a"), join(&server, "a")];
+        pump_until(&mut server, &mut scripts, |s| s[1].client.players == 2);
+        scripts[0].client.send(&ClientMessage::Start {
+            setup: GameSetup::new(10, 10, 30, 2),
+        });
+        pump_until(&mut server, &mut scripts, started);
+        scripts[0]
+            .client
+            .send(&ClientMessage::Reveal { x: 5, y: 5 });
+        pump_until(&mut server, &mut scripts, |s| {
+            s[1].game.as_ref().unwrap().cnt_revealed() > 0
+        });
+
+        // Spectators coming in mid-game catch up from the snapshot
+        scripts.push(watch(&server, "a", None));
+        scripts.push(watch(&server, "a", Some("secret")));
+        scripts.push(watch(&server, "a", Some("guess")));
+        pump_until(&mut server, &mut scripts, |s| {
+            s[2].game.is_some() && s[3].game.is_some() && s[4].client.is_closed()
+        });
+        assert_eq!(scripts[4].client.error.as_deref(), Some("wrong caster key"));
+        for i in [2, 3] {
+            let game = scripts[i].game.as_ref().unwrap();
+            assert_eq!(
+                game.grid.view(),
+                scripts[1].game.as_ref().unwrap().grid.view()
+            );
+            assert_eq!(scripts[i].client.feed.len(), 1);
+            assert!(scripts[i].client.time_left().is_some());
+            assert_eq!(scripts[i].client.slot, None);
+        }
+        assert!(scripts[2].client.mines.is_empty());
+        assert_eq!(scripts[3].client.mines.len(), 30);
+
+        // The feed goes on with every move, and spectators can't play
+        let (x, y) = scripts[1].game.as_ref().unwrap().grid.view().covered()[0];
+        scripts[2].client.send(&ClientMessage::Flag { x, y });
+        scripts[1].client.send(&ClientMessage::Flag { x, y });
+        pump_until(&mut server, &mut scripts, |s| {
+            s[2].client.error.is_some() && s[3].client.feed.len() == 2
+        });
+        assert_eq!(
+            scripts[2].client.error.as_deref(),
+            Some("spectators can't play")
+        );
+        assert_eq!(
+            scripts[3].client.feed[1].message,
+            Some(ClientMessage::Flag { x, y })
+        );
+        pump_until(&mut server, &mut scripts, |s| {
+            s[2].game.as_ref().unwrap().grid.is_flagged(x, y)
+        });
+    }
+
+    #[test]
+    fn poll_resume() {
+        let mut server = Server::bind("127.0.0.1:0", ServerConfig::default()).unwrap();
+        let mut scripts = vec![join(&server, "a"), join(&server, "a")];
+        pump_until(&mut server, &mut scripts, |s| s[1].client.players == 2);
+        scripts[0]
+            .client
+            .send(&ClientMessage::Start { setup: setup() });
+        pump_until(&mut server, &mut scripts, started);
+        scripts[0]
+            .client
+            .send(&ClientMessage::Reveal { x: 5, y: 5 });
+        pump_until(&mut server, &mut scripts, |s| current(s, 1) == Some(1));
+
+        // Player 2 loses the connection on their turn and comes back with the token
+        let token = scripts[1].client.token.clone().unwrap();
+        let addr = server.local_addr().unwrap().to_string();
+        scripts.pop();
+        for _ in 0..10 {
+            server.poll();
+            thread::sleep(Duration::from_millis(1));
+        }
+        scripts.push(Script {
+            client: Client::resume(&addr, &token).unwrap(),
+            game: None,
+            events: vec![],
+            chat: vec![],
+        });
+        pump_until(&mut server, &mut scripts, |s| s[1].game.is_some());
+        let game = scripts[1].game.as_ref().unwrap();
+        let host = scripts[0].game.as_ref().unwrap();
+        assert_eq!(scripts[1].client.slot, Some(1));
+        assert_eq!(game.grid.view(), host.grid.view());
+        assert_eq!(game.score(0), host.score(0));
+        assert_eq!(current(&scripts, 1), Some(1));
+        assert_eq!(scripts[1].client.feed.len(), 1);
+
+        let (x, y) = game.grid.view().covered()[0];
+        scripts[1].client.send(&ClientMessage::Flag { x, y });
+        pump_until(&mut server, &mut scripts, |s| {
+            s[0].game.as_ref().unwrap().grid.is_flagged(x, y)
+        });
+    }
+
+    #[test]
+    fn poll_give_up() {
+        let mut server = Server::bind("127.0.0.1:0", ServerConfig::default()).unwrap();
+        let mut scripts = vec![join(&server, "a"), join(&server, "a")];
+        pump_until(&mut server, &mut scripts, |s| s[1].client.players == 2);
+        scripts[0]
+            .client
+            .send(&ClientMessage::Start { setup: setup() });
+        pump_until(&mut server, &mut scripts, started);
+        scripts[0]
+            .client
+            .send(&ClientMessage::Reveal { x: 5, y: 5 });
+        pump_until(&mut server, &mut scripts, |s| current(s, 0) == Some(1));
+
+        // Player 2 doesn't come back in time, is out of the game and the turn passes on
+        scripts.pop();
+        while server.rooms[0]
+            .lobby
+            .members
+            .iter()
+            .all(|m| m.left.is_none())
+        {
+            server.poll();
+            thread::sleep(Duration::from_millis(1));
+        }
+        for member in &mut server.rooms[0].lobby.members {
+            member.left = member.left.map(|_| Instant::now() - RESUME_TIME);
+        }
+        pump_until(&mut server, &mut scripts, |s| {
+            s[0].events.contains(&GameEvent::Eliminated { player: 1 })
+        });
+        assert_eq!(current(&scripts, 0), Some(0));
+        assert_eq!(server.rooms[0].lobby.members.len(), 1);
+        let game = scripts[0].game.as_ref().unwrap();
+        assert_eq!(game.death(1).map(|d| d.tile), Some(None));
+    }
+}
@@ -0,0 +1,218 @@
+use crate::Game;
+use crate::GameSetup;
+use crate::Profile;
+
+// A best-of-N match: the setup, turn order and seed of the current round and the
+// standings so far, indexed by player slot
+pub struct Session {
+    pub setup: GameSetup,
+    pub order: Vec<usize>,
+    pub seed: u64,
+    pub rounds: usize,
+    pub wins: Vec<usize>,
+    pub totals: Vec<usize>,
+    // Names and colours picked in a network lobby, indexed by slot
+    pub profiles: Vec<Profile>,
+}
+
+impl Session {
+    pub fn new(setup: GameSetup) -> Self {
+        Self {
+            setup,
+            order: (0..setup.players).collect(),
+            seed: fastrand::u64(..),
+            rounds: 0,
+            wins: vec![0; setup.players],
+            totals: vec![0; setup.players],
+            profiles: vec![],
+        }
+    }
+
+    pub fn new_game(&self) -> Game {
+        self.setup
+            .build_with(&self.order, self.seed, &self.profiles)
+    }
+
+    // Same settings and turn order, new mines
+    pub fn restart(&mut self) {
+        self.next_match();
+        self.seed = fastrand::u64(..);
+    }
+
+    // Shuffled turn order, new mines
+    pub fn rematch(&mut self) {
+        self.next_match();
+        fastrand::shuffle(&mut self.order);
+        self.seed = fastrand::u64(..);
+    }
+
+    // Same mines and turn order
+    pub fn replay(&mut self) {
+        self.next_match();
+    }
+
+    // Once the match is decided the next round starts a new one
+    fn next_match(&mut self) {
+        if self.winner().is_some() {
+            self.rounds = 0;
+            self.wins.iter_mut().for_each(|w| *w = 0);
+            self.totals.iter_mut().for_each(|t| *t = 0);
+        }
+    }
+
+    pub fn record(&mut self, game: &Game) {
+        self.rounds += 1;
+        for (i, player) in game.players.iter().enumerate() {
+            self.totals[player.slot] += game.score(i);
+        }
+        // Every member of the winning team is credited with the win
+        if game.is_team_game() {
+            if let Some(team) = game.winning_team() {
+                for player in game.players.iter().filter(|p| p.team == team) {
+                    self.wins[player.slot] += 1;
+                }
+            }
+        } else if let Some(slot) = game.winner() {
+            self.wins[slot] += 1;
+        }
+    }
+
+    // Slot of the player who has won the majority of the rounds
+    pub fn winner(&self) -> Option<usize> {
+        self.wins.iter().position(|w| *w > self.setup.best_of / 2)
+    }
+
+    // Name of the player or team who won the match
+    pub fn winner_name(&self) -> Option<String> {
+        let slot = self.winner()?;
+        Some(match self.setup.team_of(slot) {
+            Some(team) => format!("Team {}", team + 1),
+            None => self.name(slot),
+        })
+    }
+
+    fn name(&self, slot: usize) -> String {
+        match self.profiles.get(slot) {
+            Some(profile) => profile.name.clone(),
+            None => Profile::new(slot).name,
+        }
+    }
+
+    pub fn standings(&self) -> Vec<String> {
+        if self.setup.teams > 0 {
+            return self.team_standings();
+        }
+        let mut order: Vec<_> = (0..self.wins.len()).collect();
+        order.sort_by_key(|i| std::cmp::Reverse((self.wins[*i], self.totals[*i])));
+        order
+            .into_iter()
+            .map(|i| {
+                format!(
+                    "{}: {} won, {} total",
+                    self.name(i),
+                    self.wins[i],
+                    self.totals[i]
+                )
+            })
+            .collect()
+    }
+
+    fn team_standings(&self) -> Vec<String> {
+        let teams = self.setup.teams;
+        // Members share their wins, so any member's count is the team's
+        let wins = |team: usize| self.wins[team];
+        let total = |team: usize| -> usize {
+            (team..self.totals.len())
+                .step_by(teams)
+                .map(|slot| self.totals[slot])
+                .sum()
+        };
+        let mut order: Vec<_> = (0..teams).collect();
+        order.sort_by_key(|t| std::cmp::Reverse((wins(*t), total(*t))));
+        order
+            .into_iter()
+            .map(|t| format!("Team {}: {} won, {} total", t + 1, wins(t), total(t)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::GameSetup;
+    use crate::Session;
+
+    fn session() -> Session {
+        let mut setup = GameSetup::new(10, 10, 1, 2);
+        setup.best_of = 3;
+        Session::new(setup)
+    }
+
+    fn play(session: &mut Session, slot: usize) {
+        let mut game = session.new_game();
+        game.grid.place_mines(&[(0, 0)]);
+        while game.players[game.curr_player].slot != slot {
+            game.curr_player += 1;
+        }
+        game.reveal(0, 1);
+        session.record(&game);
+    }
+
+    // TEST record
+    #[test]
+    fn record_standings() {
+        let mut session = session();
+        play(&mut session, 1);
+        assert_eq!(session.rounds, 1);
+        assert_eq!(session.wins, vec![0, 1]);
+        assert_eq!(session.totals, vec![0, 1]);
+        assert_eq!(session.winner(), None);
+        assert_eq!(session.standings()[0], "Player 2: 1 won, 1 total");
+
+        play(&mut session, 1);
+        assert_eq!(session.winner(), Some(1));
+    }
+
+    #[test]
+    fn record_teams() {
+        let mut setup = GameSetup::new(10, 10, 1, 4);
+        setup.teams = 2;
+        let mut session = Session::new(setup);
+        play(&mut session, 1);
+        assert_eq!(session.wins, vec![0, 1, 0, 1]);
+        assert_eq!(session.winner_name(), Some("Team 2".to_string()));
+        assert_eq!(session.standings()[0], "Team 2: 1 won, 1 total");
+    }
+
+    // TEST restart
+    #[test]
+    fn restart_new_match() {
+        let mut session = session();
+        play(&mut session, 0);
+        session.restart();
+        assert_eq!(session.rounds, 1);
+        play(&mut session, 0);
+        session.restart();
+        assert_eq!(session.rounds, 0);
+        assert_eq!(session.wins, vec![0, 0]);
+    }
+
+    // TEST rematch
+    #[test]
+    fn rematch_keeps_players() {
+        let mut session = session();
+        session.rematch();
+        let mut order = session.order.clone();
+        order.sort();
+        assert_eq!(order, vec![0, 1]);
+    }
+
+    // TEST replay
+    #[test]
+    fn replay_same_seed() {
+        let mut session = session();
+        let seed = session.seed;
+        session.replay();
+        assert_eq!(session.seed, seed);
+        assert_eq!(session.new_game().grid.seed(), seed);
+    }
+}
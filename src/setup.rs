@@ -0,0 +1,610 @@
+use crate::Game;
+use crate::HexGrid;
+use crate::LifeRule;
+use crate::Player;
+use crate::Profile;
+use crate::TurnPolicy;
+use serde::Deserialize;
+use serde::Serialize;
+use std::error::Error;
+use std::fmt;
+use std::rc::Rc;
+
+pub const MIN_SIZE: usize = 5;
+pub const MAX_SIZE: usize = 30;
+// The player panel has room for this many players next to the board
+pub const MAX_PLAYERS: usize = 4;
+pub const MAX_BEST_OF: usize = 9;
+
+// An invalid setup or command line
+#[derive(Clone, PartialEq, Debug)]
+pub struct SetupError(pub String);
+
+impl fmt::Display for SetupError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Invalid setup: {}", self.0)
+    }
+}
+
+impl Error for SetupError {}
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum Difficulty {
+    Beginner,
+    Intermediate,
+    Expert,
+    Custom,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 4] = [
+        Difficulty::Beginner,
+        Difficulty::Intermediate,
+        Difficulty::Expert,
+        Difficulty::Custom,
+    ];
+
+    // Classic board sizes; hex cells have six neighbours instead of eight, so numbers
+    // carry less information and the densities are a little lower than the classic ones
+    pub fn preset(self) -> Option<(usize, usize, f32)> {
+        match self {
+            Difficulty::Beginner => Some((9, 9, 0.10)),
+            Difficulty::Intermediate => Some((16, 16, 0.14)),
+            Difficulty::Expert => Some((30, 16, 0.18)),
+            Difficulty::Custom => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Difficulty::Beginner => "beginner",
+            Difficulty::Intermediate => "intermediate",
+            Difficulty::Expert => "expert",
+            Difficulty::Custom => "custom",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|d| d.name() == name.to_lowercase())
+    }
+
+    fn index(self) -> usize {
+        Self::ALL.iter().position(|d| *d == self).unwrap_or(0)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct GameSetup {
+    pub difficulty: Difficulty,
+    pub width: usize,
+    pub height: usize,
+    pub mines: usize,
+    pub players: usize,
+    // Rounds in a match, always odd
+    pub best_of: usize,
+    pub life_rule: LifeRule,
+    pub turn_policy: TurnPolicy,
+    // 0 plays without teams, otherwise players are dealt round the teams by slot
+    pub teams: usize,
+}
+
+impl GameSetup {
+    pub fn new(width: usize, height: usize, mines: usize, players: usize) -> Self {
+        let mut setup = Self {
+            difficulty: Difficulty::Custom,
+            width,
+            height,
+            mines,
+            players,
+            best_of: 1,
+            life_rule: LifeRule::default(),
+            turn_policy: TurnPolicy::default(),
+            teams: 0,
+        };
+        setup.clamp();
+        setup
+    }
+
+    pub fn with_difficulty(difficulty: Difficulty, players: usize) -> Self {
+        let mut setup = Self::new(MIN_SIZE, MIN_SIZE, 1, players);
+        setup.set_difficulty(difficulty);
+        setup
+    }
+
+    pub fn set_difficulty(&mut self, difficulty: Difficulty) {
+        self.difficulty = difficulty;
+        if let Some((width, height, density)) = difficulty.preset() {
+            self.width = width;
+            self.height = height;
+            self.mines = ((width * height) as f32 * density).round() as usize;
+            self.clamp();
+        }
+    }
+
+    pub fn change_difficulty(&mut self, delta: isize) {
+        let len = Difficulty::ALL.len() as isize;
+        let i = (self.difficulty.index() as isize + delta).rem_euclid(len);
+        self.set_difficulty(Difficulty::ALL[i as usize]);
+    }
+
+    pub fn max_mines(&self) -> usize {
+        HexGrid::max_mines(self.width, self.height)
+    }
+
+    // Unlike the menu, which clamps values, setups from the command line are rejected
+    pub fn validate(&self) -> Result<(), SetupError> {
+        let error = |msg: String| Err(SetupError(msg));
+        if !(MIN_SIZE..=MAX_SIZE).contains(&self.width)
+            || !(MIN_SIZE..=MAX_SIZE).contains(&self.height)
+        {
+            return error(format!(
+                "board size {}x{} is outside {}..={}",
+                self.width, self.height, MIN_SIZE, MAX_SIZE
+            ));
+        }
+        if self.mines == 0 || self.mines > self.max_mines() {
+            return error(format!(
+                "{} mines can't be placed on a {}x{} board, use 1..={}",
+                self.mines,
+                self.width,
+                self.height,
+                self.max_mines()
+            ));
+        }
+        if !(1..=MAX_PLAYERS).contains(&self.players) {
+            return error(format!("{} players, use 1..={}", self.players, MAX_PLAYERS));
+        }
+        let (min, max) = self.life_rule.amount_range();
+        if !(min..=max).contains(&self.life_rule.amount()) {
+            return error(format!(
+                "{:?} is out of range, use {}..={}",
+                self.life_rule, min, max
+            ));
+        }
+        if self.teams != 0 && !(2..self.players).contains(&self.teams) {
+            return error(format!(
+                "{} teams need at least {} players",
+                self.teams,
+                self.teams + 1
+            ));
+        }
+        if self.best_of.is_multiple_of(2) || self.best_of > MAX_BEST_OF {
+            return error(format!(
+                "best of {}, use an odd number up to {}",
+                self.best_of, MAX_BEST_OF
+            ));
+        }
+        Ok(())
+    }
+
+    // Returns None when no setup options were given
+    pub fn from_args(args: &[String]) -> Result<Option<Self>, SetupError> {
+        if args.is_empty() {
+            return Ok(None);
+        }
+        let mut setup = Self::default();
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            let mut value = || {
+                iter.next()
+                    .ok_or_else(|| SetupError(format!("{} needs a value", arg)))
+            };
+            let mut number = || -> Result<usize, SetupError> {
+                let value = value()?;
+                value
+                    .parse()
+                    .map_err(|_| SetupError(format!("{} is not a number: {}", arg, value)))
+            };
+            match arg.as_str() {
+                "--difficulty" | "-d" => {
+                    let name = value()?;
+                    let difficulty = Difficulty::from_name(name)
+                        .ok_or_else(|| SetupError(format!("unknown difficulty: {}", name)))?;
+                    setup.set_difficulty(difficulty);
+                }
+                "--width" | "-w" => {
+                    setup.width = number()?;
+                    setup.difficulty = Difficulty::Custom;
+                }
+                "--height" | "-H" => {
+                    setup.height = number()?;
+                    setup.difficulty = Difficulty::Custom;
+                }
+                "--mines" | "-m" => {
+                    setup.mines = number()?;
+                    setup.difficulty = Difficulty::Custom;
+                }
+                "--players" | "-p" => setup.players = number()?,
+                "--best-of" | "-b" => setup.best_of = number()?,
+                "--lives" => setup.life_rule = LifeRule::Lives(number()?),
+                "--penalty" => setup.life_rule = LifeRule::Penalty(number()?),
+                "--shield" => setup.life_rule = LifeRule::Shield(number()?),
+                "--teams" => setup.teams = number()?,
+                "--turns" | "-t" => {
+                    let name = value()?;
+                    setup.turn_policy = TurnPolicy::from_name(name)
+                        .ok_or_else(|| SetupError(format!("unknown turn policy: {}", name)))?;
+                }
+                _ => return Err(SetupError(format!("unknown option: {}", arg))),
+            }
+        }
+        setup.validate()?;
+        Ok(Some(setup))
+    }
+
+    fn clamp(&mut self) {
+        self.width = self.width.clamp(MIN_SIZE, MAX_SIZE);
+        self.height = self.height.clamp(MIN_SIZE, MAX_SIZE);
+        self.mines = self.mines.clamp(1, self.max_mines());
+        self.players = self.players.clamp(1, MAX_PLAYERS);
+        self.best_of = (self.best_of.clamp(1, MAX_BEST_OF) - 1) / 2 * 2 + 1;
+        // Teams only make sense when at least one of them has two members
+        if self.players < 3 {
+            self.teams = 0;
+        } else if self.teams != 0 {
+            self.teams = self.teams.clamp(2, self.players - 1);
+        }
+    }
+
+    pub fn team_of(&self, slot: usize) -> Option<usize> {
+        if self.teams == 0 {
+            None
+        } else {
+            Some(slot % self.teams)
+        }
+    }
+
+    pub fn change_width(&mut self, delta: isize) {
+        self.difficulty = Difficulty::Custom;
+        self.width = self.width.saturating_add_signed(delta);
+        self.clamp();
+    }
+
+    pub fn change_height(&mut self, delta: isize) {
+        self.difficulty = Difficulty::Custom;
+        self.height = self.height.saturating_add_signed(delta);
+        self.clamp();
+    }
+
+    pub fn change_mines(&mut self, delta: isize) {
+        self.difficulty = Difficulty::Custom;
+        self.mines = self.mines.saturating_add_signed(delta);
+        self.clamp();
+    }
+
+    pub fn set_players(&mut self, players: usize) {
+        self.players = players;
+        self.clamp();
+    }
+
+    pub fn change_players(&mut self, delta: isize) {
+        self.players = self.players.saturating_add_signed(delta);
+        self.clamp();
+    }
+
+    pub fn change_best_of(&mut self, delta: isize) {
+        self.best_of = self.best_of.saturating_add_signed(2 * delta);
+        self.clamp();
+    }
+
+    // Cycles between lives, penalty and shield, each starting at its default amount
+    pub fn change_life_rule(&mut self, delta: isize) {
+        let len = LifeRule::KINDS.len() as isize;
+        let i = (self.life_rule.kind_index() as isize + delta).rem_euclid(len);
+        self.life_rule = LifeRule::KINDS[i as usize];
+    }
+
+    pub fn change_life_amount(&mut self, delta: isize) {
+        let amount = self.life_rule.amount().saturating_add_signed(delta);
+        self.life_rule = self.life_rule.with_amount(amount);
+    }
+
+    // Cycles between no teams and every possible number of teams
+    pub fn change_teams(&mut self, delta: isize) {
+        let options: Vec<_> = std::iter::once(0).chain(2..self.players).collect();
+        let len = options.len() as isize;
+        let i = options.iter().position(|t| *t == self.teams).unwrap_or(0) as isize;
+        self.teams = options[(i + delta).rem_euclid(len) as usize];
+    }
+
+    pub fn change_turn_policy(&mut self, delta: isize) {
+        let len = TurnPolicy::ALL.len() as isize;
+        let i = (self.turn_policy.index() as isize + delta).rem_euclid(len);
+        self.turn_policy = TurnPolicy::ALL[i as usize];
+    }
+
+    pub fn new_game(&self) -> Game {
+        let order: Vec<_> = (0..self.players).collect();
+        self.build(&order, fastrand::u64(..))
+    }
+
+    // order lists player slots in turn order
+    pub fn build(&self, order: &[usize], seed: u64) -> Game {
+        self.build_with(order, seed, &[])
+    }
+
+    // Players take the names and colours picked in the lobby, indexed by slot.
+    // A team takes the colour of its first member, slot by slot.
+    pub fn build_with(&self, order: &[usize], seed: u64, profiles: &[Profile]) -> Game {
+        let profile = |slot: usize| profiles.get(slot).cloned().unwrap_or(Profile::new(slot));
+        let players = order
+            .iter()
+            .map(|i| {
+                let (team, member) = match self.team_of(*i) {
+                    Some(team) => (team, *i / self.teams),
+                    None => (*i, 0),
+                };
+                let color = profile(team).color.shade(member);
+                Rc::new(Player::new(*i, color, profile(*i).name).with_team(team, member))
+            })
+            .collect();
+        let mut game = Game::new(
+            HexGrid::with_seed(self.width, self.height, self.mines, seed),
+            players,
+        );
+        game.rule = self.life_rule;
+        game.policy = self.turn_policy;
+        game
+    }
+}
+
+impl Default for GameSetup {
+    fn default() -> Self {
+        Self::new(10, 10, 16, 3)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Difficulty;
+    use crate::GameSetup;
+    use crate::LifeRule;
+    use crate::TurnPolicy;
+
+    fn args(s: &str) -> Vec<String> {
+        s.split_whitespace().map(|a| a.to_string()).collect()
+    }
+
+    // TEST new
+    #[test]
+    fn new_clamped() {
+        let setup = GameSetup::new(1, 100, 10000, 0);
+        assert_eq!(setup, GameSetup::new(5, 30, 149, 1));
+    }
+
+    // TEST change_width
+    #[test]
+    fn change_width_clamps_mines() {
+        let mut setup = GameSetup::new(10, 5, 49, 2);
+        setup.change_width(-5);
+        assert_eq!(setup.width, 5);
+        assert_eq!(setup.mines, 24);
+        setup.change_width(-5);
+        assert_eq!(setup.width, 5);
+    }
+
+    // TEST set_difficulty
+    #[test]
+    fn set_difficulty_presets() {
+        let setup = GameSetup::with_difficulty(Difficulty::Beginner, 2);
+        assert_eq!((setup.width, setup.height, setup.mines), (9, 9, 8));
+        let setup = GameSetup::with_difficulty(Difficulty::Expert, 2);
+        assert_eq!((setup.width, setup.height, setup.mines), (30, 16, 86));
+        for difficulty in Difficulty::ALL {
+            assert!(GameSetup::with_difficulty(difficulty, 2).validate().is_ok());
+        }
+    }
+
+    #[test]
+    fn set_difficulty_custom() {
+        let mut setup = GameSetup::with_difficulty(Difficulty::Intermediate, 2);
+        setup.change_mines(1);
+        assert_eq!(setup.difficulty, Difficulty::Custom);
+        setup.change_difficulty(1);
+        assert_eq!(setup.difficulty, Difficulty::Beginner);
+        assert_eq!(setup.width, 9);
+    }
+
+    // TEST validate
+    #[test]
+    fn validate_mines() {
+        let mut setup = GameSetup::new(5, 5, 24, 2);
+        assert!(setup.validate().is_ok());
+        setup.mines = 25;
+        assert!(setup.validate().is_err());
+        setup.mines = 0;
+        assert!(setup.validate().is_err());
+    }
+
+    #[test]
+    fn validate_limits() {
+        let setup = GameSetup {
+            width: 31,
+            ..GameSetup::default()
+        };
+        assert!(setup.validate().is_err());
+        let setup = GameSetup {
+            players: 5,
+            ..GameSetup::default()
+        };
+        assert!(setup.validate().is_err());
+        let setup = GameSetup {
+            best_of: 4,
+            ..GameSetup::default()
+        };
+        assert!(setup.validate().is_err());
+    }
+
+    // TEST from_args
+    #[test]
+    fn from_args_none() {
+        assert_eq!(GameSetup::from_args(&[]).unwrap(), None);
+    }
+
+    #[test]
+    fn from_args_difficulty() {
+        let setup = GameSetup::from_args(&args("--difficulty Expert -p 2"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(setup.difficulty, Difficulty::Expert);
+        assert_eq!(setup.players, 2);
+
+        let setup = GameSetup::from_args(&args("-d beginner --mines 12"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(setup.difficulty, Difficulty::Custom);
+        assert_eq!((setup.width, setup.mines), (9, 12));
+    }
+
+    #[test]
+    fn from_args_invalid() {
+        assert!(GameSetup::from_args(&args("--difficulty nightmare")).is_err());
+        assert!(GameSetup::from_args(&args("--width")).is_err());
+        assert!(GameSetup::from_args(&args("--width ten")).is_err());
+        assert!(GameSetup::from_args(&args("-w 5 -H 5 -m 25")).is_err());
+        // -h is left for help
+        assert!(GameSetup::from_args(&args("-h 12")).is_err());
+        assert!(GameSetup::from_args(&args("--colour red")).is_err());
+    }
+
+    // TEST change_life_rule
+    #[test]
+    fn change_life_rule0() {
+        let mut setup = GameSetup::default();
+        assert_eq!(setup.life_rule, LifeRule::Lives(1));
+        setup.change_life_rule(1);
+        assert_eq!(setup.life_rule, LifeRule::Penalty(5));
+        setup.change_life_rule(-2);
+        assert_eq!(setup.life_rule, LifeRule::Shield(10));
+        assert_eq!(setup.new_game().rule, LifeRule::Shield(10));
+    }
+
+    // TEST change_life_amount
+    #[test]
+    fn change_life_amount0() {
+        let mut setup = GameSetup::default();
+        setup.change_life_amount(2);
+        assert_eq!(setup.life_rule, LifeRule::Lives(3));
+        setup.change_life_amount(-10);
+        assert_eq!(setup.life_rule, LifeRule::Lives(1));
+        setup.change_life_amount(100);
+        assert_eq!(setup.life_rule, LifeRule::Lives(9));
+    }
+
+    #[test]
+    fn from_args_life_rule() {
+        let setup = GameSetup::from_args(&args("--lives 3")).unwrap().unwrap();
+        assert_eq!(setup.life_rule, LifeRule::Lives(3));
+        let setup = GameSetup::from_args(&args("--penalty 4")).unwrap().unwrap();
+        assert_eq!(setup.life_rule, LifeRule::Penalty(4));
+        assert!(GameSetup::from_args(&args("--shield 0")).is_err());
+        assert!(GameSetup::from_args(&args("--lives 0")).is_err());
+    }
+
+    // TEST change_teams
+    #[test]
+    fn change_teams0() {
+        let mut setup = GameSetup::new(10, 10, 10, 4);
+        assert_eq!(setup.teams, 0);
+        setup.change_teams(1);
+        assert_eq!(setup.teams, 2);
+        setup.change_teams(1);
+        assert_eq!(setup.teams, 3);
+        setup.change_teams(1);
+        assert_eq!(setup.teams, 0);
+        setup.change_teams(-1);
+        assert_eq!(setup.teams, 3);
+        setup.change_players(-1);
+        assert_eq!(setup.teams, 2);
+        setup.change_players(-1);
+        assert_eq!(setup.teams, 0);
+    }
+
+    #[test]
+    fn build_teams() {
+        let mut setup = GameSetup::new(10, 10, 10, 4);
+        setup.teams = 2;
+        let game = setup.build(&[0, 1, 2, 3], 1);
+        assert!(game.is_team_game());
+        assert_eq!(game.players[2].team, 0);
+        assert_eq!(game.players[2].member, 1);
+        assert_eq!(game.players[3].team, 1);
+        assert_ne!(game.players[0].color, game.players[2].color);
+    }
+
+    #[test]
+    fn from_args_teams() {
+        let setup = GameSetup::from_args(&args("-p 4 --teams 2"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(setup.teams, 2);
+        assert!(GameSetup::from_args(&args("-p 2 --teams 2")).is_err());
+    }
+
+    // TEST change_turn_policy
+    #[test]
+    fn change_turn_policy0() {
+        let mut setup = GameSetup::default();
+        assert_eq!(setup.turn_policy, TurnPolicy::OnePerAction);
+        setup.change_turn_policy(-1);
+        assert_eq!(setup.turn_policy, TurnPolicy::Simultaneous);
+        assert_eq!(setup.new_game().policy, TurnPolicy::Simultaneous);
+    }
+
+    #[test]
+    fn from_args_turns() {
+        let setup = GameSetup::from_args(&args("--turns opening"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(setup.turn_policy, TurnPolicy::ExtraOnOpening);
+        assert!(GameSetup::from_args(&args("--turns never")).is_err());
+    }
+
+    // TEST change_players
+    #[test]
+    fn change_players0() {
+        let mut setup = GameSetup::default();
+        setup.change_players(10);
+        assert_eq!(setup.players, 4);
+        setup.change_players(-10);
+        assert_eq!(setup.players, 1);
+    }
+
+    // TEST change_best_of
+    #[test]
+    fn change_best_of_odd() {
+        let mut setup = GameSetup::default();
+        assert_eq!(setup.best_of, 1);
+        setup.change_best_of(1);
+        assert_eq!(setup.best_of, 3);
+        setup.change_best_of(10);
+        assert_eq!(setup.best_of, 9);
+        setup.change_best_of(-1);
+        assert_eq!(setup.best_of, 7);
+        setup.change_best_of(-10);
+        assert_eq!(setup.best_of, 1);
+    }
+
+    // TEST build
+    #[test]
+    fn build_order_and_seed() {
+        let game = GameSetup::new(7, 6, 10, 3).build(&[2, 0, 1], 42);
+        assert_eq!(game.players[0].slot, 2);
+        assert_eq!(game.players[0].name, "Player 3");
+        assert_eq!(game.grid.seed(), 42);
+    }
+
+    // TEST new_game
+    #[test]
+    fn new_game0() {
+        let game = GameSetup::new(7, 6, 10, 2).new_game();
+        assert_eq!(game.grid.size(), (7, 6));
+        assert_eq!(game.grid.mine_number(), 10);
+        assert_eq!(game.players.len(), 2);
+        assert_eq!(game.players[1].name, "Player 2");
+        assert!(game.is_running());
+    }
+}
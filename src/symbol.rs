@@ -0,0 +1,128 @@
+use ggez::graphics::Color;
+use ggez::graphics::DrawMode;
+use ggez::graphics::MeshBuilder;
+use ggez::GameResult;
+use glam::Vec2;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Symbol {
+    Circle,
+    Square,
+    Triangle,
+    Diamond,
+    Cross,
+    Ring,
+}
+
+impl Symbol {
+    pub const ALL: [Symbol; 6] = [
+        Symbol::Circle,
+        Symbol::Square,
+        Symbol::Triangle,
+        Symbol::Diamond,
+        Symbol::Cross,
+        Symbol::Ring,
+    ];
+
+    pub fn for_slot(slot: usize) -> Self {
+        Self::ALL[slot % Self::ALL.len()]
+    }
+
+    pub fn index(self) -> usize {
+        Self::ALL.iter().position(|s| *s == self).unwrap_or(0)
+    }
+
+    pub fn build(
+        self,
+        mb: &mut MeshBuilder,
+        center: Vec2,
+        radius: f32,
+        color: Color,
+    ) -> GameResult {
+        match self {
+            Symbol::Circle => {
+                mb.circle(DrawMode::fill(), center, radius, 0.1, color)?;
+            }
+            Symbol::Square => {
+                let r = radius * 0.8;
+                mb.polygon(
+                    DrawMode::fill(),
+                    &[
+                        center + Vec2::new(-r, -r),
+                        center + Vec2::new(r, -r),
+                        center + Vec2::new(r, r),
+                        center + Vec2::new(-r, r),
+                    ],
+                    color,
+                )?;
+            }
+            Symbol::Triangle => {
+                mb.polygon(
+                    DrawMode::fill(),
+                    &[
+                        center + Vec2::new(0.0, -radius),
+                        center + Vec2::new(radius, radius * 0.8),
+                        center + Vec2::new(-radius, radius * 0.8),
+                    ],
+                    color,
+                )?;
+            }
+            Symbol::Diamond => {
+                mb.polygon(
+                    DrawMode::fill(),
+                    &[
+                        center + Vec2::new(0.0, -radius),
+                        center + Vec2::new(radius, 0.0),
+                        center + Vec2::new(0.0, radius),
+                        center + Vec2::new(-radius, 0.0),
+                    ],
+                    color,
+                )?;
+            }
+            Symbol::Cross => {
+                let r = radius * 0.8;
+                mb.line(
+                    &[center + Vec2::new(-r, -r), center + Vec2::new(r, r)],
+                    radius * 0.5,
+                    color,
+                )?;
+                mb.line(
+                    &[center + Vec2::new(r, -r), center + Vec2::new(-r, r)],
+                    radius * 0.5,
+                    color,
+                )?;
+            }
+            Symbol::Ring => {
+                mb.circle(
+                    DrawMode::stroke(radius * 0.4),
+                    center,
+                    radius * 0.8,
+                    0.1,
+                    color,
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Symbol;
+
+    // TEST for_slot
+    #[test]
+    fn for_slot_distinct() {
+        for i in 0..Symbol::ALL.len() {
+            for j in 0..i {
+                assert_ne!(Symbol::for_slot(i), Symbol::for_slot(j));
+            }
+        }
+    }
+
+    #[test]
+    fn for_slot_wraps() {
+        assert_eq!(Symbol::for_slot(6), Symbol::for_slot(0));
+        assert_eq!(Symbol::for_slot(8).index(), 2);
+    }
+}
@@ -0,0 +1,495 @@
+use crate::Player;
+use crate::Rgba;
+use ab_glyph::FontArc;
+use ggez::filesystem;
+use ggez::graphics::Color;
+use ggez::graphics::Font;
+use ggez::Context;
+use ggez::GameError;
+use ggez::GameResult;
+use serde::Deserialize;
+use serde::Serialize;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+#[derive(Clone, Default)]
+pub struct ThemeFonts {
+    pub number: Font,
+    pub text: Font,
+    // Outlines of a number font loaded from a file; ggez keeps its default font to itself
+    pub number_outlines: Option<FontArc>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Theme {
+    pub name: String,
+    #[serde(with = "hex_color")]
+    pub background: Color,
+    #[serde(with = "hex_color")]
+    pub covered: Color,
+    #[serde(with = "hex_color")]
+    pub preview: Color,
+    #[serde(with = "hex_color")]
+    pub border: Color,
+    #[serde(with = "hex_color")]
+    pub mine: Color,
+    #[serde(with = "hex_color")]
+    pub flag: Color,
+    #[serde(with = "hex_color")]
+    pub flag_glyph: Color,
+    #[serde(with = "hex_color")]
+    pub wrong_flag: Color,
+    #[serde(with = "hex_color")]
+    pub mine_glyph: Color,
+    #[serde(with = "hex_color")]
+    pub cursor: Color,
+    #[serde(with = "hex_color")]
+    pub hover: Color,
+    #[serde(with = "hex_colors")]
+    pub numbers: Vec<Color>,
+    pub satisfied_alpha: f32,
+    #[serde(with = "hex_colors")]
+    pub players: Vec<Color>,
+    pub symbols: bool,
+    #[serde(with = "hex_color")]
+    pub symbol: Color,
+    #[serde(with = "hex_color")]
+    pub text: Color,
+    #[serde(with = "hex_color")]
+    pub game_over: Color,
+    #[serde(with = "hex_color")]
+    pub panel_cover: Color,
+    #[serde(with = "hex_color")]
+    pub panel_dead: Color,
+    // Text drawn on panel_cover, like chat and the move feed
+    #[serde(with = "hex_color")]
+    pub panel_text: Color,
+    pub number_font: Option<String>,
+    pub number_scale: f32,
+    pub text_font: Option<String>,
+    pub text_size: f32,
+    pub title_size: f32,
+    #[serde(skip)]
+    pub fonts: ThemeFonts,
+    #[serde(skip)]
+    pub source: Option<PathBuf>,
+    #[serde(skip)]
+    modified: Option<SystemTime>,
+}
+
+impl Theme {
+    pub fn classic() -> Self {
+        Self {
+            name: "Classic".to_string(),
+            background: Color::new(0.42, 0.42, 0.42, 1.0),
+            covered: Color::new(0.8, 0.8, 0.8, 1.0),
+            preview: Color::new(0.95, 0.95, 0.95, 1.0),
+            border: Color::WHITE,
+            mine: Color::BLACK,
+            flag: Color::RED,
+            flag_glyph: Color::WHITE,
+            wrong_flag: Color::BLACK,
+            mine_glyph: Color::WHITE,
+            cursor: Color::MAGENTA,
+            hover: Color::WHITE,
+            numbers: vec![Color::BLACK],
+            satisfied_alpha: 0.4,
+            players: vec![],
+            symbols: false,
+            symbol: Color::new(0.0, 0.0, 0.0, 0.6),
+            text: Color::BLACK,
+            game_over: Color::RED,
+            panel_cover: Color::new(0.0, 0.0, 0.0, 0.9),
+            panel_dead: Color::RED,
+            panel_text: Color::new(0.9, 0.9, 0.9, 1.0),
+            number_font: None,
+            number_scale: 0.6,
+            text_font: None,
+            text_size: 30.0,
+            title_size: 50.0,
+            fonts: ThemeFonts::default(),
+            source: None,
+            modified: None,
+        }
+    }
+
+    // Okabe-Ito palette, distinguishable under the common colour vision deficiencies
+    pub fn colorblind() -> Self {
+        Self {
+            name: "Colour-blind".to_string(),
+            covered: Color::new(0.75, 0.75, 0.75, 1.0),
+            flag: Color::new(0.84, 0.37, 0.0, 1.0),
+            flag_glyph: Color::BLACK,
+            cursor: Color::new(0.0, 0.45, 0.7, 1.0),
+            numbers: vec![
+                Color::BLACK,
+                Color::new(0.0, 0.25, 0.55, 1.0),
+                Color::new(0.0, 0.4, 0.25, 1.0),
+                Color::new(0.55, 0.15, 0.0, 1.0),
+                Color::new(0.35, 0.1, 0.45, 1.0),
+                Color::new(0.4, 0.25, 0.0, 1.0),
+                Color::BLACK,
+            ],
+            players: vec![
+                Color::new(0.9, 0.62, 0.0, 1.0),
+                Color::new(0.34, 0.71, 0.91, 1.0),
+                Color::new(0.94, 0.89, 0.26, 1.0),
+                Color::new(0.8, 0.47, 0.65, 1.0),
+                Color::new(0.0, 0.62, 0.45, 1.0),
+                Color::new(0.6, 0.6, 0.9, 1.0),
+            ],
+            symbols: true,
+            game_over: Color::new(0.84, 0.37, 0.0, 1.0),
+            ..Self::classic()
+        }
+    }
+
+    pub fn high_contrast() -> Self {
+        Self {
+            name: "High contrast".to_string(),
+            background: Color::BLACK,
+            covered: Color::WHITE,
+            preview: Color::new(0.7, 0.7, 0.7, 1.0),
+            border: Color::BLACK,
+            mine: Color::new(0.3, 0.3, 0.3, 1.0),
+            flag: Color::new(1.0, 0.85, 0.0, 1.0),
+            flag_glyph: Color::BLACK,
+            cursor: Color::new(0.0, 0.9, 1.0, 1.0),
+            hover: Color::new(1.0, 0.85, 0.0, 1.0),
+            satisfied_alpha: 0.6,
+            players: vec![
+                Color::new(1.0, 0.6, 0.0, 1.0),
+                Color::new(0.0, 0.9, 1.0, 1.0),
+                Color::new(1.0, 0.4, 1.0, 1.0),
+                Color::new(0.6, 1.0, 0.2, 1.0),
+                Color::new(1.0, 1.0, 0.4, 1.0),
+                Color::new(0.7, 0.7, 1.0, 1.0),
+            ],
+            symbol: Color::BLACK,
+            text: Color::BLACK,
+            game_over: Color::new(1.0, 0.85, 0.0, 1.0),
+            ..Self::colorblind()
+        }
+    }
+
+    pub fn presets() -> Vec<Self> {
+        vec![Self::classic(), Self::colorblind(), Self::high_contrast()]
+    }
+
+    pub fn from_toml(src: &str) -> GameResult<Self> {
+        toml::from_str(src).map_err(|e| GameError::ConfigError(e.to_string()))
+    }
+
+    pub fn to_toml(&self) -> GameResult<String> {
+        toml::to_string(self).map_err(|e| GameError::ConfigError(e.to_string()))
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> GameResult<Self> {
+        let path = path.as_ref();
+        let modified = fs::metadata(path).and_then(|m| m.modified()).ok();
+        let mut theme = Self::from_toml(&fs::read_to_string(path)?)?;
+        theme.source = Some(path.to_path_buf());
+        theme.modified = modified;
+        Ok(theme)
+    }
+
+    pub fn load_dir<P: AsRef<Path>>(dir: P) -> Vec<GameResult<Self>> {
+        let mut paths = match fs::read_dir(dir) {
+            Ok(entries) => entries
+                .filter_map(|e| e.ok().map(|e| e.path()))
+                .filter(|p| p.extension().is_some_and(|ext| ext == "toml"))
+                .collect(),
+            Err(_) => vec![],
+        };
+        paths.sort();
+        paths.into_iter().map(Self::load).collect()
+    }
+
+    // Font paths are relative to the resources directory, e.g. "/fonts/DejaVuSans.ttf"
+    pub fn load_fonts(&mut self, ctx: &mut Context) -> GameResult {
+        let load = |ctx: &mut Context, path: &Option<String>| match path {
+            Some(path) => Font::new(ctx, path),
+            None => Ok(Font::default()),
+        };
+        let (number, number_outlines) = match &self.number_font {
+            Some(path) => {
+                let (font, outlines) = load_outlined(ctx, path)?;
+                (font, Some(outlines))
+            }
+            None => (Font::default(), None),
+        };
+        self.fonts = ThemeFonts {
+            number,
+            text: load(ctx, &self.text_font)?,
+            number_outlines,
+        };
+        Ok(())
+    }
+
+    // Returns true when the theme was reloaded from its source file
+    pub fn reload_if_changed(&mut self) -> GameResult<bool> {
+        let path = match &self.source {
+            Some(path) => path.clone(),
+            None => return Ok(false),
+        };
+        let modified = fs::metadata(&path).and_then(|m| m.modified()).ok();
+        if modified == self.modified {
+            return Ok(false);
+        }
+        self.modified = modified;
+        *self = Self::load(&path)?;
+        Ok(true)
+    }
+
+    // Team members get shades of their team's colour
+    pub fn player_color(&self, player: &Player) -> Color {
+        if self.players.is_empty() {
+            player.color.into()
+        } else {
+            Rgba::from(self.players[player.team % self.players.len()])
+                .shade(player.member)
+                .into()
+        }
+    }
+
+    pub fn number_color(&self, num: usize, satisfied: bool) -> Color {
+        let mut color = match self.numbers.len() {
+            0 => self.text,
+            len => self.numbers[num.min(len - 1)],
+        };
+        if satisfied {
+            color.a *= self.satisfied_alpha;
+        }
+        color
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::classic()
+    }
+}
+
+// The outlines are parsed first, ggez panics on bytes that are not a font
+fn load_outlined(ctx: &mut Context, path: &str) -> GameResult<(Font, FontArc)> {
+    let mut bytes = vec![];
+    filesystem::open(ctx, path)?.read_to_end(&mut bytes)?;
+    let outlines =
+        FontArc::try_from_vec(bytes.clone()).map_err(|e| GameError::FontError(e.to_string()))?;
+    Ok((Font::new_glyph_font_bytes(ctx, &bytes)?, outlines))
+}
+
+mod hex_color {
+    use ggez::graphics::Color;
+    use serde::de::Error;
+    use serde::Deserialize;
+    use serde::Deserializer;
+    use serde::Serializer;
+
+    pub fn to_hex(color: Color) -> String {
+        let (r, g, b, a) = color.to_rgba();
+        if a == 255 {
+            format!("#{:02x}{:02x}{:02x}", r, g, b)
+        } else {
+            format!("#{:02x}{:02x}{:02x}{:02x}", r, g, b, a)
+        }
+    }
+
+    pub fn from_hex(s: &str) -> Option<Color> {
+        let hex = s.strip_prefix('#')?;
+        if !(hex.len() == 6 || hex.len() == 8) || !hex.is_ascii() {
+            return None;
+        }
+        let mut channels = [255; 4];
+        for (i, channel) in channels.iter_mut().enumerate().take(hex.len() / 2) {
+            *channel = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).ok()?;
+        }
+        Some(Color::from_rgba(
+            channels[0],
+            channels[1],
+            channels[2],
+            channels[3],
+        ))
+    }
+
+    pub fn serialize<S: Serializer>(color: &Color, s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_str(&to_hex(*color))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Color, D::Error> {
+        let s = String::deserialize(d)?;
+        from_hex(&s).ok_or_else(|| D::Error::custom(format!("invalid colour `{}`", s)))
+    }
+}
+
+mod hex_colors {
+    use super::hex_color;
+    use ggez::graphics::Color;
+    use serde::de::Error;
+    use serde::Deserialize;
+    use serde::Deserializer;
+    use serde::Serializer;
+
+    pub fn serialize<S: Serializer>(colors: &[Color], s: S) -> Result<S::Ok, S::Error> {
+        s.collect_seq(colors.iter().map(|c| hex_color::to_hex(*c)))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Vec<Color>, D::Error> {
+        Vec::<String>::deserialize(d)?
+            .iter()
+            .map(|s| {
+                hex_color::from_hex(s)
+                    .ok_or_else(|| D::Error::custom(format!("invalid colour `{}`", s)))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Player;
+    use crate::Rgba;
+    use crate::Theme;
+    use ggez::graphics::Color;
+
+    // TEST player_color
+    #[test]
+    fn player_color_own() {
+        let player = Player::new(1, Rgba::BLUE, "Player 2".to_string());
+        assert_eq!(Theme::classic().player_color(&player), Color::BLUE);
+    }
+
+    #[test]
+    fn player_color_palette() {
+        let theme = Theme::colorblind();
+        let player = Player::new(1, Rgba::BLUE, "Player 2".to_string());
+        assert_eq!(theme.player_color(&player), theme.players[1]);
+        let player = Player::new(7, Rgba::BLUE, "Player 8".to_string());
+        assert_eq!(theme.player_color(&player), theme.players[1]);
+    }
+
+    #[test]
+    fn player_color_team() {
+        let theme = Theme::colorblind();
+        let player = Player::new(2, Rgba::BLUE, "Player 3".to_string()).with_team(0, 1);
+        let shade = Rgba::from(theme.players[0]).shade(1);
+        assert_eq!(theme.player_color(&player), shade.into());
+        assert_ne!(theme.player_color(&player), theme.players[0]);
+    }
+
+    #[test]
+    fn player_color_distinct() {
+        for theme in Theme::presets().iter().skip(1) {
+            for i in 0..theme.players.len() {
+                for j in 0..i {
+                    assert_ne!(theme.players[i], theme.players[j]);
+                }
+            }
+        }
+    }
+
+    // TEST number_color
+    #[test]
+    fn number_color_distinct() {
+        let theme = Theme::colorblind();
+        for i in 1..=5 {
+            for j in 1..i {
+                assert_ne!(theme.number_color(i, false), theme.number_color(j, false));
+            }
+        }
+    }
+
+    #[test]
+    fn number_color_empty() {
+        let mut theme = Theme::classic();
+        theme.numbers.clear();
+        assert_eq!(theme.number_color(3, false), theme.text);
+    }
+
+    #[test]
+    fn number_color_clamped() {
+        let theme = Theme::classic();
+        assert_eq!(theme.number_color(6, false), Color::BLACK);
+        assert!(theme.number_color(6, true).a < 1.0);
+    }
+
+    // TEST presets
+    #[test]
+    fn presets_panel_text_readable() {
+        let luma = |c: Color| 0.3 * c.r + 0.59 * c.g + 0.11 * c.b;
+        for theme in Theme::presets() {
+            let contrast = luma(theme.panel_text) - luma(theme.panel_cover);
+            assert!(contrast > 0.5, "{}", theme.name);
+        }
+    }
+
+    // TEST from_toml
+    #[test]
+    fn from_toml_partial() {
+        let theme = Theme::from_toml(
+            r##"
+            name = "Dark"
+            background = "#101010"
+            flag = "#ff000080"
+            numbers = ["#000000", "#1122ff"]
+            number_scale = 0.5
+            text_font = "/fonts/Mono.ttf"
+            "##,
+        )
+        .unwrap();
+        assert_eq!(theme.name, "Dark");
+        assert_eq!(theme.background, Color::from_rgb(16, 16, 16));
+        assert_eq!(theme.flag, Color::from_rgba(255, 0, 0, 128));
+        assert_eq!(
+            theme.number_color(5, false),
+            Color::from_rgb(0x11, 0x22, 0xff)
+        );
+        assert_eq!(theme.number_scale, 0.5);
+        assert_eq!(theme.text_font.as_deref(), Some("/fonts/Mono.ttf"));
+        assert_eq!(theme.number_font, None);
+        assert_eq!(theme.covered, Theme::classic().covered);
+    }
+
+    #[test]
+    fn from_toml_invalid_colour() {
+        assert!(Theme::from_toml("background = \"#12345\"").is_err());
+        assert!(Theme::from_toml("background = \"red\"").is_err());
+    }
+
+    #[test]
+    fn to_toml_round_trip() {
+        let src = Theme::colorblind().to_toml().unwrap();
+        let parsed = Theme::from_toml(&src).unwrap();
+        assert_eq!(parsed.name, "Colour-blind");
+        assert_eq!(parsed.players.len(), 6);
+        assert_eq!(parsed.to_toml().unwrap(), src);
+    }
+
+    // TEST reload_if_changed
+    #[test]
+    fn reload_if_changed0() {
+        let path =
+            std::env::temp_dir().join(format!("hexsweeper-theme-{}.toml", std::process::id()));
+        std::fs::write(&path, "name = \"One\"").unwrap();
+        let mut theme = Theme::load(&path).unwrap();
+        assert_eq!(theme.name, "One");
+        assert!(!theme.reload_if_changed().unwrap());
+
+        // Coarse file system clocks could give the rewrite the same mtime
+        let modified = std::fs::metadata(&path).unwrap().modified().unwrap();
+        std::fs::write(&path, "name = \"Two\"").unwrap();
+        std::fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(modified + std::time::Duration::from_secs(1))
+            .unwrap();
+        assert!(theme.reload_if_changed().unwrap());
+        assert_eq!(theme.name, "Two");
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,252 @@
+use crate::HexTile;
+use crate::Symbol;
+use ab_glyph::Font as _;
+use ab_glyph::FontArc;
+use ab_glyph::ScaleFont;
+use ggez::graphics;
+use ggez::graphics::Color;
+use ggez::graphics::DrawMode;
+use ggez::graphics::DrawParam;
+use ggez::graphics::Font;
+use ggez::graphics::Mesh;
+use ggez::graphics::MeshBatch;
+use ggez::graphics::MeshBuilder;
+use ggez::graphics::PxScale;
+use ggez::graphics::Text;
+use ggez::graphics::TextFragment;
+use ggez::Context;
+use ggez::GameResult;
+use glam::Vec2;
+use std::f32::consts::PI;
+
+const MAX_NUMBER: usize = 6;
+
+pub struct TileBatch {
+    fill: MeshBatch,
+    border: MeshBatch,
+    highlight: MeshBatch,
+    flags: MeshBatch,
+    mines: MeshBatch,
+    crosses: MeshBatch,
+    bursts: MeshBatch,
+    symbols: Vec<MeshBatch>,
+    symbol_offset: Vec2,
+    size: f32,
+    numbers: Vec<(Text, Vec2)>,
+    number_style: Option<(Font, f32)>,
+    queued_numbers: Vec<(usize, Vec2, Color)>,
+}
+
+impl TileBatch {
+    pub fn new(ctx: &mut Context, size: f32) -> GameResult<Self> {
+        let points = HexTile::new(size, Vec2::new(0.0, 0.0)).get_points();
+        let fill = Mesh::new_polygon(ctx, DrawMode::fill(), &points, Color::WHITE)?;
+        let border = Mesh::new_polygon(ctx, DrawMode::stroke(2.0), &points, Color::WHITE)?;
+        let highlight = Mesh::new_polygon(ctx, DrawMode::stroke(6.0), &points, Color::WHITE)?;
+
+        let mut flag = MeshBuilder::new();
+        flag.line(
+            &[
+                Vec2::new(-size * 0.1, -size * 0.3),
+                Vec2::new(-size * 0.1, size * 0.3),
+            ],
+            size * 0.05,
+            Color::WHITE,
+        )?;
+        flag.polygon(
+            DrawMode::fill(),
+            &[
+                Vec2::new(-size * 0.1, -size * 0.3),
+                Vec2::new(size * 0.25, -size * 0.15),
+                Vec2::new(-size * 0.1, 0.0),
+            ],
+            Color::WHITE,
+        )?;
+        let flags = MeshBatch::new(flag.build(ctx)?)?;
+
+        let mut mine = MeshBuilder::new();
+        mine.circle(
+            DrawMode::fill(),
+            Vec2::new(0.0, 0.0),
+            size * 0.2,
+            0.1,
+            Color::WHITE,
+        )?;
+        for i in 0..4 {
+            let angle = i as f32 * PI / 4.0;
+            let dir = Vec2::new(angle.cos(), angle.sin()) * size * 0.3;
+            mine.line(&[-dir, dir], size * 0.05, Color::WHITE)?;
+        }
+        let mines = MeshBatch::new(mine.build(ctx)?)?;
+
+        let mut cross = MeshBuilder::new();
+        let r = size * 0.3;
+        cross.line(
+            &[Vec2::new(-r, -r), Vec2::new(r, r)],
+            size * 0.08,
+            Color::WHITE,
+        )?;
+        cross.line(
+            &[Vec2::new(r, -r), Vec2::new(-r, r)],
+            size * 0.08,
+            Color::WHITE,
+        )?;
+        let crosses = MeshBatch::new(cross.build(ctx)?)?;
+        let burst = Mesh::new_circle(
+            ctx,
+            DrawMode::stroke(size * 0.1),
+            Vec2::new(0.0, 0.0),
+            size / 2.0,
+            0.5,
+            Color::WHITE,
+        )?;
+
+        let mut symbols = vec![];
+        for symbol in Symbol::ALL {
+            let mut mb = MeshBuilder::new();
+            symbol.build(&mut mb, Vec2::new(0.0, 0.0), size * 0.1, Color::WHITE)?;
+            symbols.push(MeshBatch::new(mb.build(ctx)?)?);
+        }
+
+        Ok(Self {
+            fill: MeshBatch::new(fill)?,
+            border: MeshBatch::new(border)?,
+            highlight: MeshBatch::new(highlight)?,
+            flags,
+            mines,
+            crosses,
+            bursts: MeshBatch::new(burst)?,
+            symbols,
+            symbol_offset: Vec2::new(0.0, size * 0.3),
+            size,
+            numbers: vec![],
+            number_style: None,
+            queued_numbers: vec![],
+        })
+    }
+
+    // Numbers are scaled relative to the tile size and centred on their drawn shape.
+    // The layout box runs from the ascender down to the descender, so centring it
+    // draws digits high: at 100px DejaVu Sans gives a box 100px tall with its middle
+    // at 50, but the digits run from about 15 to 81, 2px higher than the box.
+    // Without outlines, as for ggez's built-in font, the layout box is all there is.
+    pub fn set_number_style(
+        &mut self,
+        ctx: &Context,
+        font: Font,
+        outlines: Option<&FontArc>,
+        scale: f32,
+    ) {
+        if self.number_style == Some((font, scale)) {
+            return;
+        }
+        self.number_style = Some((font, scale));
+        let px = self.size * scale;
+        self.numbers = (0..=MAX_NUMBER)
+            .map(|num| {
+                let text = Text::new(TextFragment {
+                    text: num.to_string(),
+                    color: Some(Color::BLACK),
+                    font: Some(font),
+                    scale: Some(PxScale::from(px)),
+                });
+                let digit = char::from_digit(num as u32, 10).unwrap_or('0');
+                let offset = outlines
+                    .and_then(|outlines| glyph_centre(outlines, px, digit))
+                    .unwrap_or_else(|| {
+                        let dims = text.dimensions(ctx);
+                        Vec2::new(dims.w / 2.0, dims.h / 2.0)
+                    });
+                (text, offset)
+            })
+            .collect();
+    }
+
+    pub fn add_tile(&mut self, pos: Vec2, scale: Vec2, color: Color, border: Color) {
+        self.fill
+            .add(DrawParam::new().dest(pos).scale(scale).color(color));
+        self.border
+            .add(DrawParam::new().dest(pos).scale(scale).color(border));
+    }
+
+    // Expanding, fading ring; progress runs from 0 to 1
+    pub fn add_burst(&mut self, pos: Vec2, progress: f32, color: Color) {
+        let mut color = color;
+        color.a *= 1.0 - progress;
+        self.bursts.add(
+            DrawParam::new()
+                .dest(pos)
+                .scale(Vec2::splat(0.5 + 2.0 * progress))
+                .color(color),
+        );
+    }
+
+    pub fn add_flag(&mut self, pos: Vec2, color: Color) {
+        self.flags.add(DrawParam::new().dest(pos).color(color));
+    }
+
+    pub fn add_mine(&mut self, pos: Vec2, color: Color) {
+        self.mines.add(DrawParam::new().dest(pos).color(color));
+    }
+
+    pub fn add_cross(&mut self, pos: Vec2, color: Color) {
+        self.crosses.add(DrawParam::new().dest(pos).color(color));
+    }
+
+    pub fn add_symbol(&mut self, symbol: Symbol, pos: Vec2, color: Color) {
+        self.symbols[symbol.index()]
+            .add(DrawParam::new().dest(pos + self.symbol_offset).color(color));
+    }
+
+    pub fn add_highlight(&mut self, pos: Vec2, color: Color) {
+        self.highlight.add(DrawParam::new().dest(pos).color(color));
+    }
+
+    pub fn add_number(&mut self, pos: Vec2, num: usize, color: Color) {
+        self.queued_numbers.push((num.min(MAX_NUMBER), pos, color));
+    }
+
+    pub fn draw(&mut self, ctx: &mut Context, param: DrawParam) -> GameResult {
+        self.fill.draw(ctx, param)?;
+        self.border.draw(ctx, param)?;
+        self.flags.draw(ctx, param)?;
+        self.mines.draw(ctx, param)?;
+        self.crosses.draw(ctx, param)?;
+        for symbol in &mut self.symbols {
+            symbol.draw(ctx, param)?;
+        }
+        self.highlight.draw(ctx, param)?;
+        self.bursts.draw(ctx, param)?;
+        for (num, pos, color) in &self.queued_numbers {
+            let (text, offset) = &self.numbers[*num];
+            graphics::queue_text(ctx, text, *pos - *offset, Some(*color));
+        }
+        graphics::draw_queued_text(ctx, param, None, graphics::FilterMode::Linear)?;
+
+        self.fill.clear();
+        self.border.clear();
+        self.highlight.clear();
+        self.flags.clear();
+        self.mines.clear();
+        self.crosses.clear();
+        self.bursts.clear();
+        for symbol in &mut self.symbols {
+            symbol.clear();
+        }
+        self.queued_numbers.clear();
+        Ok(())
+    }
+}
+
+// Middle of a glyph's outline, from the top left of where ggez lays it out: the
+// first line's baseline sits one ascent down
+fn glyph_centre(font: &FontArc, px: f32, c: char) -> Option<Vec2> {
+    let font = font.as_scaled(PxScale::from(px));
+    let mut glyph = font.scaled_glyph(c);
+    glyph.position = ab_glyph::point(0.0, font.ascent());
+    let bounds = font.outline_glyph(glyph)?.px_bounds();
+    Some(Vec2::new(
+        (bounds.min.x + bounds.max.x) / 2.0,
+        (bounds.min.y + bounds.max.y) / 2.0,
+    ))
+}